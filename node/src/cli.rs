@@ -45,6 +45,11 @@ pub struct RunCmd {
     // #[clap(long, parse(from_os_str))]
     #[clap(long, value_parser)]
     pub config_file_path: Option<std::path::PathBuf>,
+
+    /// Query the quantum portal signer balance on every configured network at startup
+    /// and warn about unfunded accounts
+    #[clap(long)]
+    pub qp_check_signer_balance: bool,
 }
 
 #[derive(Debug, clap::Parser)]
@@ -1,15 +1,18 @@
-use std::{fs::File, io::BufReader, path::Path};
+use std::{collections::BTreeMap, fmt, fs::File, io::BufReader, path::Path};
 
 use serde::{Deserialize, Serialize};
 
-use pallet_quantum_portal::qp_types::{EIP712Config, QpConfig, QpNetworkItem};
+use pallet_quantum_portal::{
+    chain_utils::ChainUtils,
+    qp_types::{EIP712Config, QpConfig, QpNetworkItem, QpNetworkType, QpRpcAuth, QpSchedule},
+};
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Config {
     pub networks: NetworkConfig,
 }
 
-#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct NetworkItem {
     /// The rpc url for this network
     #[serde(with = "serde_bytes")]
@@ -19,6 +22,74 @@ pub struct NetworkItem {
     pub ledger_manager: Vec<u8>,
     /// The ChainId for this network
     pub id: u64,
+    /// `native` for the EVM of this chain, which needs no url, or `http` for a remote network,
+    /// the default
+    #[serde(default, rename = "type", with = "serde_bytes")]
+    pub network_type: Vec<u8>,
+    /// Credentials of the rpc endpoint
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Blocks to wait before reading source blocks and trusting receipts. None by default
+    #[serde(default)]
+    pub confirmations: u64,
+}
+
+/// Only shows the scheme and host of the url, which may hold an api key
+impl fmt::Debug for NetworkItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NetworkItem")
+            .field("url", &ChainUtils::redact_url(&self.url))
+            .field(
+                "ledger_manager",
+                &String::from_utf8_lossy(&self.ledger_manager),
+            )
+            .field("id", &self.id)
+            .field("network_type", &String::from_utf8_lossy(&self.network_type))
+            .field("auth", &self.auth)
+            .field("confirmations", &self.confirmations)
+            .finish()
+    }
+}
+
+/// Credentials sent with every request to a network rpc endpoint
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AuthConfig {
+    /// Extra headers, e.g. `{"x-api-key": "..."}` or `{"Authorization": "Bearer ..."}`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Http basic auth
+    pub basic_auth: Option<BasicAuthConfig>,
+}
+
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct BasicAuthConfig {
+    pub user: String,
+    pub password: String,
+}
+
+/// Only shows the header names, never the secrets
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("basic_auth", &self.basic_auth.is_some())
+            .finish()
+    }
+}
+
+impl From<AuthConfig> for QpRpcAuth {
+    fn from(auth: AuthConfig) -> Self {
+        QpRpcAuth {
+            headers: auth
+                .headers
+                .into_iter()
+                .map(|(name, value)| (name.into_bytes(), value.into_bytes()))
+                .collect(),
+            basic_auth: auth
+                .basic_auth
+                .map(|b| (b.user.into_bytes(), b.password.into_bytes())),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -40,20 +111,37 @@ pub struct NetworkConfig {
     /// The role of this node
     #[serde(with = "serde_bytes")]
     pub role: Vec<u8>,
+    /// When the offchain worker runs, every block for every pair by default
+    #[serde(default)]
+    pub schedule: QpSchedule,
 }
 
 pub fn convert(network_config: NetworkConfig) -> QpConfig {
     let role_as_bytes: &[u8] = &network_config.role;
-    QpConfig {
-        network_vec: network_config
-            .network_vec
-            .into_iter()
-            .map(|network_item| QpNetworkItem {
+    let network_vec = network_config
+        .network_vec
+        .into_iter()
+        .filter_map(|network_item| {
+            // Only the pairs of a network with an unknown type are left out
+            let network_type = match QpNetworkType::try_from(network_item.network_type.as_slice()) {
+                Ok(network_type) => network_type,
+                Err(e) => {
+                    log::error!("Skipping quantum portal network {}: {}", network_item.id, e);
+                    return None;
+                }
+            };
+            Some(QpNetworkItem {
                 url: network_item.url,
                 ledger_manager: network_item.ledger_manager,
                 id: network_item.id,
+                network_type,
+                auth: network_item.auth.into(),
+                confirmations: network_item.confirmations,
             })
-            .collect(),
+        })
+        .collect();
+    QpConfig {
+        network_vec,
         pair_vec: network_config.pair_vec,
         signer_public_key: network_config.signer_public_key,
         eip_712_config: EIP712Config {
@@ -62,6 +150,7 @@ pub fn convert(network_config: NetworkConfig) -> QpConfig {
             verifying_address: network_config.authority_manager_contract_address,
         },
         role: role_as_bytes.into(),
+        schedule: network_config.schedule,
     }
 }
 
@@ -78,3 +167,26 @@ pub fn read_config_from_file<P: AsRef<Path>>(path: P) -> Result<Config, String>
         Err(err) => Err(err.to_string()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_item_debug_redacts_the_url() {
+        let item: NetworkItem = serde_json::from_str(
+            r#"{
+                "url": "https://user:pw@mainnet.example.io/v3/secret-path?key=secret-query",
+                "ledger_manager": "d36312d594852462d6760042e779164eb97301cd",
+                "id": 1,
+                "auth": { "headers": { "x-api-key": "secret-key" } }
+            }"#,
+        )
+        .unwrap();
+        let debug = format!("{:?}", item);
+        assert!(debug.contains("mainnet.example.io"), "{}", debug);
+        for secret in ["pw", "secret-path", "secret-query", "secret-key"] {
+            assert!(!debug.contains(secret), "{} in {}", secret, debug);
+        }
+    }
+}
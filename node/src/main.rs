@@ -7,7 +7,12 @@ mod service;
 mod cli;
 mod command;
 mod config;
+mod qp_health;
+mod qp_metrics;
+mod qp_rpc;
+mod remote_keystore;
 mod rpc;
+mod signer_check;
 
 fn main() -> sc_cli::Result<()> {
     command::run()
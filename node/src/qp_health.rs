@@ -0,0 +1,52 @@
+//! `qp_health` rpc, reports the state of the quantum portal offchain worker so monitoring can tell
+//! a misconfigured worker (e.g. missing signer key) from one that simply has nothing to do.
+
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use serde::{Deserialize, Serialize};
+use sp_core::offchain::OffchainStorage;
+
+use crate::qp_metrics::{read_snapshot, worker_status_name};
+
+/// Health of the quantum portal offchain worker
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QpHealth {
+    /// Outcome of the last run: unknown, running, noConfig, noRole or noSignerKey
+    pub status: String,
+    /// When the status was recorded, unix time in millis
+    pub status_timestamp: u64,
+    /// Whether the worker can currently do its job
+    pub healthy: bool,
+}
+
+#[rpc(server)]
+pub trait QpHealthApi {
+    /// Report the state of the quantum portal offchain worker
+    #[method(name = "qp_health")]
+    fn health(&self) -> RpcResult<QpHealth>;
+}
+
+pub struct QpHealthRpc<S> {
+    offchain_storage: Option<S>,
+}
+
+impl<S> QpHealthRpc<S> {
+    pub fn new(offchain_storage: Option<S>) -> Self {
+        Self { offchain_storage }
+    }
+}
+
+impl<S: OffchainStorage + 'static> QpHealthApiServer for QpHealthRpc<S> {
+    fn health(&self) -> RpcResult<QpHealth> {
+        let metrics = self
+            .offchain_storage
+            .as_ref()
+            .and_then(read_snapshot)
+            .unwrap_or_default();
+        Ok(QpHealth {
+            status: worker_status_name(metrics.status).to_string(),
+            status_timestamp: metrics.status_timestamp,
+            healthy: metrics.status == pallet_quantum_portal::qp_types::QpWorkerStatus::Running,
+        })
+    }
+}
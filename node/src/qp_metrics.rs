@@ -0,0 +1,396 @@
+//! Exposes the quantum portal offchain worker metrics on the node prometheus endpoint.
+//! The offchain worker records its counters in offchain storage (see `qp_metrics` in the pallet),
+//! this task periodically reads the snapshot and mirrors it into the prometheus registry.
+
+use std::{sync::Arc, time::Duration};
+
+use codec::Decode;
+use ferrum_primitives::OFFCHAIN_QP_METRICS_KEY;
+use ferrum_x_runtime::opaque::Block;
+use pallet_quantum_portal::qp_types::{QpMetrics, QpWorkerStatus};
+use prometheus_endpoint::{
+    register, Counter, CounterVec, GaugeVec, Opts, PrometheusError, Registry, U64,
+};
+use sc_client_api::Backend;
+use sp_core::offchain::{OffchainStorage, STORAGE_PREFIX};
+
+/// How often the offchain storage snapshot is scraped
+const SCRAPE_INTERVAL: Duration = Duration::from_secs(6);
+
+const PAIR_LABELS: &[&str] = &["remote_chain", "local_chain"];
+const NETWORK_LABELS: &[&str] = &["chain_id"];
+
+/// Prometheus view of the offchain worker metrics
+pub struct QpPrometheusMetrics {
+    mine_attempts: CounterVec<U64>,
+    mine_successes: CounterVec<U64>,
+    mine_transactions: CounterVec<U64>,
+    finalize_attempts: CounterVec<U64>,
+    finalize_successes: CounterVec<U64>,
+    finalize_transactions: CounterVec<U64>,
+    seconds_since_last_success: GaugeVec<U64>,
+    nonce_lag: GaugeVec<U64>,
+    rpc_requests: CounterVec<U64>,
+    rpc_errors: CounterVec<U64>,
+    rpc_latency_ms: CounterVec<U64>,
+    pending_tx_age_seconds: GaugeVec<U64>,
+    lock_contention: Counter<U64>,
+    worker_status: GaugeVec<U64>,
+}
+
+const WORKER_STATUSES: &[QpWorkerStatus] = &[
+    QpWorkerStatus::Unknown,
+    QpWorkerStatus::Running,
+    QpWorkerStatus::NoConfig,
+    QpWorkerStatus::NoRole,
+    QpWorkerStatus::NoSignerKey,
+];
+
+/// Label used for the worker status in metrics and the health rpc
+pub fn worker_status_name(status: QpWorkerStatus) -> &'static str {
+    match status {
+        QpWorkerStatus::Unknown => "unknown",
+        QpWorkerStatus::Running => "running",
+        QpWorkerStatus::NoConfig => "noConfig",
+        QpWorkerStatus::NoRole => "noRole",
+        QpWorkerStatus::NoSignerKey => "noSignerKey",
+    }
+}
+
+fn counter(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> Result<CounterVec<U64>, PrometheusError> {
+    register(CounterVec::new(Opts::new(name, help), labels)?, registry)
+}
+
+fn gauge(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> Result<GaugeVec<U64>, PrometheusError> {
+    register(GaugeVec::new(Opts::new(name, help), labels)?, registry)
+}
+
+impl QpPrometheusMetrics {
+    pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+        Ok(Self {
+            mine_attempts: counter(
+                registry,
+                "qp_mine_attempts_total",
+                "Number of mine steps attempted per pair",
+                PAIR_LABELS,
+            )?,
+            mine_successes: counter(
+                registry,
+                "qp_mine_successes_total",
+                "Number of mine steps that completed without error per pair",
+                PAIR_LABELS,
+            )?,
+            mine_transactions: counter(
+                registry,
+                "qp_mine_transactions_total",
+                "Number of mine transactions sent per pair",
+                PAIR_LABELS,
+            )?,
+            finalize_attempts: counter(
+                registry,
+                "qp_finalize_attempts_total",
+                "Number of finalize steps attempted per pair",
+                PAIR_LABELS,
+            )?,
+            finalize_successes: counter(
+                registry,
+                "qp_finalize_successes_total",
+                "Number of finalize steps that completed without error per pair",
+                PAIR_LABELS,
+            )?,
+            finalize_transactions: counter(
+                registry,
+                "qp_finalize_transactions_total",
+                "Number of finalize transactions sent per pair",
+                PAIR_LABELS,
+            )?,
+            seconds_since_last_success: gauge(
+                registry,
+                "qp_seconds_since_last_success",
+                "Seconds since the last successful mine or finalize step per pair, or since the \
+                 pair was first processed if none succeeded",
+                PAIR_LABELS,
+            )?,
+            nonce_lag: gauge(
+                registry,
+                "qp_nonce_lag",
+                "Number of source blocks not yet mined on the local chain per pair",
+                PAIR_LABELS,
+            )?,
+            rpc_requests: counter(
+                registry,
+                "qp_rpc_requests_total",
+                "Number of json rpc requests per network",
+                NETWORK_LABELS,
+            )?,
+            rpc_errors: counter(
+                registry,
+                "qp_rpc_errors_total",
+                "Number of failed json rpc requests per network",
+                NETWORK_LABELS,
+            )?,
+            rpc_latency_ms: counter(
+                registry,
+                "qp_rpc_latency_ms_total",
+                "Total time spent in json rpc requests per network, in milliseconds",
+                NETWORK_LABELS,
+            )?,
+            pending_tx_age_seconds: gauge(
+                registry,
+                "qp_pending_tx_age_seconds",
+                "Age of the oldest pending transaction per network",
+                NETWORK_LABELS,
+            )?,
+            lock_contention: register(
+                Counter::new(
+                    "qp_lock_contention_total",
+                    "Number of worker runs or pairs skipped because the lock was held",
+                )?,
+                registry,
+            )?,
+            worker_status: gauge(
+                registry,
+                "qp_worker_status",
+                "Outcome of the last worker run, 1 for the current status and 0 for the others",
+                &["status"],
+            )?,
+        })
+    }
+
+    /// Mirror a snapshot into the registry. Counters are advanced by the difference with the
+    /// previous snapshot, a counter going backwards (e.g. offchain storage wiped) restarts from zero.
+    fn update(&self, last: &QpMetrics, current: &QpMetrics, now_ms: u64) {
+        fn delta(last: u64, current: u64) -> u64 {
+            if current >= last {
+                current - last
+            } else {
+                current
+            }
+        }
+
+        for pair in current.pairs.iter() {
+            let remote = pair.remote_chain.to_string();
+            let local = pair.local_chain.to_string();
+            let labels = &[remote.as_str(), local.as_str()];
+            let prev = last
+                .pairs
+                .iter()
+                .find(|p| p.remote_chain == pair.remote_chain && p.local_chain == pair.local_chain)
+                .cloned()
+                .unwrap_or_default();
+
+            self.mine_attempts
+                .with_label_values(labels)
+                .inc_by(delta(prev.mine_attempts, pair.mine_attempts));
+            self.mine_successes
+                .with_label_values(labels)
+                .inc_by(delta(prev.mine_successes, pair.mine_successes));
+            self.mine_transactions
+                .with_label_values(labels)
+                .inc_by(delta(prev.mine_transactions, pair.mine_transactions));
+            self.finalize_attempts
+                .with_label_values(labels)
+                .inc_by(delta(prev.finalize_attempts, pair.finalize_attempts));
+            self.finalize_successes
+                .with_label_values(labels)
+                .inc_by(delta(prev.finalize_successes, pair.finalize_successes));
+            self.finalize_transactions
+                .with_label_values(labels)
+                .inc_by(delta(
+                    prev.finalize_transactions,
+                    pair.finalize_transactions,
+                ));
+            // Until the pair has a success, measured from when the worker first processed it
+            let since = match pair.last_success_timestamp {
+                0 => pair.first_seen_timestamp,
+                last_success => last_success,
+            };
+            if since > 0 {
+                self.seconds_since_last_success
+                    .with_label_values(labels)
+                    .set(now_ms.saturating_sub(since) / 1000);
+            }
+            self.nonce_lag.with_label_values(labels).set(pair.nonce_lag);
+        }
+
+        for network in current.networks.iter() {
+            let chain_id = network.chain_id.to_string();
+            let labels = &[chain_id.as_str()];
+            let prev = last
+                .networks
+                .iter()
+                .find(|n| n.chain_id == network.chain_id)
+                .cloned()
+                .unwrap_or_default();
+
+            self.rpc_requests
+                .with_label_values(labels)
+                .inc_by(delta(prev.rpc_requests, network.rpc_requests));
+            self.rpc_errors
+                .with_label_values(labels)
+                .inc_by(delta(prev.rpc_errors, network.rpc_errors));
+            self.rpc_latency_ms
+                .with_label_values(labels)
+                .inc_by(delta(prev.rpc_latency_ms, network.rpc_latency_ms));
+            self.pending_tx_age_seconds
+                .with_label_values(labels)
+                .set(network.pending_tx_age_ms / 1000);
+        }
+
+        self.lock_contention
+            .inc_by(delta(last.lock_contention, current.lock_contention));
+        for status in WORKER_STATUSES {
+            self.worker_status
+                .with_label_values(&[worker_status_name(*status)])
+                .set((*status == current.status) as u64);
+        }
+    }
+}
+
+/// Read the metrics snapshot written by the offchain worker
+pub fn read_snapshot<S: OffchainStorage>(storage: &S) -> Option<QpMetrics> {
+    let encoded = storage.get(STORAGE_PREFIX, OFFCHAIN_QP_METRICS_KEY)?;
+    match QpMetrics::decode(&mut &encoded[..]) {
+        Ok(metrics) => Some(metrics),
+        Err(e) => {
+            log::warn!("Could not decode the QP metrics snapshot: {:?}", e);
+            None
+        }
+    }
+}
+
+fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Periodically scrape the offchain worker metrics from offchain storage
+pub async fn run_qp_metrics_task<B: Backend<Block>>(backend: Arc<B>, metrics: QpPrometheusMetrics) {
+    let mut last = QpMetrics::default();
+    loop {
+        futures_timer::Delay::new(SCRAPE_INTERVAL).await;
+        if let Some(current) = backend.offchain_storage().and_then(|s| read_snapshot(&s)) {
+            metrics.update(&last, &current, unix_millis());
+            last = current;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pallet_quantum_portal::qp_types::{QpNetworkMetrics, QpPairMetrics};
+
+    fn snapshot(mine_attempts: u64, rpc_requests: u64) -> QpMetrics {
+        QpMetrics {
+            pairs: vec![QpPairMetrics {
+                remote_chain: 4,
+                local_chain: 5,
+                mine_attempts,
+                last_success_timestamp: 1_000,
+                nonce_lag: 3,
+                ..Default::default()
+            }],
+            networks: vec![QpNetworkMetrics {
+                chain_id: 4,
+                rpc_requests,
+                pending_tx_age_ms: 12_000,
+                ..Default::default()
+            }],
+            lock_contention: 2,
+            status: QpWorkerStatus::NoSignerKey,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn mirrors_snapshots_into_registry() {
+        let registry = Registry::new();
+        let metrics = QpPrometheusMetrics::register(&registry).unwrap();
+
+        let first = snapshot(3, 10);
+        metrics.update(&QpMetrics::default(), &first, 61_000);
+        let second = snapshot(5, 12);
+        metrics.update(&first, &second, 61_000);
+
+        assert_eq!(
+            metrics.mine_attempts.with_label_values(&["4", "5"]).get(),
+            5
+        );
+        assert_eq!(metrics.rpc_requests.with_label_values(&["4"]).get(), 12);
+        assert_eq!(metrics.nonce_lag.with_label_values(&["4", "5"]).get(), 3);
+        assert_eq!(
+            metrics
+                .seconds_since_last_success
+                .with_label_values(&["4", "5"])
+                .get(),
+            60
+        );
+        assert_eq!(
+            metrics
+                .pending_tx_age_seconds
+                .with_label_values(&["4"])
+                .get(),
+            12
+        );
+        assert_eq!(metrics.lock_contention.get(), 2);
+        assert_eq!(
+            metrics
+                .worker_status
+                .with_label_values(&["noSignerKey"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            metrics.worker_status.with_label_values(&["running"]).get(),
+            0
+        );
+    }
+
+    #[test]
+    fn counters_restart_after_reset() {
+        let registry = Registry::new();
+        let metrics = QpPrometheusMetrics::register(&registry).unwrap();
+
+        let first = snapshot(7, 0);
+        metrics.update(&QpMetrics::default(), &first, 0);
+        let reset = snapshot(2, 0);
+        metrics.update(&first, &reset, 0);
+
+        assert_eq!(
+            metrics.mine_attempts.with_label_values(&["4", "5"]).get(),
+            9
+        );
+    }
+
+    #[test]
+    fn a_pair_without_success_ages_from_when_it_was_first_seen() {
+        let registry = Registry::new();
+        let metrics = QpPrometheusMetrics::register(&registry).unwrap();
+        let mut failing = snapshot(1, 0);
+        failing.pairs[0].last_success_timestamp = 0;
+        failing.pairs[0].first_seen_timestamp = 1_000;
+
+        metrics.update(&QpMetrics::default(), &failing, 601_000);
+
+        assert_eq!(
+            metrics
+                .seconds_since_last_success
+                .with_label_values(&["4", "5"])
+                .get(),
+            600
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Quantum portal query rpcs, backed by the `QuantumPortalApi` runtime api so clients do not
+//! depend on the pallet storage layout.
+
+use std::sync::Arc;
+
+use ferrum_x_runtime::{opaque::Block, AccountId, BlockNumber, Hash};
+use jsonrpsee::{
+    core::RpcResult,
+    proc_macros::rpc,
+    types::error::{CallError, ErrorObject},
+};
+use pallet_quantum_portal::qp_types::{QpBlockRecord, QpPairInfo};
+use pallet_quantum_portal_runtime_api::QuantumPortalApi;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::H160;
+use sp_runtime::generic::BlockId;
+
+/// Number of records returned by `qp_recentRecords` when no limit is given
+const DEFAULT_RECORDS_LIMIT: u32 = 20;
+
+/// Error code returned when the runtime api call fails
+const RUNTIME_ERROR: i32 = 1;
+
+/// A registered network
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QpNetwork {
+    pub chain_id: u64,
+    pub ledger_manager: H160,
+}
+
+/// Accounts allowed to mine and finalize
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QpWorkers {
+    pub miners: Vec<AccountId>,
+    pub finalizers: Vec<AccountId>,
+}
+
+#[rpc(server)]
+pub trait QpApi {
+    /// Registered networks with their ledger manager contract
+    #[method(name = "qp_networks")]
+    fn networks(&self, at: Option<Hash>) -> RpcResult<Vec<QpNetwork>>;
+
+    /// Pairs with a mined or finalized block and their last nonces. The mined nonce is
+    /// self-reported by the miners and unverified.
+    #[method(name = "qp_pairs")]
+    fn pairs(&self, at: Option<Hash>) -> RpcResult<Vec<QpPairInfo>>;
+
+    /// Registered miners and finalizers
+    #[method(name = "qp_workers")]
+    fn workers(&self, at: Option<Hash>) -> RpcResult<QpWorkers>;
+
+    /// Most recent mined and finalized block reports, newest first
+    #[method(name = "qp_recentRecords")]
+    fn recent_records(
+        &self,
+        limit: Option<u32>,
+        at: Option<Hash>,
+    ) -> RpcResult<Vec<QpBlockRecord<AccountId, BlockNumber>>>;
+}
+
+pub struct QpRpc<C> {
+    client: Arc<C>,
+}
+
+impl<C> QpRpc<C> {
+    pub fn new(client: Arc<C>) -> Self {
+        Self { client }
+    }
+}
+
+impl<C> QpRpc<C>
+where
+    C: HeaderBackend<Block>,
+{
+    fn block_id(&self, at: Option<Hash>) -> BlockId<Block> {
+        BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash))
+    }
+}
+
+fn runtime_error(e: impl std::fmt::Debug) -> jsonrpsee::core::Error {
+    CallError::Custom(ErrorObject::owned(
+        RUNTIME_ERROR,
+        "Unable to query the quantum portal state",
+        Some(format!("{e:?}")),
+    ))
+    .into()
+}
+
+impl<C> QpApiServer for QpRpc<C>
+where
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+    C::Api: QuantumPortalApi<Block, AccountId, BlockNumber>,
+{
+    fn networks(&self, at: Option<Hash>) -> RpcResult<Vec<QpNetwork>> {
+        let networks = self
+            .client
+            .runtime_api()
+            .networks(&self.block_id(at))
+            .map_err(runtime_error)?;
+        Ok(networks
+            .into_iter()
+            .map(|(chain_id, ledger_manager)| QpNetwork {
+                chain_id,
+                ledger_manager,
+            })
+            .collect())
+    }
+
+    fn pairs(&self, at: Option<Hash>) -> RpcResult<Vec<QpPairInfo>> {
+        self.client
+            .runtime_api()
+            .pairs(&self.block_id(at))
+            .map_err(runtime_error)
+    }
+
+    fn workers(&self, at: Option<Hash>) -> RpcResult<QpWorkers> {
+        let at = self.block_id(at);
+        let api = self.client.runtime_api();
+        Ok(QpWorkers {
+            miners: api.miners(&at).map_err(runtime_error)?,
+            finalizers: api.finalizers(&at).map_err(runtime_error)?,
+        })
+    }
+
+    fn recent_records(
+        &self,
+        limit: Option<u32>,
+        at: Option<Hash>,
+    ) -> RpcResult<Vec<QpBlockRecord<AccountId, BlockNumber>>> {
+        self.client
+            .runtime_api()
+            .recent_records(&self.block_id(at), limit.unwrap_or(DEFAULT_RECORDS_LIMIT))
+            .map_err(runtime_error)
+    }
+}
@@ -0,0 +1,842 @@
+//! A keystore that keeps the offchain signer key off the node.
+//!
+//! Keys of the types listed in `RemoteKeystore::remote_key_types` (by default only the `ofsg`
+//! offchain signer) are never stored locally: listing and signing are forwarded to an external
+//! signer, everything else (aura, grandpa, ...) is served by the local keystore as usual.
+//!
+//! The external signer is reached through `--keystore-uri`, either over plain HTTP
+//! (`http://host:port/path`) or over a Unix socket (`unix:///path/to/signer.sock`), and speaks
+//! JSON-RPC 2.0 with the following methods :
+//!
+//! - `signer_ecdsaPublicKeys(key_type)` -> list of 0x-prefixed compressed public keys
+//! - `signer_ecdsaSignPrehashed(key_type, public, message)` -> 0x-prefixed 65 byte signature or
+//!   `null` if the signer does not hold the key
+//!
+//! `key_type` is the four character key type id (e.g. `"ofsg"`). Over a Unix socket a single
+//! request is written per connection followed by a newline, and the signer writes its response
+//! and closes the connection. Over HTTP the response must have a `Content-Length` or end with the
+//! connection, chunked responses are refused.
+//!
+//! The requests are blocking. The `SyncCryptoStore` methods, used by the offchain worker, make
+//! them on the calling thread, the `CryptoStore` ones on the tokio blocking thread pool so they do
+//! not hold up the async runtime.
+
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use sc_keystore::LocalKeystore;
+use serde::Deserialize;
+use sp_core::{
+    bytes::{from_hex, to_hex},
+    crypto::{ByteArray, CryptoTypePublicPair, KeyTypeId},
+    ecdsa, ed25519,
+    hashing::blake2_256,
+    sr25519,
+};
+use sp_keystore::{
+    vrf::{VRFSignature, VRFTranscriptData},
+    CryptoStore, Error as KeystoreError, SyncCryptoStore,
+};
+
+use ferrum_primitives::OFFCHAIN_SIGNER_KEY_TYPE;
+
+/// How long we wait for the external signer before giving up on a request.
+const SIGNER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The transport used to reach the external signer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerEndpoint {
+    /// Plain HTTP, the request is POSTed to `path` on `host`.
+    Http { host: String, path: String },
+    /// A Unix domain socket on the local machine.
+    Unix(PathBuf),
+}
+
+impl SignerEndpoint {
+    /// Parse a `--keystore-uri` value.
+    pub fn parse(uri: &str) -> Result<Self, String> {
+        if let Some(path) = uri.strip_prefix("unix://") {
+            if path.is_empty() {
+                return Err("Unix socket path is empty".to_string());
+            }
+            return Ok(SignerEndpoint::Unix(PathBuf::from(path)));
+        }
+        if let Some(rest) = uri.strip_prefix("http://") {
+            let (host, path) = match rest.find('/') {
+                Some(idx) => (&rest[..idx], &rest[idx..]),
+                None => (rest, "/"),
+            };
+            if host.is_empty() {
+                return Err("HTTP signer host is empty".to_string());
+            }
+            return Ok(SignerEndpoint::Http {
+                host: host.to_string(),
+                path: path.to_string(),
+            });
+        }
+        Err(format!(
+            "Unsupported keystore uri {uri}, expected http://host:port/path or unix:///path"
+        ))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+/// A thin JSON-RPC client for the external signer.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    endpoint: SignerEndpoint,
+}
+
+impl RemoteSigner {
+    pub fn new(endpoint: SignerEndpoint) -> Self {
+        RemoteSigner { endpoint }
+    }
+
+    /// Compressed ECDSA public keys the signer holds for the given key type.
+    pub fn ecdsa_public_keys(
+        &self,
+        key_type: KeyTypeId,
+    ) -> Result<Vec<ecdsa::Public>, KeystoreError> {
+        let keys: Vec<String> = self
+            .request(
+                "signer_ecdsaPublicKeys",
+                serde_json::json!([key_type_str(key_type)]),
+            )?
+            .unwrap_or_default();
+        keys.iter()
+            .map(|k| {
+                let raw = from_hex(k).map_err(|e| {
+                    KeystoreError::Other(format!("Signer returned a bad public key {k}: {e}"))
+                })?;
+                ecdsa::Public::from_slice(&raw).map_err(|_| {
+                    KeystoreError::Other(format!("Signer returned a bad public key {k}"))
+                })
+            })
+            .collect()
+    }
+
+    /// Whether the signer holds the given public key of the key type.
+    pub fn has_key(&self, key_type: KeyTypeId, public: &[u8]) -> bool {
+        self.ecdsa_public_keys(key_type)
+            .map(|keys| keys.iter().any(|k| k.as_slice() == public))
+            .unwrap_or(false)
+    }
+
+    /// Sign `msg` like `SyncCryptoStore::sign_with` does with an ECDSA key.
+    pub fn sign_with(
+        &self,
+        key_type: KeyTypeId,
+        key: &CryptoTypePublicPair,
+        msg: &[u8],
+    ) -> Result<Option<Vec<u8>>, KeystoreError> {
+        if key.0 != ecdsa::CRYPTO_ID {
+            return Err(KeystoreError::KeyNotSupported(key_type));
+        }
+        let public = ecdsa::Public::from_slice(key.1.as_slice())
+            .map_err(|_| KeystoreError::ValidationError("Invalid public key format".into()))?;
+        // Same as `ecdsa::Pair::sign`, which hashes the message with blake2 before signing.
+        let sig = self.ecdsa_sign_prehashed(key_type, &public, &blake2_256(msg))?;
+        Ok(sig.map(|s| s.0.to_vec()))
+    }
+
+    /// Ask the signer to sign a 32 byte digest. Returns `None` if the signer does not hold the key.
+    pub fn ecdsa_sign_prehashed(
+        &self,
+        key_type: KeyTypeId,
+        public: &ecdsa::Public,
+        msg: &[u8; 32],
+    ) -> Result<Option<ecdsa::Signature>, KeystoreError> {
+        let sig: Option<String> = self.request(
+            "signer_ecdsaSignPrehashed",
+            serde_json::json!([
+                key_type_str(key_type),
+                to_hex(public.as_ref(), false),
+                to_hex(msg, false)
+            ]),
+        )?;
+        sig.map(|s| {
+            let raw = from_hex(&s).map_err(|e| {
+                KeystoreError::Other(format!("Signer returned a bad signature: {e}"))
+            })?;
+            ecdsa::Signature::from_slice(&raw).ok_or_else(|| {
+                KeystoreError::Other(format!(
+                    "Signer returned a signature of size {}, expected 65",
+                    raw.len()
+                ))
+            })
+        })
+        .transpose()
+    }
+
+    fn request<T>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<Option<T>, KeystoreError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        })
+        .to_string();
+
+        let raw = match &self.endpoint {
+            SignerEndpoint::Http { host, path } => Self::post_http(host, path, &body),
+            SignerEndpoint::Unix(path) => Self::post_unix(path, &body),
+        }
+        .map_err(|e| {
+            log::error!("Remote signer {:?} is unavailable: {}", self.endpoint, e);
+            KeystoreError::Unavailable
+        })?;
+
+        let res: RpcResponse<T> = serde_json::from_slice(&raw).map_err(|e| {
+            KeystoreError::Other(format!("Could not parse the signer response: {e}"))
+        })?;
+        if let Some(err) = res.error {
+            return Err(KeystoreError::Other(format!(
+                "Signer rejected {method} ({}): {}",
+                err.code, err.message
+            )));
+        }
+        Ok(res.result)
+    }
+
+    fn post_unix(path: &Path, body: &str) -> std::io::Result<Vec<u8>> {
+        let mut stream = UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(SIGNER_TIMEOUT))?;
+        stream.set_write_timeout(Some(SIGNER_TIMEOUT))?;
+        stream.write_all(body.as_bytes())?;
+        stream.write_all(b"\n")?;
+        stream.shutdown(std::net::Shutdown::Write)?;
+        let mut rv = Vec::new();
+        stream.read_to_end(&mut rv)?;
+        Ok(rv)
+    }
+
+    fn post_http(host: &str, path: &str, body: &str) -> std::io::Result<Vec<u8>> {
+        let mut stream = TcpStream::connect(host)?;
+        stream.set_read_timeout(Some(SIGNER_TIMEOUT))?;
+        stream.set_write_timeout(Some(SIGNER_TIMEOUT))?;
+        let req = format!(
+            "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(req.as_bytes())?;
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw)?;
+
+        let header_end = raw
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or_else(|| invalid_data("Malformed HTTP response from signer"))?;
+        let head = String::from_utf8_lossy(&raw[..header_end]).to_string();
+        let mut lines = head.split("\r\n");
+        let status_line = lines.next().unwrap_or_default();
+        if status_line.split_whitespace().nth(1) != Some("200") {
+            return Err(invalid_data(&format!(
+                "Unexpected signer status: {}",
+                status_line.trim()
+            )));
+        }
+        // The body is read to the end of the connection, it cannot be decoded if chunked
+        let chunked = lines.any(|line| {
+            let line = line.to_ascii_lowercase();
+            line.strip_prefix("transfer-encoding:")
+                .map_or(false, |v| v.contains("chunked"))
+        });
+        if chunked {
+            return Err(invalid_data("Chunked signer responses are not supported"));
+        }
+        Ok(raw[header_end + 4..].to_vec())
+    }
+}
+
+fn invalid_data(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn key_type_str(key_type: KeyTypeId) -> String {
+    String::from_utf8_lossy(&key_type.0).to_string()
+}
+
+/// A keystore forwarding the remote key types to a [`RemoteSigner`] and everything else to the
+/// node's local keystore.
+pub struct RemoteKeystore {
+    local: Arc<LocalKeystore>,
+    signer: RemoteSigner,
+    remote_key_types: Vec<KeyTypeId>,
+}
+
+impl RemoteKeystore {
+    pub fn new(local: Arc<LocalKeystore>, signer: RemoteSigner) -> Self {
+        RemoteKeystore {
+            local,
+            signer,
+            remote_key_types: vec![OFFCHAIN_SIGNER_KEY_TYPE],
+        }
+    }
+
+    fn is_remote(&self, id: KeyTypeId) -> bool {
+        self.remote_key_types.contains(&id)
+    }
+
+    /// Make a request to the signer on the blocking thread pool, for the async methods.
+    async fn remote<R, F>(&self, f: F) -> Result<R, KeystoreError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&RemoteSigner) -> Result<R, KeystoreError> + Send + 'static,
+    {
+        let signer = self.signer.clone();
+        tokio::task::spawn_blocking(move || f(&signer))
+            .await
+            .map_err(|e| KeystoreError::Other(format!("Remote signer request failed: {e}")))?
+    }
+}
+
+fn ecdsa_pairs(keys: Vec<ecdsa::Public>) -> Vec<CryptoTypePublicPair> {
+    keys.into_iter()
+        .map(|k| CryptoTypePublicPair(ecdsa::CRYPTO_ID, k.to_raw_vec()))
+        .collect()
+}
+
+#[async_trait]
+impl CryptoStore for RemoteKeystore {
+    async fn sr25519_public_keys(&self, id: KeyTypeId) -> Vec<sr25519::Public> {
+        SyncCryptoStore::sr25519_public_keys(self, id)
+    }
+
+    async fn sr25519_generate_new(
+        &self,
+        id: KeyTypeId,
+        seed: Option<&str>,
+    ) -> Result<sr25519::Public, KeystoreError> {
+        SyncCryptoStore::sr25519_generate_new(self, id, seed)
+    }
+
+    async fn ed25519_public_keys(&self, id: KeyTypeId) -> Vec<ed25519::Public> {
+        SyncCryptoStore::ed25519_public_keys(self, id)
+    }
+
+    async fn ed25519_generate_new(
+        &self,
+        id: KeyTypeId,
+        seed: Option<&str>,
+    ) -> Result<ed25519::Public, KeystoreError> {
+        SyncCryptoStore::ed25519_generate_new(self, id, seed)
+    }
+
+    async fn ecdsa_public_keys(&self, id: KeyTypeId) -> Vec<ecdsa::Public> {
+        if !self.is_remote(id) {
+            return SyncCryptoStore::ecdsa_public_keys(&*self.local, id);
+        }
+        self.remote(move |signer| signer.ecdsa_public_keys(id))
+            .await
+            .unwrap_or_else(|e| {
+                log::error!("Could not list remote signer keys: {:?}", e);
+                Vec::new()
+            })
+    }
+
+    async fn ecdsa_generate_new(
+        &self,
+        id: KeyTypeId,
+        seed: Option<&str>,
+    ) -> Result<ecdsa::Public, KeystoreError> {
+        SyncCryptoStore::ecdsa_generate_new(self, id, seed)
+    }
+
+    async fn insert_unknown(&self, id: KeyTypeId, suri: &str, public: &[u8]) -> Result<(), ()> {
+        SyncCryptoStore::insert_unknown(self, id, suri, public)
+    }
+
+    async fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
+        for (public, id) in public_keys {
+            let found = if self.is_remote(*id) {
+                let (public, id) = (public.clone(), *id);
+                self.remote(move |signer| Ok(signer.has_key(id, &public)))
+                    .await
+                    .unwrap_or(false)
+            } else {
+                SyncCryptoStore::has_keys(&*self.local, &[(public.clone(), *id)])
+            };
+            if !found {
+                return false;
+            }
+        }
+        true
+    }
+
+    async fn supported_keys(
+        &self,
+        id: KeyTypeId,
+        keys: Vec<CryptoTypePublicPair>,
+    ) -> Result<Vec<CryptoTypePublicPair>, KeystoreError> {
+        if !self.is_remote(id) {
+            return SyncCryptoStore::supported_keys(&*self.local, id, keys);
+        }
+        let available = CryptoStore::keys(self, id).await?;
+        Ok(keys.into_iter().filter(|k| available.contains(k)).collect())
+    }
+
+    async fn keys(&self, id: KeyTypeId) -> Result<Vec<CryptoTypePublicPair>, KeystoreError> {
+        if !self.is_remote(id) {
+            return SyncCryptoStore::keys(&*self.local, id);
+        }
+        self.remote(move |signer| signer.ecdsa_public_keys(id).map(ecdsa_pairs))
+            .await
+    }
+
+    async fn sign_with(
+        &self,
+        id: KeyTypeId,
+        key: &CryptoTypePublicPair,
+        msg: &[u8],
+    ) -> Result<Option<Vec<u8>>, KeystoreError> {
+        if !self.is_remote(id) {
+            return SyncCryptoStore::sign_with(&*self.local, id, key, msg);
+        }
+        let (key, msg) = (key.clone(), msg.to_vec());
+        self.remote(move |signer| signer.sign_with(id, &key, &msg))
+            .await
+    }
+
+    async fn sr25519_vrf_sign(
+        &self,
+        key_type: KeyTypeId,
+        public: &sr25519::Public,
+        transcript_data: VRFTranscriptData,
+    ) -> Result<Option<VRFSignature>, KeystoreError> {
+        SyncCryptoStore::sr25519_vrf_sign(self, key_type, public, transcript_data)
+    }
+
+    async fn ecdsa_sign_prehashed(
+        &self,
+        id: KeyTypeId,
+        public: &ecdsa::Public,
+        msg: &[u8; 32],
+    ) -> Result<Option<ecdsa::Signature>, KeystoreError> {
+        if !self.is_remote(id) {
+            return SyncCryptoStore::ecdsa_sign_prehashed(&*self.local, id, public, msg);
+        }
+        let (public, msg) = (public.clone(), *msg);
+        self.remote(move |signer| signer.ecdsa_sign_prehashed(id, &public, &msg))
+            .await
+    }
+}
+
+impl SyncCryptoStore for RemoteKeystore {
+    fn sr25519_public_keys(&self, id: KeyTypeId) -> Vec<sr25519::Public> {
+        SyncCryptoStore::sr25519_public_keys(&*self.local, id)
+    }
+
+    fn sr25519_generate_new(
+        &self,
+        id: KeyTypeId,
+        seed: Option<&str>,
+    ) -> Result<sr25519::Public, KeystoreError> {
+        SyncCryptoStore::sr25519_generate_new(&*self.local, id, seed)
+    }
+
+    fn ed25519_public_keys(&self, id: KeyTypeId) -> Vec<ed25519::Public> {
+        SyncCryptoStore::ed25519_public_keys(&*self.local, id)
+    }
+
+    fn ed25519_generate_new(
+        &self,
+        id: KeyTypeId,
+        seed: Option<&str>,
+    ) -> Result<ed25519::Public, KeystoreError> {
+        SyncCryptoStore::ed25519_generate_new(&*self.local, id, seed)
+    }
+
+    fn ecdsa_public_keys(&self, id: KeyTypeId) -> Vec<ecdsa::Public> {
+        if !self.is_remote(id) {
+            return SyncCryptoStore::ecdsa_public_keys(&*self.local, id);
+        }
+        self.signer.ecdsa_public_keys(id).unwrap_or_else(|e| {
+            log::error!("Could not list remote signer keys: {:?}", e);
+            Vec::new()
+        })
+    }
+
+    fn ecdsa_generate_new(
+        &self,
+        id: KeyTypeId,
+        seed: Option<&str>,
+    ) -> Result<ecdsa::Public, KeystoreError> {
+        if self.is_remote(id) {
+            // Remote keys are provisioned on the signer, never generated on the node.
+            return Err(KeystoreError::KeyNotSupported(id));
+        }
+        SyncCryptoStore::ecdsa_generate_new(&*self.local, id, seed)
+    }
+
+    fn insert_unknown(&self, key_type: KeyTypeId, suri: &str, public: &[u8]) -> Result<(), ()> {
+        if self.is_remote(key_type) {
+            log::error!(
+                "Refusing to insert a {} key into the node keystore, it is held by the remote signer",
+                key_type_str(key_type)
+            );
+            return Err(());
+        }
+        SyncCryptoStore::insert_unknown(&*self.local, key_type, suri, public)
+    }
+
+    fn supported_keys(
+        &self,
+        id: KeyTypeId,
+        keys: Vec<CryptoTypePublicPair>,
+    ) -> Result<Vec<CryptoTypePublicPair>, KeystoreError> {
+        if !self.is_remote(id) {
+            return SyncCryptoStore::supported_keys(&*self.local, id, keys);
+        }
+        let available = ecdsa_pairs(self.signer.ecdsa_public_keys(id)?);
+        Ok(keys.into_iter().filter(|k| available.contains(k)).collect())
+    }
+
+    fn keys(&self, id: KeyTypeId) -> Result<Vec<CryptoTypePublicPair>, KeystoreError> {
+        if !self.is_remote(id) {
+            return SyncCryptoStore::keys(&*self.local, id);
+        }
+        self.signer.ecdsa_public_keys(id).map(ecdsa_pairs)
+    }
+
+    fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
+        public_keys.iter().all(|(public, id)| {
+            if !self.is_remote(*id) {
+                return SyncCryptoStore::has_keys(&*self.local, &[(public.clone(), *id)]);
+            }
+            self.signer.has_key(*id, public)
+        })
+    }
+
+    fn sign_with(
+        &self,
+        id: KeyTypeId,
+        key: &CryptoTypePublicPair,
+        msg: &[u8],
+    ) -> Result<Option<Vec<u8>>, KeystoreError> {
+        if !self.is_remote(id) {
+            return SyncCryptoStore::sign_with(&*self.local, id, key, msg);
+        }
+        self.signer.sign_with(id, key, msg)
+    }
+
+    fn sr25519_vrf_sign(
+        &self,
+        key_type: KeyTypeId,
+        public: &sr25519::Public,
+        transcript_data: VRFTranscriptData,
+    ) -> Result<Option<VRFSignature>, KeystoreError> {
+        SyncCryptoStore::sr25519_vrf_sign(&*self.local, key_type, public, transcript_data)
+    }
+
+    fn ecdsa_sign_prehashed(
+        &self,
+        id: KeyTypeId,
+        public: &ecdsa::Public,
+        msg: &[u8; 32],
+    ) -> Result<Option<ecdsa::Signature>, KeystoreError> {
+        if !self.is_remote(id) {
+            return SyncCryptoStore::ecdsa_sign_prehashed(&*self.local, id, public, msg);
+        }
+        self.signer.ecdsa_sign_prehashed(id, public, msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::BufRead,
+        net::TcpListener,
+        os::unix::net::UnixListener,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use sp_core::Pair;
+
+    /// A local stand-in for the external signer, holding its keys in memory.
+    struct MockSigner {
+        pairs: Vec<ecdsa::Pair>,
+    }
+
+    impl MockSigner {
+        fn handle(&self, body: &[u8]) -> Vec<u8> {
+            let req: serde_json::Value = serde_json::from_slice(body).unwrap();
+            let params = req["params"].as_array().unwrap();
+            assert_eq!(params[0], "ofsg");
+            let result = match req["method"].as_str().unwrap() {
+                "signer_ecdsaPublicKeys" => serde_json::json!(self
+                    .pairs
+                    .iter()
+                    .map(|p| to_hex(p.public().as_ref(), false))
+                    .collect::<Vec<_>>()),
+                "signer_ecdsaSignPrehashed" => {
+                    let public = from_hex(params[1].as_str().unwrap()).unwrap();
+                    let msg: [u8; 32] = from_hex(params[2].as_str().unwrap())
+                        .unwrap()
+                        .try_into()
+                        .unwrap();
+                    match self.pairs.iter().find(|p| p.public().as_ref() == public) {
+                        Some(pair) => {
+                            serde_json::json!(to_hex(&pair.sign_prehashed(&msg).0, false))
+                        }
+                        None => serde_json::Value::Null,
+                    }
+                }
+                m => panic!("Unexpected method {m}"),
+            };
+            serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": result })
+                .to_string()
+                .into_bytes()
+        }
+
+        fn serve_unix(self, requests: usize) -> PathBuf {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "ferrum-mock-signer-{}-{}.sock",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::SeqCst)
+            ));
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path).unwrap();
+            std::thread::spawn(move || {
+                for stream in listener.incoming().take(requests) {
+                    let mut stream = stream.unwrap();
+                    let mut line = Vec::new();
+                    std::io::BufReader::new(&mut stream)
+                        .read_until(b'\n', &mut line)
+                        .unwrap();
+                    stream.write_all(&self.handle(&line)).unwrap();
+                }
+            });
+            path
+        }
+
+        fn serve_http(self, requests: usize) -> String {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap().to_string();
+            std::thread::spawn(move || {
+                for stream in listener.incoming().take(requests) {
+                    let mut stream = stream.unwrap();
+                    let mut reader = std::io::BufReader::new(&mut stream);
+                    let mut content_length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line == "\r\n" {
+                            break;
+                        }
+                        if let Some(v) = line.to_lowercase().strip_prefix("content-length:") {
+                            content_length = v.trim().parse().unwrap();
+                        }
+                    }
+                    let mut body = vec![0u8; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    let res = self.handle(&body);
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+                        res.len()
+                    );
+                    stream.write_all(head.as_bytes()).unwrap();
+                    stream.write_all(&res).unwrap();
+                }
+            });
+            format!("http://{addr}/")
+        }
+    }
+
+    fn test_pair() -> ecdsa::Pair {
+        ecdsa::Pair::from_string("//QpSigner", None).unwrap()
+    }
+
+    #[test]
+    fn parse_endpoints() {
+        assert_eq!(
+            SignerEndpoint::parse("unix:///run/signer.sock").unwrap(),
+            SignerEndpoint::Unix(PathBuf::from("/run/signer.sock"))
+        );
+        assert_eq!(
+            SignerEndpoint::parse("http://127.0.0.1:8600/sign").unwrap(),
+            SignerEndpoint::Http {
+                host: "127.0.0.1:8600".to_string(),
+                path: "/sign".to_string()
+            }
+        );
+        assert!(SignerEndpoint::parse("https://signer.example.com").is_err());
+        assert!(SignerEndpoint::parse("unix://").is_err());
+    }
+
+    #[test]
+    fn signs_offchain_key_over_unix_socket() {
+        let pair = test_pair();
+        let path = MockSigner {
+            pairs: vec![pair.clone()],
+        }
+        .serve_unix(3);
+        let keystore = RemoteKeystore::new(
+            Arc::new(LocalKeystore::in_memory()),
+            RemoteSigner::new(SignerEndpoint::Unix(path)),
+        );
+
+        let keys = SyncCryptoStore::ecdsa_public_keys(&keystore, OFFCHAIN_SIGNER_KEY_TYPE);
+        assert_eq!(keys, vec![pair.public()]);
+
+        let msg = blake2_256(b"mineRemoteBlock");
+        let sig = SyncCryptoStore::ecdsa_sign_prehashed(
+            &keystore,
+            OFFCHAIN_SIGNER_KEY_TYPE,
+            &pair.public(),
+            &msg,
+        )
+        .unwrap()
+        .unwrap();
+        assert!(ecdsa::Pair::verify_prehashed(&sig, &msg, &pair.public()));
+
+        let unknown = ecdsa::Pair::from_string("//Unknown", None)
+            .unwrap()
+            .public();
+        let sig = SyncCryptoStore::ecdsa_sign_prehashed(
+            &keystore,
+            OFFCHAIN_SIGNER_KEY_TYPE,
+            &unknown,
+            &msg,
+        )
+        .unwrap();
+        assert!(sig.is_none());
+    }
+
+    #[test]
+    fn signs_offchain_key_over_http() {
+        let pair = test_pair();
+        let uri = MockSigner {
+            pairs: vec![pair.clone()],
+        }
+        .serve_http(1);
+        let keystore = RemoteKeystore::new(
+            Arc::new(LocalKeystore::in_memory()),
+            RemoteSigner::new(SignerEndpoint::parse(&uri).unwrap()),
+        );
+
+        let msg = blake2_256(b"finalizeSingleSigner");
+        let sig = SyncCryptoStore::ecdsa_sign_prehashed(
+            &keystore,
+            OFFCHAIN_SIGNER_KEY_TYPE,
+            &pair.public(),
+            &msg,
+        )
+        .unwrap()
+        .unwrap();
+        assert!(ecdsa::Pair::verify_prehashed(&sig, &msg, &pair.public()));
+    }
+
+    #[test]
+    fn async_methods_sign_off_the_runtime_threads() {
+        let pair = test_pair();
+        let path = MockSigner {
+            pairs: vec![pair.clone()],
+        }
+        .serve_unix(2);
+        let keystore = RemoteKeystore::new(
+            Arc::new(LocalKeystore::in_memory()),
+            RemoteSigner::new(SignerEndpoint::Unix(path)),
+        );
+        let msg = blake2_256(b"mineRemoteBlock");
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let (keys, sig) = runtime.block_on(async {
+            let keys = CryptoStore::ecdsa_public_keys(&keystore, OFFCHAIN_SIGNER_KEY_TYPE).await;
+            let sig = CryptoStore::ecdsa_sign_prehashed(
+                &keystore,
+                OFFCHAIN_SIGNER_KEY_TYPE,
+                &pair.public(),
+                &msg,
+            )
+            .await;
+            (keys, sig)
+        });
+
+        assert_eq!(keys, vec![pair.public()]);
+        assert!(ecdsa::Pair::verify_prehashed(
+            &sig.unwrap().unwrap(),
+            &msg,
+            &pair.public()
+        ));
+    }
+
+    #[test]
+    fn chunked_http_responses_are_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut head = String::new();
+            let mut reader = std::io::BufReader::new(&mut stream);
+            while head != "\r\n" {
+                head.clear();
+                reader.read_line(&mut head).unwrap();
+            }
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                      2\r\n[]\r\n0\r\n\r\n",
+                )
+                .unwrap();
+        });
+
+        let err = RemoteSigner::post_http(&addr, "/", "{}").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn other_key_types_stay_local() {
+        // No signer is listening, any remote call would fail.
+        let keystore = RemoteKeystore::new(
+            Arc::new(LocalKeystore::in_memory()),
+            RemoteSigner::new(SignerEndpoint::Unix(PathBuf::from(
+                "/nonexistent/ferrum-signer.sock",
+            ))),
+        );
+        let aura = KeyTypeId(*b"aura");
+        let public = SyncCryptoStore::sr25519_generate_new(&keystore, aura, None).unwrap();
+        assert_eq!(
+            SyncCryptoStore::sr25519_public_keys(&keystore, aura),
+            vec![public]
+        );
+
+        assert!(SyncCryptoStore::insert_unknown(
+            &keystore,
+            OFFCHAIN_SIGNER_KEY_TYPE,
+            "//Alice",
+            test_pair().public().as_ref()
+        )
+        .is_err());
+        assert!(SyncCryptoStore::ecdsa_public_keys(&keystore, OFFCHAIN_SIGNER_KEY_TYPE).is_empty());
+    }
+}
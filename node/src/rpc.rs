@@ -27,7 +27,7 @@ use fc_rpc::{
 use fc_rpc_core::types::{FeeHistoryCache, FeeHistoryCacheLimit, FilterPool};
 use fp_storage::EthereumStorageSchema;
 // Runtime
-use ferrum_x_runtime::{opaque::Block, AccountId, Balance, Hash, Index};
+use ferrum_x_runtime::{opaque::Block, AccountId, Balance, BlockNumber, Hash, Index};
 
 /// Full client dependencies.
 pub struct FullDeps<C, P, A: ChainApi> {
@@ -59,6 +59,8 @@ pub struct FullDeps<C, P, A: ChainApi> {
     pub overrides: Arc<OverrideHandle<Block>>,
     /// Cache for Ethereum block data.
     pub block_data_cache: Arc<EthBlockDataCacheTask<Block>>,
+    /// Offchain storage, read by the quantum portal health rpc
+    pub offchain_storage: Option<<crate::service::FullBackend as Backend<Block>>::OffchainStorage>,
     /// Manual seal command sink
     #[cfg(feature = "manual-seal")]
     pub command_sink:
@@ -116,9 +118,12 @@ where
     C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
     C::Api: fp_rpc::ConvertTransactionRuntimeApi<Block>,
     C::Api: fp_rpc::EthereumRuntimeRPCApi<Block>,
+    C::Api: pallet_quantum_portal_runtime_api::QuantumPortalApi<Block, AccountId, BlockNumber>,
     P: TransactionPool<Block = Block> + 'static,
     A: ChainApi<Block = Block> + 'static,
 {
+    use crate::qp_health::{QpHealthApiServer, QpHealthRpc};
+    use crate::qp_rpc::{QpApiServer, QpRpc};
     use fc_rpc::{
         Eth, EthApiServer, EthDevSigner, EthFilter, EthFilterApiServer, EthPubSub,
         EthPubSubApiServer, EthSigner, Net, NetApiServer, Web3, Web3ApiServer,
@@ -142,6 +147,7 @@ where
         fee_history_cache_limit,
         overrides,
         block_data_cache,
+        offchain_storage,
         #[cfg(feature = "manual-seal")]
         command_sink,
     } = deps;
@@ -209,7 +215,11 @@ where
         .into_rpc(),
     )?;
 
-    io.merge(Web3::new(client).into_rpc())?;
+    io.merge(Web3::new(client.clone()).into_rpc())?;
+
+    io.merge(QpRpc::new(client).into_rpc())?;
+
+    io.merge(QpHealthRpc::new(offchain_storage).into_rpc())?;
 
     #[cfg(feature = "manual-seal")]
     if let Some(command_sink) = command_sink {
@@ -13,8 +13,9 @@ use sc_cli::SubstrateCli;
 use sc_client_api::Backend;
 use sc_client_api::BlockchainEvents;
 use sc_executor::NativeElseWasmExecutor;
-use sc_keystore::LocalKeystore;
-use sc_service::{error::Error as ServiceError, BasePath, Configuration, TaskManager};
+use sc_service::{
+    error::Error as ServiceError, BasePath, Configuration, KeystoreContainer, TaskManager,
+};
 use sc_telemetry::{Telemetry, TelemetryWorker};
 use sp_application_crypto::sp_core::offchain::{OffchainStorage, STORAGE_PREFIX};
 use sp_core::U256;
@@ -33,6 +34,9 @@ use ferrum_x_runtime::{opaque::Block, RuntimeApi};
 use crate::cli::Cli;
 #[cfg(feature = "manual-seal")]
 use crate::cli::Sealing;
+use crate::qp_metrics::{run_qp_metrics_task, QpPrometheusMetrics};
+use crate::remote_keystore::{RemoteKeystore, RemoteSigner, SignerEndpoint};
+use crate::signer_check::{check_signer_balances, check_signer_key};
 
 // Our native executor instance.
 pub struct ExecutorDispatch;
@@ -56,7 +60,7 @@ impl sc_executor::NativeExecutionDispatch for ExecutorDispatch {
 
 pub type FullClient =
     sc_service::TFullClient<Block, RuntimeApi, NativeElseWasmExecutor<ExecutorDispatch>>;
-type FullBackend = sc_service::TFullBackend<Block>;
+pub type FullBackend = sc_service::TFullBackend<Block>;
 type FullSelectChain = sc_consensus::LongestChain<FullBackend, Block>;
 
 #[cfg(feature = "aura")]
@@ -106,12 +110,6 @@ pub fn new_partial(
     >,
     ServiceError,
 > {
-    if config.keystore_remote.is_some() {
-        return Err(ServiceError::Other(
-            "Remote Keystores are not supported.".to_string(),
-        ));
-    }
-
     let telemetry = config
         .telemetry_endpoints
         .clone()
@@ -130,7 +128,7 @@ pub fn new_partial(
         config.runtime_cache_size,
     );
 
-    let (client, backend, keystore_container, task_manager) =
+    let (client, backend, mut keystore_container, task_manager) =
         sc_service::new_full_parts::<Block, RuntimeApi, _>(
             config,
             telemetry.as_ref().map(|(_, telemetry)| telemetry.handle()),
@@ -138,6 +136,18 @@ pub fn new_partial(
         )?;
     let client = Arc::new(client);
 
+    // The remote keystore has to be in place before anything asks the container for a keystore
+    if let Some(url) = &config.keystore_remote {
+        match remote_keystore(url, &keystore_container) {
+            Ok(k) => keystore_container.set_remote_keystore(k),
+            Err(e) => {
+                return Err(ServiceError::Other(format!(
+                    "Error hooking up remote keystore for {url}: {e}"
+                )))
+            }
+        };
+    }
+
     let telemetry = telemetry.map(|(worker, telemetry)| {
         task_manager
             .spawn_handle()
@@ -174,24 +184,29 @@ pub fn new_partial(
             // read the config file
             let config = read_config_from_file(local_path_buf)
                 .expect("Failed to read chainspec config file");
+            let qp_config = crate::config::convert(config.networks);
+
+            // Make sure we can sign with the configured key before the offchain worker tries to
+            let signer_address = check_signer_key(&keystore, &qp_config).map_err(|e| {
+                ServiceError::Other(format!("Quantum portal signer check failed: {e}"))
+            })?;
+            if cli.run.qp_check_signer_balance {
+                task_manager.spawn_handle().spawn(
+                    "qp-signer-balance",
+                    None,
+                    check_signer_balances(signer_address, qp_config.clone()),
+                );
+            }
 
             // Load the configs for the offchain worker to function properly, we read from the file and write to the offchain storage
             offchain_storage.set(
                 STORAGE_PREFIX,
                 OFFCHAIN_SIGNER_CONFIG_KEY,
-                &crate::config::convert(config.networks).encode(),
+                &qp_config.encode(),
             );
 
             println!("QP Configs loaded to offchain storage");
         }
-
-        // just a sanity check to make sure the keystore is populated correctly
-        let ecdsa_keys: Vec<sp_core::ecdsa::Public> =
-            sp_keystore::SyncCryptoStore::ecdsa_public_keys(
-                &*keystore,
-                ferrum_primitives::OFFCHAIN_SIGNER_KEY_TYPE,
-            );
-        println!("ECDSA KEYS in keystore {ecdsa_keys:?}");
     }
 
     #[cfg(feature = "aura")]
@@ -286,11 +301,18 @@ pub fn new_partial(
     }
 }
 
-fn remote_keystore(_url: &str) -> Result<Arc<LocalKeystore>, &'static str> {
-    // FIXME: here would the concrete keystore be built,
-    //        must return a concrete type (NOT `LocalKeystore`) that
-    //        implements `CryptoStore` and `SyncCryptoStore`
-    Err("Remote Keystore not supported.")
+fn remote_keystore(
+    url: &str,
+    keystore_container: &KeystoreContainer,
+) -> Result<Arc<RemoteKeystore>, String> {
+    let endpoint = SignerEndpoint::parse(url)?;
+    let local = keystore_container
+        .local_keystore()
+        .ok_or_else(|| "No local keystore available".to_string())?;
+    Ok(Arc::new(RemoteKeystore::new(
+        local,
+        RemoteSigner::new(endpoint),
+    )))
 }
 
 /// Builds a new service for a full client.
@@ -307,7 +329,7 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
         backend,
         mut task_manager,
         import_queue,
-        mut keystore_container,
+        keystore_container,
         select_chain,
         transaction_pool,
         other:
@@ -320,17 +342,6 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
             ),
     } = new_partial(&config, cli)?;
 
-    if let Some(url) = &config.keystore_remote {
-        match remote_keystore(url) {
-            Ok(k) => keystore_container.set_remote_keystore(k),
-            Err(e) => {
-                return Err(ServiceError::Other(format!(
-                    "Error hooking up remote keystore for {url}: {e}"
-                )))
-            }
-        };
-    }
-
     let grandpa_protocol_name = sc_finality_grandpa::protocol_standard_name(
         &client
             .block_hash(0)
@@ -370,6 +381,15 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
             client.clone(),
             network.clone(),
         );
+
+        if let Some(registry) = config.prometheus_registry() {
+            let metrics = QpPrometheusMetrics::register(registry)?;
+            task_manager.spawn_handle().spawn(
+                "qp-metrics",
+                None,
+                run_qp_metrics_task(backend.clone(), metrics),
+            );
+        }
     }
 
     let role = config.role.clone();
@@ -397,6 +417,7 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
         let overrides = overrides.clone();
         let fee_history_cache = fee_history_cache.clone();
         let max_past_logs = cli.run.max_past_logs;
+        let offchain_storage = backend.offchain_storage();
 
         Box::new(move |deny_unsafe, subscription_task_executor| {
             let deps = crate::rpc::FullDeps {
@@ -414,6 +435,7 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
                 fee_history_cache_limit,
                 overrides: overrides.clone(),
                 block_data_cache: block_data_cache.clone(),
+                offchain_storage: offchain_storage.clone(),
             };
 
             crate::rpc::create_full(deps, subscription_task_executor).map_err(Into::into)
@@ -553,7 +575,7 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
         backend,
         mut task_manager,
         import_queue,
-        mut keystore_container,
+        keystore_container,
         select_chain,
         transaction_pool,
         other:
@@ -566,18 +588,6 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
             ),
     } = new_partial(&config, cli)?;
 
-    if let Some(url) = &config.keystore_remote {
-        match remote_keystore(url) {
-            Ok(k) => keystore_container.set_remote_keystore(k),
-            Err(e) => {
-                return Err(ServiceError::Other(format!(
-                    "Error hooking up remote keystore for {}: {}",
-                    url, e
-                )))
-            }
-        };
-    }
-
     let (network, system_rpc_tx, tx_handler_controller, network_starter) =
         sc_service::build_network(sc_service::BuildNetworkParams {
             config: &config,
@@ -596,6 +606,15 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
             client.clone(),
             network.clone(),
         );
+
+        if let Some(registry) = config.prometheus_registry() {
+            let metrics = QpPrometheusMetrics::register(registry)?;
+            task_manager.spawn_handle().spawn(
+                "qp-metrics",
+                None,
+                run_qp_metrics_task(backend.clone(), metrics),
+            );
+        }
     }
 
     let role = config.role.clone();
@@ -622,6 +641,7 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
         let overrides = overrides.clone();
         let fee_history_cache = fee_history_cache.clone();
         let max_past_logs = cli.run.max_past_logs;
+        let offchain_storage = backend.offchain_storage();
 
         Box::new(move |deny_unsafe, subscription_task_executor| {
             let deps = crate::rpc::FullDeps {
@@ -639,6 +659,7 @@ pub fn new_full(mut config: Configuration, cli: &Cli) -> Result<TaskManager, Ser
                 fee_history_cache_limit,
                 overrides: overrides.clone(),
                 block_data_cache: block_data_cache.clone(),
+                offchain_storage: offchain_storage.clone(),
                 command_sink: Some(command_sink.clone()),
             };
 
@@ -0,0 +1,197 @@
+//! Startup checks for the quantum portal signer key.
+//! The offchain worker signs with the `ofsg` key matching `signer_public_key` from the QP config,
+//! a missing key would only surface at signing time, so we verify it before the node starts.
+
+use std::time::Duration;
+
+use ferrum_primitives::OFFCHAIN_SIGNER_KEY_TYPE;
+use futures::future::{self, Either};
+use pallet_quantum_portal::{
+    chain_utils::ChainUtils,
+    qp_types::{QpConfig, QpNetworkType, QpRpcAuth},
+};
+use sp_core::{bytes::from_hex, ecdsa, H160, U256};
+use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
+
+const LOG_TARGET: &str = "qp";
+
+/// How long to wait for a network to answer the balance query
+const BALANCE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Make sure the configured signer key is in the keystore and return its EVM address
+pub fn check_signer_key(keystore: &SyncCryptoStorePtr, config: &QpConfig) -> Result<H160, String> {
+    let hex = std::str::from_utf8(&config.signer_public_key)
+        .map_err(|_| "signer_public_key is not a valid hex string".to_string())?;
+    let raw =
+        from_hex(hex).map_err(|e| format!("signer_public_key {hex} is not valid hex: {e}"))?;
+    let public = ecdsa::Public::try_from(raw.as_slice()).map_err(|_| {
+        format!("signer_public_key {hex} must be a 33 byte compressed ECDSA public key")
+    })?;
+
+    let keys = SyncCryptoStore::ecdsa_public_keys(&**keystore, OFFCHAIN_SIGNER_KEY_TYPE);
+    if !keys.contains(&public) {
+        return Err(format!(
+            "signer_public_key {hex} is not in the keystore ({} ofsg key(s) found). \
+             Insert it with `key insert --key-type ofsg --scheme Ecdsa`",
+            keys.len()
+        ));
+    }
+
+    let address = H160::from_slice(&ChainUtils::eth_address_from_public_key(&public.0));
+    log::info!(
+        target: LOG_TARGET,
+        "QP signer key {} found in keystore, EVM address {:?}",
+        hex,
+        address
+    );
+    Ok(address)
+}
+
+/// Query the signer balance on every configured network and warn about unfunded accounts
+pub async fn check_signer_balances(address: H160, config: QpConfig) {
+    let https = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client = hyper::Client::builder().build::<_, hyper::Body>(https);
+
+    // Native networks have no rpc url to query
+    for network in config
+        .network_vec
+        .iter()
+        .filter(|network| network.network_type == QpNetworkType::Http)
+    {
+        let url = String::from_utf8_lossy(&network.url).to_string();
+        let query = Box::pin(get_balance(&client, &url, &network.auth, address));
+        let timeout = futures_timer::Delay::new(BALANCE_TIMEOUT);
+        match future::select(query, timeout).await {
+            Either::Left((Ok(balance), _)) if balance.is_zero() => log::warn!(
+                target: LOG_TARGET,
+                "QP signer {:?} has no balance on chain {}, transactions will fail",
+                address,
+                network.id
+            ),
+            Either::Left((Ok(balance), _)) => log::info!(
+                target: LOG_TARGET,
+                "QP signer {:?} balance on chain {} is {}",
+                address,
+                network.id,
+                balance
+            ),
+            Either::Left((Err(e), _)) => log::warn!(
+                target: LOG_TARGET,
+                "Could not query the QP signer balance on chain {}: {}",
+                network.id,
+                e
+            ),
+            Either::Right(_) => log::warn!(
+                target: LOG_TARGET,
+                "Timed out querying the QP signer balance on chain {}",
+                network.id
+            ),
+        }
+    }
+}
+
+async fn get_balance<C>(
+    client: &hyper::Client<C, hyper::Body>,
+    url: &str,
+    auth: &QpRpcAuth,
+    address: H160,
+) -> Result<U256, String>
+where
+    C: hyper::client::connect::Connect + Clone + Send + Sync + 'static,
+{
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_getBalance",
+        "params": [format!("{address:?}"), "latest"],
+    });
+    let mut request = hyper::Request::post(url).header("Content-Type", "application/json");
+    for (name, value) in auth.http_headers() {
+        request = request.header(name.as_slice(), value.as_slice());
+    }
+    let request = request
+        .body(hyper::Body::from(body.to_string()))
+        .map_err(|e| e.to_string())?;
+    let response = client.request(request).await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("unexpected http status {}", response.status()));
+    }
+    let bytes = hyper::body::to_bytes(response.into_body())
+        .await
+        .map_err(|e| e.to_string())?;
+    parse_balance_response(&bytes)
+}
+
+fn parse_balance_response(bytes: &[u8]) -> Result<U256, String> {
+    let response: serde_json::Value = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+    if let Some(error) = response.get("error") {
+        return Err(format!("json rpc error {error}"));
+    }
+    let result = response
+        .get("result")
+        .and_then(|r| r.as_str())
+        .ok_or_else(|| "missing result".to_string())?;
+    U256::from_str_radix(result.trim_start_matches("0x"), 16).map_err(|e| format!("{e:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pallet_quantum_portal::qp_types::{EIP712Config, Role};
+    use sc_keystore::LocalKeystore;
+    use sp_core::Pair;
+    use std::sync::Arc;
+
+    fn config(signer_public_key: &[u8]) -> QpConfig {
+        QpConfig {
+            network_vec: vec![],
+            pair_vec: vec![],
+            signer_public_key: signer_public_key.to_vec(),
+            eip_712_config: EIP712Config::default(),
+            role: Role::QP_MINER,
+            schedule: Default::default(),
+        }
+    }
+
+    #[test]
+    fn finds_configured_key() {
+        let keystore: SyncCryptoStorePtr = Arc::new(LocalKeystore::in_memory());
+        let public =
+            SyncCryptoStore::ecdsa_generate_new(&*keystore, OFFCHAIN_SIGNER_KEY_TYPE, None)
+                .unwrap();
+        let hex = sp_core::bytes::to_hex(&public.0, false);
+
+        let address = check_signer_key(&keystore, &config(hex.as_bytes())).unwrap();
+        assert_eq!(
+            address.as_bytes(),
+            ChainUtils::eth_address_from_public_key(&public.0).as_slice()
+        );
+    }
+
+    #[test]
+    fn rejects_missing_key() {
+        let keystore: SyncCryptoStorePtr = Arc::new(LocalKeystore::in_memory());
+        let other = ecdsa::Pair::from_string("//Alice", None).unwrap();
+        let hex = sp_core::bytes::to_hex(&other.public().0, false);
+
+        let err = check_signer_key(&keystore, &config(hex.as_bytes())).unwrap_err();
+        assert!(err.contains("not in the keystore"));
+        assert!(check_signer_key(&keystore, &config(b"0x1234")).is_err());
+    }
+
+    #[test]
+    fn parses_balance() {
+        assert_eq!(
+            parse_balance_response(br#"{"jsonrpc":"2.0","id":1,"result":"0x0de0b6b3a7640000"}"#),
+            Ok(U256::from(1_000_000_000_000_000_000_u64))
+        );
+        assert!(parse_balance_response(
+            br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"bad"}}"#
+        )
+        .is_err());
+    }
+}
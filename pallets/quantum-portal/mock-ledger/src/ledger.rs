@@ -0,0 +1,516 @@
+//! In memory ledger manager: the state the offchain worker reads with `eth_call` and updates
+//! with `mineRemoteBlock` / `finalizeSingleSigner` transactions
+
+use crate::{
+    types::{LocalBlock, MinedBlock, Receipt, RemoteTransaction},
+    LOG_TARGET,
+};
+use ethabi_nostd::{decoder::decode, encoder::encode, ParamKind, Token};
+use ethereum::{LegacyTransaction, LegacyTransactionMessage, TransactionAction};
+use ethereum_types::{H160, H256, U256, U64};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tiny_keccak::{Hasher, Keccak};
+
+pub const IS_LOCAL_BLOCK_READY: &[u8] = b"isLocalBlockReady(uint64)";
+pub const LAST_LOCAL_BLOCK: &[u8] = b"lastLocalBlock(uint256)";
+pub const LOCAL_BLOCK_BY_NONCE: &[u8] = b"localBlockByNonce(uint64,uint64)";
+pub const LAST_REMOTE_MINED_BLOCK: &[u8] = b"lastRemoteMinedBlock(uint64)";
+pub const MINED_BLOCK_BY_NONCE: &[u8] = b"minedBlockByNonce(uint64,uint64)";
+pub const LAST_FINALIZED_BLOCK: &[u8] = b"lastFinalizedBlock(uint256)";
+pub const MINE_REMOTE_BLOCK: &[u8] = b"mineRemoteBlock(uint64,uint64,(uint64,address,address,address,address,uint256,bytes,uint256)[],bytes32,uint64,bytes)";
+pub const FINALIZE_SINGLE_SIGNER: &[u8] =
+    b"finalizeSingleSigner(uint256,uint256,bytes32,address[],bytes32,uint64,bytes)";
+
+/// Gas reported by `eth_estimateGas` for calls that do not revert
+pub const ESTIMATED_GAS: u64 = 1_000_000;
+/// Gas price reported by `eth_gasPrice` unless configured, 1 gwei
+pub const DEFAULT_GAS_PRICE: u64 = 1_000_000_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LedgerError {
+    /// The call data does not start with the selector of a known method
+    UnknownMethod,
+    /// The call data could not be decoded for its method
+    InvalidArguments,
+    /// The raw transaction is not a signed legacy transaction
+    InvalidTransaction,
+    /// The transaction is signed for another chain
+    WrongChainId(Option<u64>),
+    /// The transaction nonce is not the next nonce of the sender
+    WrongNonce { expected: u64, got: U256 },
+    /// The ledger manager rejected the call, a sent transaction is mined with a failed receipt
+    Reverted(&'static str),
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedgerError::UnknownMethod => write!(f, "unknown method"),
+            LedgerError::InvalidArguments => write!(f, "invalid arguments"),
+            LedgerError::InvalidTransaction => write!(f, "invalid transaction"),
+            LedgerError::WrongChainId(id) => write!(f, "transaction chain id is {:?}", id),
+            LedgerError::WrongNonce { expected, got } => {
+                write!(
+                    f,
+                    "nonce {} does not match the expected nonce {}",
+                    got, expected
+                )
+            }
+            LedgerError::Reverted(reason) => write!(f, "execution reverted: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for LedgerError {}
+
+pub type LedgerResult<T> = Result<T, LedgerError>;
+
+pub fn keccak(data: &[u8]) -> H256 {
+    let mut out = [0u8; 32];
+    let mut sponge = Keccak::v256();
+    sponge.update(data);
+    sponge.finalize(&mut out);
+    H256::from(out)
+}
+
+pub fn selector(signature: &[u8]) -> [u8; 4] {
+    let mut out = [0u8; 4];
+    out.copy_from_slice(&keccak(signature)[..4]);
+    out
+}
+
+/// Output of a call reverted with `reason`, the ABI encoded `Error(string)`
+pub fn revert_data(reason: &str) -> Vec<u8> {
+    [
+        &selector(b"Error(string)")[..],
+        &encode(&[Token::String(reason.as_bytes().to_vec())]),
+    ]
+    .concat()
+}
+
+fn to_u64(token: Token) -> LedgerResult<u64> {
+    token
+        .to_uint()
+        .filter(|v| *v <= U256::from(u64::MAX))
+        .map(|v| v.low_u64())
+        .ok_or(LedgerError::InvalidArguments)
+}
+
+fn remote_transaction_kind() -> ParamKind {
+    ParamKind::Tuple(vec![
+        Box::new(ParamKind::Uint(64)),  // timestamp
+        Box::new(ParamKind::Address),   // remoteContract
+        Box::new(ParamKind::Address),   // sourceMsgSender
+        Box::new(ParamKind::Address),   // sourceBeneficiary
+        Box::new(ParamKind::Address),   // token
+        Box::new(ParamKind::Uint(256)), // amount
+        Box::new(ParamKind::Bytes),     // method
+        Box::new(ParamKind::Uint(256)), // gas
+    ])
+}
+
+/// Recover the sender of a signed legacy transaction
+pub fn recover_sender(tx: &LegacyTransaction) -> LedgerResult<H160> {
+    let hash = LegacyTransactionMessage::from(tx.clone()).hash();
+    let mut rs = [0u8; 64];
+    rs[..32].copy_from_slice(tx.signature.r().as_bytes());
+    rs[32..].copy_from_slice(tx.signature.s().as_bytes());
+    let signature = libsecp256k1::Signature::parse_standard(&rs)
+        .map_err(|_| LedgerError::InvalidTransaction)?;
+    let recovery_id = libsecp256k1::RecoveryId::parse(tx.signature.standard_v())
+        .map_err(|_| LedgerError::InvalidTransaction)?;
+    let public = libsecp256k1::recover(
+        &libsecp256k1::Message::parse(hash.as_fixed_bytes()),
+        &signature,
+        &recovery_id,
+    )
+    .map_err(|_| LedgerError::InvalidTransaction)?;
+    Ok(H160::from_slice(&keccak(&public.serialize()[1..])[12..]))
+}
+
+/// Ledger manager of one mock chain.
+///
+/// Source blocks are added with `add_local_block`, standing in for the cross chain calls users
+/// make on the real contract. Mined and finalized blocks only change through transactions, and
+/// the checks are limited to what the offchain worker relies on: a block is mined once, after the
+/// last mined nonce, and finalized once it is mined. Finalizer signatures are not verified.
+#[derive(Clone, Debug)]
+pub struct MockLedger {
+    chain_id: u64,
+    address: H160,
+    gas_price: U256,
+    timestamp: Option<u64>,
+    block_number: u64,
+    /// Source blocks by target chain, in nonce order starting at 1
+    local_blocks: BTreeMap<u64, Vec<(LocalBlock, Vec<RemoteTransaction>)>>,
+    /// Mined blocks by source chain and nonce
+    mined_blocks: BTreeMap<(u64, u64), (MinedBlock, Vec<RemoteTransaction>)>,
+    last_mined: BTreeMap<u64, LocalBlock>,
+    last_finalized: BTreeMap<u64, LocalBlock>,
+    nonces: BTreeMap<H160, u64>,
+    receipts: BTreeMap<H256, Receipt>,
+}
+
+impl MockLedger {
+    pub fn new(chain_id: u64, address: H160) -> Self {
+        MockLedger {
+            chain_id,
+            address,
+            gas_price: U256::from(DEFAULT_GAS_PRICE),
+            timestamp: None,
+            block_number: 0,
+            local_blocks: Default::default(),
+            mined_blocks: Default::default(),
+            last_mined: Default::default(),
+            last_finalized: Default::default(),
+            nonces: Default::default(),
+            receipts: Default::default(),
+        }
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub fn address(&self) -> H160 {
+        self.address
+    }
+
+    pub fn gas_price(&self) -> U256 {
+        self.gas_price
+    }
+
+    /// Each sent transaction is mined in its own block
+    pub fn block_number(&self) -> u64 {
+        self.block_number
+    }
+
+    pub fn set_gas_price(&mut self, gas_price: U256) {
+        self.gas_price = gas_price;
+    }
+
+    /// Fix the block timestamp, in seconds. The system time is used otherwise
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        self.timestamp = Some(timestamp);
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        })
+    }
+
+    /// Close a source block with the given transactions for the target chain
+    pub fn add_local_block(
+        &mut self,
+        target_chain_id: u64,
+        txs: Vec<RemoteTransaction>,
+    ) -> LocalBlock {
+        let timestamp = self.timestamp();
+        let blocks = self.local_blocks.entry(target_chain_id).or_default();
+        let block = LocalBlock {
+            chain_id: target_chain_id,
+            nonce: blocks.len() as u64 + 1,
+            timestamp,
+        };
+        blocks.push((block, txs));
+        log::info!(
+            target: LOG_TARGET,
+            "[{}] Added source block {} for chain {}",
+            self.chain_id,
+            block.nonce,
+            target_chain_id
+        );
+        block
+    }
+
+    pub fn is_local_block_ready(&self, target_chain_id: u64) -> bool {
+        self.local_blocks.contains_key(&target_chain_id)
+    }
+
+    pub fn last_local_block(&self, target_chain_id: u64) -> LocalBlock {
+        self.local_blocks
+            .get(&target_chain_id)
+            .and_then(|blocks| blocks.last())
+            .map(|(block, _)| *block)
+            .unwrap_or_default()
+    }
+
+    pub fn local_block_by_nonce(
+        &self,
+        target_chain_id: u64,
+        nonce: u64,
+    ) -> (LocalBlock, Vec<RemoteTransaction>) {
+        nonce
+            .checked_sub(1)
+            .and_then(|i| self.local_blocks.get(&target_chain_id)?.get(i as usize))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn last_remote_mined_block(&self, source_chain_id: u64) -> LocalBlock {
+        self.last_mined
+            .get(&source_chain_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn mined_block_by_nonce(
+        &self,
+        source_chain_id: u64,
+        nonce: u64,
+    ) -> (MinedBlock, Vec<RemoteTransaction>) {
+        self.mined_blocks
+            .get(&(source_chain_id, nonce))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn last_finalized_block(&self, source_chain_id: u64) -> LocalBlock {
+        self.last_finalized
+            .get(&source_chain_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Next transaction nonce of the account
+    pub fn transaction_count(&self, account: H160) -> u64 {
+        self.nonces.get(&account).copied().unwrap_or_default()
+    }
+
+    pub fn receipt(&self, tx_hash: &H256) -> Option<Receipt> {
+        self.receipts.get(tx_hash).cloned()
+    }
+
+    /// Answer an `eth_call` of the ledger manager view methods with the ABI encoded result
+    pub fn call(&self, data: &[u8]) -> LedgerResult<Vec<u8>> {
+        if data.len() < 4 {
+            return Err(LedgerError::UnknownMethod);
+        }
+        let (method, args) = data.split_at(4);
+        let tokens = if method == selector(IS_LOCAL_BLOCK_READY) {
+            let [chain_id] = Self::uint_args(args)?;
+            vec![Token::Bool(self.is_local_block_ready(chain_id))]
+        } else if method == selector(LAST_LOCAL_BLOCK) {
+            let [chain_id] = Self::uint_args(args)?;
+            self.last_local_block(chain_id).tokens()
+        } else if method == selector(LOCAL_BLOCK_BY_NONCE) {
+            let [chain_id, nonce] = Self::uint_args(args)?;
+            let (block, txs) = self.local_block_by_nonce(chain_id, nonce);
+            let mut tokens = block.tokens();
+            tokens.push(Token::Array(txs.iter().map(|t| t.token()).collect()));
+            tokens
+        } else if method == selector(LAST_REMOTE_MINED_BLOCK) {
+            let [chain_id] = Self::uint_args(args)?;
+            self.last_remote_mined_block(chain_id).tokens()
+        } else if method == selector(MINED_BLOCK_BY_NONCE) {
+            let [chain_id, nonce] = Self::uint_args(args)?;
+            let (block, txs) = self.mined_block_by_nonce(chain_id, nonce);
+            let mut tokens = block.tokens();
+            tokens.push(Token::Array(txs.iter().map(|t| t.token()).collect()));
+            tokens
+        } else if method == selector(LAST_FINALIZED_BLOCK) {
+            let [chain_id] = Self::uint_args(args)?;
+            self.last_finalized_block(chain_id).tokens()
+        } else {
+            return Err(LedgerError::UnknownMethod);
+        };
+        Ok(encode(&tokens))
+    }
+
+    /// Answer an `eth_call` from `from`. Transactions run on a copy of the ledger and return
+    /// nothing, like the pre-flight calls of the worker
+    pub fn simulate(&self, from: H160, data: &[u8]) -> LedgerResult<Vec<u8>> {
+        match self.call(data) {
+            Err(LedgerError::UnknownMethod) => self.clone().execute(from, data).map(|_| vec![]),
+            rv => rv,
+        }
+    }
+
+    /// Run a transaction on a copy of the ledger, to answer `eth_estimateGas`
+    pub fn estimate_gas(&self, from: H160, data: &[u8]) -> LedgerResult<U256> {
+        self.clone().execute(from, data)?;
+        Ok(U256::from(ESTIMATED_GAS))
+    }
+
+    /// Accept a signed raw legacy transaction and execute it. Transactions that can not be
+    /// included are rejected, reverted calls are included with a failed receipt
+    pub fn send_raw_transaction(&mut self, raw: &[u8]) -> LedgerResult<H256> {
+        let tx: LegacyTransaction =
+            rlp::decode(raw).map_err(|_| LedgerError::InvalidTransaction)?;
+        let chain_id = tx.signature.chain_id();
+        if chain_id != Some(self.chain_id) {
+            return Err(LedgerError::WrongChainId(chain_id));
+        }
+        let from = recover_sender(&tx)?;
+        let expected = self.transaction_count(from);
+        if tx.nonce != U256::from(expected) {
+            return Err(LedgerError::WrongNonce {
+                expected,
+                got: tx.nonce,
+            });
+        }
+        self.nonces.insert(from, expected + 1);
+
+        let tx_hash = keccak(raw);
+        let to = match tx.action {
+            TransactionAction::Call(to) => Some(to),
+            TransactionAction::Create => None,
+        };
+        let outcome = if to == Some(self.address) {
+            self.execute(from, &tx.input)
+        } else {
+            Err(LedgerError::Reverted("not a call to the ledger manager"))
+        };
+        match &outcome {
+            Ok(()) => log::info!(
+                target: LOG_TARGET,
+                "[{}] Executed {:?}",
+                self.chain_id,
+                tx_hash
+            ),
+            Err(e) => log::warn!(
+                target: LOG_TARGET,
+                "[{}] Transaction {:?} failed: {}",
+                self.chain_id,
+                tx_hash,
+                e
+            ),
+        }
+
+        self.block_number += 1;
+        self.receipts.insert(
+            tx_hash,
+            Receipt {
+                transaction_hash: tx_hash,
+                block_hash: keccak(&self.block_number.to_be_bytes()),
+                block_number: U64::from(self.block_number),
+                from,
+                to,
+                status: U64::from(outcome.is_ok() as u64),
+            },
+        );
+        Ok(tx_hash)
+    }
+
+    /// Execute a ledger manager transaction from `from`. The state is only changed on success
+    fn execute(&mut self, from: H160, data: &[u8]) -> LedgerResult<()> {
+        if data.len() < 4 {
+            return Err(LedgerError::Reverted("unknown method"));
+        }
+        let (method, args) = data.split_at(4);
+        if method == selector(MINE_REMOTE_BLOCK) {
+            self.mine_remote_block(from, args)
+        } else if method == selector(FINALIZE_SINGLE_SIGNER) {
+            self.finalize_single_signer(args)
+        } else {
+            Err(LedgerError::Reverted("unknown method"))
+        }
+    }
+
+    fn mine_remote_block(&mut self, miner: H160, args: &[u8]) -> LedgerResult<()> {
+        let tokens = decode(
+            &[
+                ParamKind::Uint(64),                                   // remoteChainId
+                ParamKind::Uint(64),                                   // blockNonce
+                ParamKind::Array(Box::new(remote_transaction_kind())), // transactions
+                ParamKind::FixedBytes(32),                             // salt
+                ParamKind::Uint(64),                                   // expiry
+                ParamKind::Bytes,                                      // multiSignature
+            ],
+            args,
+        )
+        .map_err(|_| LedgerError::InvalidArguments)?;
+        let [chain_id, nonce, txs, _, _, _]: [Token; 6] = tokens
+            .try_into()
+            .map_err(|_| LedgerError::InvalidArguments)?;
+        let chain_id = to_u64(chain_id)?;
+        let nonce = to_u64(nonce)?;
+        let txs = txs
+            .to_array()
+            .ok_or(LedgerError::InvalidArguments)?
+            .into_iter()
+            .map(RemoteTransaction::from_token)
+            .collect::<Option<Vec<_>>>()
+            .ok_or(LedgerError::InvalidArguments)?;
+
+        if self.mined_blocks.contains_key(&(chain_id, nonce)) {
+            return Err(LedgerError::Reverted("block already mined"));
+        }
+        if nonce <= self.last_remote_mined_block(chain_id).nonce {
+            return Err(LedgerError::Reverted(
+                "nonce is not after the last mined block",
+            ));
+        }
+
+        let block_metadata = LocalBlock {
+            chain_id,
+            nonce,
+            timestamp: self.timestamp(),
+        };
+        let block = MinedBlock {
+            block_hash: keccak(args),
+            miner,
+            stake: U256::zero(),
+            total_value: txs
+                .iter()
+                .fold(U256::zero(), |total, tx| total.saturating_add(tx.amount)),
+            block_metadata,
+        };
+        self.mined_blocks.insert((chain_id, nonce), (block, txs));
+        self.last_mined.insert(chain_id, block_metadata);
+        Ok(())
+    }
+
+    fn finalize_single_signer(&mut self, args: &[u8]) -> LedgerResult<()> {
+        let tokens = decode(
+            &[
+                ParamKind::Uint(256),                           // remoteChainId
+                ParamKind::Uint(256),                           // blockNonce
+                ParamKind::FixedBytes(32),                      // finalizersHash
+                ParamKind::Array(Box::new(ParamKind::Address)), // finalizers
+                ParamKind::FixedBytes(32),                      // salt
+                ParamKind::Uint(64),                            // expiry
+                ParamKind::Bytes,                               // multiSignature
+            ],
+            args,
+        )
+        .map_err(|_| LedgerError::InvalidArguments)?;
+        let [chain_id, nonce, _, _, _, expiry, _]: [Token; 7] = tokens
+            .try_into()
+            .map_err(|_| LedgerError::InvalidArguments)?;
+        let chain_id = to_u64(chain_id)?;
+        let nonce = to_u64(nonce)?;
+        let expiry = to_u64(expiry)?;
+
+        let (block, _) = self
+            .mined_blocks
+            .get(&(chain_id, nonce))
+            .ok_or(LedgerError::Reverted("block is not mined"))?;
+        if nonce <= self.last_finalized_block(chain_id).nonce {
+            return Err(LedgerError::Reverted("block already finalized"));
+        }
+        if expiry < self.timestamp() {
+            return Err(LedgerError::Reverted("signature expired"));
+        }
+        self.last_finalized.insert(chain_id, block.block_metadata);
+        Ok(())
+    }
+
+    fn uint_args<const N: usize>(args: &[u8]) -> LedgerResult<[u64; N]> {
+        let tokens = decode(&vec![ParamKind::Uint(256); N], args)
+            .map_err(|_| LedgerError::InvalidArguments)?;
+        let values = tokens
+            .into_iter()
+            .map(to_u64)
+            .collect::<LedgerResult<Vec<_>>>()?;
+        values.try_into().map_err(|_| LedgerError::InvalidArguments)
+    }
+}
@@ -0,0 +1,19 @@
+//! Mock of the Quantum Portal ledger manager contract behind an Ethereum JSON-RPC endpoint.
+//!
+//! The offchain worker only talks to EVM networks through `eth_call` on the ledger manager view
+//! methods and raw `mineRemoteBlock` / `finalizeSingleSigner` transactions. `MockLedgerServer`
+//! serves those methods from an in memory `MockLedger`, so mining and finalization can be
+//! exercised end to end between two fake chains without any network access.
+
+pub mod ledger;
+pub mod rpc;
+pub mod types;
+
+#[cfg(test)]
+mod tests;
+
+pub use ledger::{LedgerError, MockLedger};
+pub use rpc::{MockLedgerServer, SharedLedger};
+pub use types::{LocalBlock, MinedBlock, Receipt, RemoteTransaction};
+
+pub const LOG_TARGET: &str = "qp::mock";
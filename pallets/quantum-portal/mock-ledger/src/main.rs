@@ -0,0 +1,40 @@
+//! Standalone mock ledger manager, to point a dev node's quantum portal networks at
+
+use clap::Parser;
+use ethereum_types::{H160, U256};
+use qp_mock_ledger::{MockLedger, MockLedgerServer};
+use std::net::SocketAddr;
+
+#[derive(Debug, Parser)]
+#[clap(about = "Serve a mock quantum portal ledger manager over Ethereum JSON-RPC")]
+struct Args {
+    /// Address to listen on
+    #[clap(long, default_value = "127.0.0.1:9545")]
+    listen: SocketAddr,
+
+    /// Chain id reported by `eth_chainId` and required in transaction signatures
+    #[clap(long)]
+    chain_id: u64,
+
+    /// Address of the ledger manager contract, as configured for the network
+    #[clap(long, default_value = "A8B001Ff4191F5e0D6b5DD15b8D466d8546d214b")]
+    ledger_manager: H160,
+
+    /// Gas price reported by `eth_gasPrice`, in wei
+    #[clap(long, default_value = "1000000000")]
+    gas_price: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+
+    let mut ledger = MockLedger::new(args.chain_id, args.ledger_manager);
+    ledger.set_gas_price(U256::from(args.gas_price));
+    let server = MockLedgerServer::start(args.listen, ledger).await?;
+
+    tokio::signal::ctrl_c().await?;
+    server.stop()?;
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! The Ethereum JSON-RPC methods used by the offchain worker, served from a `MockLedger`
+
+use crate::{
+    ledger::{revert_data, LedgerError, MockLedger},
+    types::{Bytes, CallRequest, LocalBlock, Receipt, RemoteTransaction},
+};
+use ethereum_types::{H160, H256, U256, U64};
+use jsonrpsee::{
+    core::RpcResult,
+    http_server::{HttpServerBuilder, HttpServerHandle},
+    proc_macros::rpc,
+    types::error::{CallError, ErrorObject},
+};
+use parking_lot::Mutex;
+use std::{net::SocketAddr, sync::Arc};
+
+/// Error code of rejected transactions, as returned by geth
+const SERVER_ERROR: i32 = -32000;
+/// Error code of reverted calls, which carry the revert output as data
+const EXECUTION_REVERTED: i32 = 3;
+
+pub type SharedLedger = Arc<Mutex<MockLedger>>;
+
+#[rpc(server)]
+pub trait MockLedgerApi {
+    #[method(name = "eth_chainId")]
+    fn chain_id(&self) -> RpcResult<U64>;
+
+    #[method(name = "eth_gasPrice")]
+    fn gas_price(&self) -> RpcResult<U256>;
+
+    #[method(name = "eth_blockNumber")]
+    fn block_number(&self) -> RpcResult<U64>;
+
+    #[method(name = "eth_getTransactionCount")]
+    fn transaction_count(&self, address: H160, block: Option<String>) -> RpcResult<U256>;
+
+    /// Call a view method of the ledger manager, or simulate a transaction. There is no state
+    /// history, every block reads the latest state
+    #[method(name = "eth_call")]
+    fn call(&self, request: CallRequest, block: Option<String>) -> RpcResult<Bytes>;
+
+    /// Fails like the call would revert, otherwise returns a fixed estimate
+    #[method(name = "eth_estimateGas")]
+    fn estimate_gas(&self, request: CallRequest, block: Option<String>) -> RpcResult<U256>;
+
+    #[method(name = "eth_sendRawTransaction")]
+    fn send_raw_transaction(&self, raw: Bytes) -> RpcResult<H256>;
+
+    #[method(name = "eth_getTransactionReceipt")]
+    fn transaction_receipt(&self, tx_hash: H256) -> RpcResult<Option<Receipt>>;
+
+    /// Close a source block for the target chain, as users sending cross chain calls would
+    #[method(name = "qpmock_addLocalBlock")]
+    fn add_local_block(
+        &self,
+        target_chain_id: u64,
+        transactions: Vec<RemoteTransaction>,
+    ) -> RpcResult<LocalBlock>;
+}
+
+pub struct MockLedgerRpc {
+    ledger: SharedLedger,
+}
+
+impl MockLedgerRpc {
+    pub fn new(ledger: SharedLedger) -> Self {
+        Self { ledger }
+    }
+}
+
+fn ledger_error(e: LedgerError) -> jsonrpsee::core::Error {
+    let error = match &e {
+        LedgerError::Reverted(reason) => ErrorObject::owned(
+            EXECUTION_REVERTED,
+            e.to_string(),
+            Some(Bytes(revert_data(reason))),
+        ),
+        _ => ErrorObject::owned(SERVER_ERROR, e.to_string(), None::<()>),
+    };
+    CallError::Custom(error).into()
+}
+
+impl MockLedgerApiServer for MockLedgerRpc {
+    fn chain_id(&self) -> RpcResult<U64> {
+        Ok(U64::from(self.ledger.lock().chain_id()))
+    }
+
+    fn gas_price(&self) -> RpcResult<U256> {
+        Ok(self.ledger.lock().gas_price())
+    }
+
+    fn block_number(&self) -> RpcResult<U64> {
+        Ok(U64::from(self.ledger.lock().block_number()))
+    }
+
+    fn transaction_count(&self, address: H160, _block: Option<String>) -> RpcResult<U256> {
+        Ok(U256::from(self.ledger.lock().transaction_count(address)))
+    }
+
+    fn call(&self, request: CallRequest, _block: Option<String>) -> RpcResult<Bytes> {
+        let ledger = self.ledger.lock();
+        if request.to != Some(ledger.address()) {
+            // Calls of accounts without code succeed with empty output
+            return Ok(Bytes::default());
+        }
+        ledger
+            .simulate(request.from.unwrap_or_default(), request.call_data())
+            .map(Bytes)
+            .map_err(ledger_error)
+    }
+
+    fn estimate_gas(&self, request: CallRequest, _block: Option<String>) -> RpcResult<U256> {
+        let ledger = self.ledger.lock();
+        if request.to != Some(ledger.address()) {
+            return Err(ledger_error(LedgerError::Reverted(
+                "not a call to the ledger manager",
+            )));
+        }
+        ledger
+            .estimate_gas(request.from.unwrap_or_default(), request.call_data())
+            .map_err(ledger_error)
+    }
+
+    fn send_raw_transaction(&self, raw: Bytes) -> RpcResult<H256> {
+        self.ledger
+            .lock()
+            .send_raw_transaction(&raw.0)
+            .map_err(ledger_error)
+    }
+
+    fn transaction_receipt(&self, tx_hash: H256) -> RpcResult<Option<Receipt>> {
+        Ok(self.ledger.lock().receipt(&tx_hash))
+    }
+
+    fn add_local_block(
+        &self,
+        target_chain_id: u64,
+        transactions: Vec<RemoteTransaction>,
+    ) -> RpcResult<LocalBlock> {
+        Ok(self
+            .ledger
+            .lock()
+            .add_local_block(target_chain_id, transactions))
+    }
+}
+
+/// A running mock ledger manager server
+pub struct MockLedgerServer {
+    addr: SocketAddr,
+    ledger: SharedLedger,
+    handle: HttpServerHandle,
+}
+
+impl MockLedgerServer {
+    /// Serve the ledger over HTTP on `addr`. Use port 0 to pick a free port
+    pub async fn start(
+        addr: SocketAddr,
+        ledger: MockLedger,
+    ) -> Result<Self, jsonrpsee::core::Error> {
+        let server = HttpServerBuilder::default().build(addr).await?;
+        let addr = server.local_addr()?;
+        log::info!(
+            target: crate::LOG_TARGET,
+            "Mock ledger manager {:?} of chain {} listening on http://{}",
+            ledger.address(),
+            ledger.chain_id(),
+            addr
+        );
+        let ledger = Arc::new(Mutex::new(ledger));
+        let handle = server.start(MockLedgerRpc::new(ledger.clone()).into_rpc())?;
+        Ok(Self {
+            addr,
+            ledger,
+            handle,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Url to configure as the network `url` of the quantum portal config
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// The served ledger, to seed source blocks or inspect the state in tests
+    pub fn ledger(&self) -> SharedLedger {
+        self.ledger.clone()
+    }
+
+    pub fn stop(self) -> Result<(), jsonrpsee::core::Error> {
+        self.handle.stop().map(|_| ())
+    }
+}
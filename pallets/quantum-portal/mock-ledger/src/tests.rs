@@ -0,0 +1,398 @@
+use crate::{
+    ledger::{
+        keccak, LedgerError, FINALIZE_SINGLE_SIGNER, IS_LOCAL_BLOCK_READY, LAST_FINALIZED_BLOCK,
+        LAST_LOCAL_BLOCK, LAST_REMOTE_MINED_BLOCK, LOCAL_BLOCK_BY_NONCE, MINED_BLOCK_BY_NONCE,
+        MINE_REMOTE_BLOCK,
+    },
+    types::Bytes,
+    LocalBlock, MockLedger, MockLedgerServer, RemoteTransaction,
+};
+use ethabi_nostd::{decoder::decode, encoder::encode_function_u8, ParamKind, Token};
+use ethereum::{
+    LegacyTransaction, LegacyTransactionMessage, TransactionAction, TransactionSignature,
+};
+use ethereum_types::{H160, H256, U256};
+use jsonrpsee::{core::client::ClientT, http_client::HttpClientBuilder, rpc_params};
+use libsecp256k1::{Message, PublicKey, SecretKey};
+
+const CHAIN: u64 = 97;
+const SOURCE_CHAIN: u64 = 80001;
+const NOW: u64 = 1_670_000_000;
+
+fn ledger_manager() -> H160 {
+    H160::repeat_byte(0xa8)
+}
+
+fn ledger() -> MockLedger {
+    let mut ledger = MockLedger::new(CHAIN, ledger_manager());
+    ledger.set_timestamp(NOW);
+    ledger
+}
+
+fn secret() -> SecretKey {
+    SecretKey::parse(&[7u8; 32]).unwrap()
+}
+
+fn sender() -> H160 {
+    let public = PublicKey::from_secret_key(&secret());
+    H160::from_slice(&keccak(&public.serialize()[1..])[12..])
+}
+
+fn uint(v: u64) -> Token {
+    Token::Uint(U256::from(v))
+}
+
+fn remote_tx() -> RemoteTransaction {
+    RemoteTransaction {
+        timestamp: NOW - 100,
+        remote_contract: H160::repeat_byte(0x11),
+        source_msg_sender: H160::repeat_byte(0x22),
+        source_beneficiary: H160::repeat_byte(0x33),
+        token: H160::repeat_byte(0x44),
+        amount: U256::from(1_000_000u64),
+        method: Bytes(vec![0xde, 0xad, 0xbe, 0xef]),
+        gas: 100_000,
+    }
+}
+
+/// A legacy transaction to `to`, signed for `chain_id` the way the offchain worker does
+fn signed_tx(chain_id: u64, nonce: u64, to: H160, input: Vec<u8>) -> Vec<u8> {
+    let mut tx = LegacyTransaction {
+        nonce: U256::from(nonce),
+        gas_price: U256::from(1_000_000_000u64),
+        gas_limit: U256::from(1_000_000u64),
+        action: TransactionAction::Call(to),
+        value: U256::zero(),
+        input,
+        signature: TransactionSignature::new(27, H256::repeat_byte(1), H256::repeat_byte(1))
+            .unwrap(),
+    };
+    let mut msg: LegacyTransactionMessage = tx.clone().into();
+    msg.chain_id = Some(chain_id);
+    let (sig, recovery_id) =
+        libsecp256k1::sign(&Message::parse(msg.hash().as_fixed_bytes()), &secret());
+    let rs = sig.serialize();
+    tx.signature = TransactionSignature::new(
+        chain_id * 2 + 35 + recovery_id.serialize() as u64,
+        H256::from_slice(&rs[..32]),
+        H256::from_slice(&rs[32..]),
+    )
+    .unwrap();
+    rlp::encode(&tx).to_vec()
+}
+
+fn mine_input(chain_id: u64, nonce: u64, txs: &[RemoteTransaction]) -> Vec<u8> {
+    encode_function_u8(
+        MINE_REMOTE_BLOCK,
+        &[
+            uint(chain_id),
+            uint(nonce),
+            Token::Array(txs.iter().map(|t| t.token()).collect()),
+            Token::FixedBytes(H256::from_low_u64_be(1).as_bytes().to_vec()),
+            uint(2147483647),
+            Token::Bytes(vec![1, 1]),
+        ],
+    )
+}
+
+fn finalize_input(chain_id: u64, nonce: u64, expiry: u64) -> Vec<u8> {
+    encode_function_u8(
+        FINALIZE_SINGLE_SIGNER,
+        &[
+            uint(chain_id),
+            uint(nonce),
+            Token::FixedBytes(vec![0; 32]),
+            Token::Array(vec![]),
+            Token::FixedBytes(vec![0; 32]),
+            uint(expiry),
+            Token::Bytes(vec![0; 96]),
+        ],
+    )
+}
+
+fn send(ledger: &mut MockLedger, input: Vec<u8>) -> H256 {
+    let nonce = ledger.transaction_count(sender());
+    ledger
+        .send_raw_transaction(&signed_tx(CHAIN, nonce, ledger_manager(), input))
+        .unwrap()
+}
+
+fn succeeded(ledger: &MockLedger, tx_hash: H256) -> bool {
+    !ledger.receipt(&tx_hash).unwrap().status.is_zero()
+}
+
+/// Decode a `LocalBlock` result the way the offchain worker does
+fn call_local_block(ledger: &MockLedger, signature: &[u8], chain_id: u64) -> Vec<Token> {
+    let out = ledger
+        .call(&encode_function_u8(signature, &[uint(chain_id)]))
+        .unwrap();
+    decode(&vec![ParamKind::Uint(256); 3], &out).unwrap()
+}
+
+fn remote_transactions_kind() -> ParamKind {
+    ParamKind::Array(Box::new(ParamKind::Tuple(vec![
+        Box::new(ParamKind::Uint(256)),
+        Box::new(ParamKind::Address),
+        Box::new(ParamKind::Address),
+        Box::new(ParamKind::Address),
+        Box::new(ParamKind::Address),
+        Box::new(ParamKind::Uint(256)),
+        Box::new(ParamKind::Bytes),
+        Box::new(ParamKind::Uint(256)),
+    ])))
+}
+
+fn local_block_kind() -> ParamKind {
+    ParamKind::Tuple(vec![
+        Box::new(ParamKind::Uint(256)),
+        Box::new(ParamKind::Uint(256)),
+        Box::new(ParamKind::Uint(256)),
+    ])
+}
+
+#[test]
+fn views_of_an_empty_ledger_return_zero_blocks() {
+    let ledger = ledger();
+    let ready = ledger
+        .call(&encode_function_u8(
+            IS_LOCAL_BLOCK_READY,
+            &[uint(SOURCE_CHAIN)],
+        ))
+        .unwrap();
+    assert_eq!(ready, vec![0u8; 32]);
+    for signature in [
+        LAST_LOCAL_BLOCK,
+        LAST_REMOTE_MINED_BLOCK,
+        LAST_FINALIZED_BLOCK,
+    ] {
+        assert_eq!(
+            call_local_block(&ledger, signature, SOURCE_CHAIN),
+            vec![uint(0), uint(0), uint(0)]
+        );
+    }
+    assert_eq!(ledger.call(&[1, 2, 3, 4]), Err(LedgerError::UnknownMethod));
+}
+
+#[test]
+fn local_blocks_are_returned_in_the_worker_layout() {
+    let mut ledger = ledger();
+    assert_eq!(
+        ledger.add_local_block(SOURCE_CHAIN, vec![remote_tx()]),
+        LocalBlock {
+            chain_id: SOURCE_CHAIN,
+            nonce: 1,
+            timestamp: NOW
+        }
+    );
+
+    let ready = ledger
+        .call(&encode_function_u8(
+            IS_LOCAL_BLOCK_READY,
+            &[uint(SOURCE_CHAIN)],
+        ))
+        .unwrap();
+    assert_eq!(U256::from_big_endian(&ready), U256::one());
+    assert_eq!(
+        call_local_block(&ledger, LAST_LOCAL_BLOCK, SOURCE_CHAIN),
+        vec![uint(SOURCE_CHAIN), uint(1), uint(NOW)]
+    );
+
+    let out = ledger
+        .call(&encode_function_u8(
+            LOCAL_BLOCK_BY_NONCE,
+            &[uint(SOURCE_CHAIN), uint(1)],
+        ))
+        .unwrap();
+    let dec = decode(&[local_block_kind(), remote_transactions_kind()], &out).unwrap();
+    assert_eq!(
+        dec,
+        vec![
+            Token::Tuple(vec![uint(SOURCE_CHAIN), uint(1), uint(NOW)]),
+            Token::Array(vec![remote_tx().token()]),
+        ]
+    );
+}
+
+#[test]
+fn mine_remote_block_records_the_mined_block() {
+    let mut ledger = ledger();
+    let tx_hash = send(&mut ledger, mine_input(SOURCE_CHAIN, 1, &[remote_tx()]));
+    assert!(succeeded(&ledger, tx_hash));
+    assert_eq!(ledger.receipt(&tx_hash).unwrap().from, sender());
+    assert_eq!(ledger.transaction_count(sender()), 1);
+
+    assert_eq!(
+        call_local_block(&ledger, LAST_REMOTE_MINED_BLOCK, SOURCE_CHAIN),
+        vec![uint(SOURCE_CHAIN), uint(1), uint(NOW)]
+    );
+    let out = ledger
+        .call(&encode_function_u8(
+            MINED_BLOCK_BY_NONCE,
+            &[uint(SOURCE_CHAIN), uint(1)],
+        ))
+        .unwrap();
+    let mined_block_kind = ParamKind::Tuple(vec![
+        Box::new(ParamKind::FixedBytes(32)),
+        Box::new(ParamKind::Address),
+        Box::new(ParamKind::Uint(256)),
+        Box::new(ParamKind::Uint(256)),
+        Box::new(local_block_kind()),
+    ]);
+    let dec = decode(&[mined_block_kind, remote_transactions_kind()], &out).unwrap();
+    let (block, txs) = ledger.mined_block_by_nonce(SOURCE_CHAIN, 1);
+    assert_ne!(block.block_hash, H256::zero());
+    assert_eq!(
+        dec,
+        vec![
+            Token::Tuple(vec![
+                Token::FixedBytes(block.block_hash.as_bytes().to_vec()),
+                Token::Address(sender()),
+                uint(0),
+                Token::Uint(remote_tx().amount),
+                Token::Tuple(vec![uint(SOURCE_CHAIN), uint(1), uint(NOW)]),
+            ]),
+            Token::Array(vec![remote_tx().token()]),
+        ]
+    );
+    assert_eq!(txs, vec![remote_tx()]);
+}
+
+#[test]
+fn mining_a_block_twice_reverts() {
+    let mut ledger = ledger();
+    send(&mut ledger, mine_input(SOURCE_CHAIN, 1, &[remote_tx()]));
+    let block = ledger.mined_block_by_nonce(SOURCE_CHAIN, 1);
+
+    let tx_hash = send(&mut ledger, mine_input(SOURCE_CHAIN, 1, &[]));
+    assert!(!succeeded(&ledger, tx_hash));
+    assert_eq!(ledger.mined_block_by_nonce(SOURCE_CHAIN, 1), block);
+    // The nonce is used even though the call reverted
+    assert_eq!(ledger.transaction_count(sender()), 2);
+    assert_eq!(
+        ledger.estimate_gas(sender(), &mine_input(SOURCE_CHAIN, 1, &[])),
+        Err(LedgerError::Reverted("block already mined"))
+    );
+}
+
+#[test]
+fn finalize_requires_a_mined_block() {
+    let mut ledger = ledger();
+    let tx_hash = send(&mut ledger, finalize_input(SOURCE_CHAIN, 1, NOW + 3600));
+    assert!(!succeeded(&ledger, tx_hash));
+
+    send(&mut ledger, mine_input(SOURCE_CHAIN, 1, &[remote_tx()]));
+    let tx_hash = send(&mut ledger, finalize_input(SOURCE_CHAIN, 1, NOW + 3600));
+    assert!(succeeded(&ledger, tx_hash));
+    assert_eq!(
+        call_local_block(&ledger, LAST_FINALIZED_BLOCK, SOURCE_CHAIN),
+        vec![uint(SOURCE_CHAIN), uint(1), uint(NOW)]
+    );
+
+    let tx_hash = send(&mut ledger, finalize_input(SOURCE_CHAIN, 1, NOW + 3600));
+    assert!(!succeeded(&ledger, tx_hash));
+}
+
+#[test]
+fn finalize_with_an_expired_signature_reverts() {
+    let mut ledger = ledger();
+    send(&mut ledger, mine_input(SOURCE_CHAIN, 1, &[]));
+    assert_eq!(
+        ledger.estimate_gas(sender(), &finalize_input(SOURCE_CHAIN, 1, NOW - 1)),
+        Err(LedgerError::Reverted("signature expired"))
+    );
+}
+
+#[test]
+fn calls_simulate_transactions_without_changing_the_ledger() {
+    let ledger = ledger();
+    assert_eq!(
+        ledger.simulate(sender(), &mine_input(SOURCE_CHAIN, 1, &[remote_tx()])),
+        Ok(vec![])
+    );
+    assert_eq!(ledger.last_remote_mined_block(SOURCE_CHAIN).nonce, 0);
+    assert_eq!(
+        ledger.simulate(sender(), &finalize_input(SOURCE_CHAIN, 1, NOW + 3600)),
+        Err(LedgerError::Reverted("block is not mined"))
+    );
+}
+
+#[test]
+fn invalid_transactions_are_rejected() {
+    let mut ledger = ledger();
+    let input = mine_input(SOURCE_CHAIN, 1, &[]);
+    assert_eq!(
+        ledger.send_raw_transaction(&signed_tx(SOURCE_CHAIN, 0, ledger_manager(), input.clone())),
+        Err(LedgerError::WrongChainId(Some(SOURCE_CHAIN)))
+    );
+    assert_eq!(
+        ledger.send_raw_transaction(&signed_tx(CHAIN, 1, ledger_manager(), input)),
+        Err(LedgerError::WrongNonce {
+            expected: 0,
+            got: U256::one()
+        })
+    );
+    assert_eq!(
+        ledger.send_raw_transaction(&[0xc0]),
+        Err(LedgerError::InvalidTransaction)
+    );
+    assert_eq!(ledger.transaction_count(sender()), 0);
+}
+
+#[tokio::test]
+async fn server_answers_the_worker_requests() {
+    let server = MockLedgerServer::start("127.0.0.1:0".parse().unwrap(), ledger())
+        .await
+        .unwrap();
+    let client = HttpClientBuilder::default().build(server.url()).unwrap();
+
+    let chain_id: String = client.request("eth_chainId", rpc_params![]).await.unwrap();
+    assert_eq!(chain_id, "0x61");
+
+    let block: LocalBlock = client
+        .request(
+            "qpmock_addLocalBlock",
+            rpc_params![SOURCE_CHAIN, vec![remote_tx()]],
+        )
+        .await
+        .unwrap();
+    assert_eq!(block.nonce, 1);
+
+    let call = serde_json::json!({
+        "data": Bytes(encode_function_u8(LAST_LOCAL_BLOCK, &[uint(SOURCE_CHAIN)])),
+        "to": ledger_manager(),
+    });
+    let out: Bytes = client
+        .request("eth_call", rpc_params![call, "latest"])
+        .await
+        .unwrap();
+    assert_eq!(
+        decode(&vec![ParamKind::Uint(256); 3], &out.0).unwrap(),
+        vec![uint(SOURCE_CHAIN), uint(1), uint(NOW)]
+    );
+
+    let raw = Bytes(signed_tx(
+        CHAIN,
+        0,
+        ledger_manager(),
+        mine_input(SOURCE_CHAIN, 1, &[remote_tx()]),
+    ));
+    let tx_hash: H256 = client
+        .request("eth_sendRawTransaction", rpc_params![raw])
+        .await
+        .unwrap();
+    let receipt: serde_json::Value = client
+        .request("eth_getTransactionReceipt", rpc_params![tx_hash])
+        .await
+        .unwrap();
+    assert_eq!(receipt["status"], "0x1");
+    assert_eq!(
+        server
+            .ledger()
+            .lock()
+            .last_remote_mined_block(SOURCE_CHAIN)
+            .nonce,
+        1
+    );
+
+    server.stop().unwrap();
+}
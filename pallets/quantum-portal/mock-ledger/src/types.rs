@@ -0,0 +1,155 @@
+//! Ledger manager records and the JSON-RPC types of the mock server
+
+use ethabi_nostd::Token;
+use ethereum_types::{H160, H256, U256, U64};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Bytes serialized as a `0x` prefixed hex string
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bytes(pub Vec<u8>);
+
+impl Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(&self.0)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let s = s.strip_prefix("0x").unwrap_or(&s);
+        hex::decode(s).map(Bytes).map_err(D::Error::custom)
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes(bytes)
+    }
+}
+
+/// A block of the ledger manager, `LocalBlock` in the contracts. Its chain id is the chain of the
+/// transactions, i.e. the target chain for a source block and the source chain for a mined one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalBlock {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub timestamp: u64,
+}
+
+impl LocalBlock {
+    /// ABI fields of the block. Static tuples are encoded in place, so they are flattened into
+    /// the surrounding tokens
+    pub fn tokens(&self) -> Vec<Token> {
+        vec![
+            Token::Uint(U256::from(self.chain_id)),
+            Token::Uint(U256::from(self.nonce)),
+            Token::Uint(U256::from(self.timestamp)),
+        ]
+    }
+}
+
+/// A cross chain transaction, `RemoteTransaction` in the contracts
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteTransaction {
+    pub timestamp: u64,
+    pub remote_contract: H160,
+    pub source_msg_sender: H160,
+    pub source_beneficiary: H160,
+    pub token: H160,
+    pub amount: U256,
+    pub method: Bytes,
+    pub gas: u64,
+}
+
+impl RemoteTransaction {
+    pub fn token(&self) -> Token {
+        Token::Tuple(vec![
+            Token::Uint(U256::from(self.timestamp)),
+            Token::Address(self.remote_contract),
+            Token::Address(self.source_msg_sender),
+            Token::Address(self.source_beneficiary),
+            Token::Address(self.token),
+            Token::Uint(self.amount),
+            Token::Bytes(self.method.0.clone()),
+            Token::Uint(U256::from(self.gas)),
+        ])
+    }
+
+    pub fn from_token(token: Token) -> Option<Self> {
+        match token.to_tuple()?.as_slice() {
+            [timestamp, remote_contract, source_msg_sender, source_beneficiary, token, amount, method, gas] => {
+                Some(RemoteTransaction {
+                    timestamp: timestamp.clone().to_uint()?.low_u64(),
+                    remote_contract: remote_contract.clone().to_address()?,
+                    source_msg_sender: source_msg_sender.clone().to_address()?,
+                    source_beneficiary: source_beneficiary.clone().to_address()?,
+                    token: token.clone().to_address()?,
+                    amount: amount.clone().to_uint()?,
+                    method: Bytes(method.clone().to_bytes()?),
+                    gas: gas.clone().to_uint()?.low_u64(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A block mined on this chain from the transactions of a source chain
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MinedBlock {
+    pub block_hash: H256,
+    pub miner: H160,
+    pub stake: U256,
+    pub total_value: U256,
+    pub block_metadata: LocalBlock,
+}
+
+impl MinedBlock {
+    /// ABI fields of the block, flattened like `LocalBlock::tokens`
+    pub fn tokens(&self) -> Vec<Token> {
+        let mut tokens = vec![
+            Token::FixedBytes(self.block_hash.as_bytes().to_vec()),
+            Token::Address(self.miner),
+            Token::Uint(self.stake),
+            Token::Uint(self.total_value),
+        ];
+        tokens.extend(self.block_metadata.tokens());
+        tokens
+    }
+}
+
+/// Receipt of a transaction sent to the mock chain. Reverted transactions have a zero status
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+    pub transaction_hash: H256,
+    pub block_hash: H256,
+    pub block_number: U64,
+    pub from: H160,
+    pub to: Option<H160>,
+    pub status: U64,
+}
+
+/// Parameters of `eth_call` and `eth_estimateGas`. The call data is read from `data`, or from
+/// `input` as sent by the offchain worker gas estimates
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallRequest {
+    pub from: Option<H160>,
+    pub to: Option<H160>,
+    pub data: Option<Bytes>,
+    pub input: Option<Bytes>,
+    pub value: Option<U256>,
+}
+
+impl CallRequest {
+    pub fn call_data(&self) -> &[u8] {
+        self.data
+            .as_ref()
+            .or(self.input.as_ref())
+            .map(|b| b.0.as_slice())
+            .unwrap_or_default()
+    }
+}
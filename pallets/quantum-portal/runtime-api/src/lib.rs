@@ -0,0 +1,29 @@
+//! Runtime API for querying the quantum portal state, a versioned alternative to reading the
+//! pallet storage keys directly.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use pallet_quantum_portal::qp_types::{QpBlockRecord, QpPairInfo};
+use parity_scale_codec::Codec;
+use sp_core::H160;
+use sp_std::prelude::*;
+
+sp_api::decl_runtime_apis! {
+    pub trait QuantumPortalApi<AccountId, BlockNumber>
+    where
+        AccountId: Codec,
+        BlockNumber: Codec,
+    {
+        /// Registered networks with their ledger manager contract
+        fn networks() -> Vec<(u64, H160)>;
+        /// Pairs with a mined or finalized block and their last nonces. The mined nonce is
+        /// self-reported by the miners and unverified.
+        fn pairs() -> Vec<QpPairInfo>;
+        /// Accounts allowed to mine
+        fn miners() -> Vec<AccountId>;
+        /// Accounts allowed to finalize
+        fn finalizers() -> Vec<AccountId>;
+        /// The `limit` most recent mined and finalized block reports, newest first
+        fn recent_records(limit: u32) -> Vec<QpBlockRecord<AccountId, BlockNumber>>;
+    }
+}
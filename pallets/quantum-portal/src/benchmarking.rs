@@ -0,0 +1,299 @@
+//! Benchmarks of the quantum portal pallet calls
+
+use super::*;
+use crate::{
+    chain_utils::ChainUtils,
+    qp_types::{QpBlockRecord, QpBlockRecordKind, QpFraudProof, QpWorkReport, QpWorkerRole},
+    Pallet as QuantumPortal,
+};
+use ferrum_primitives::OFFCHAIN_SIGNER_KEY_TYPE;
+use frame_benchmarking::{account, benchmarks, whitelisted_caller};
+use frame_support::{
+    traits::{Currency, EnsureOrigin},
+    BoundedVec,
+};
+use frame_system::RawOrigin;
+use sp_core::{ecdsa, H160, H256};
+use sp_runtime::traits::{Bounded, Zero};
+use sp_std::prelude::*;
+
+const SEED: u32 = 0;
+const REMOTE_CHAIN: u64 = 4;
+const LOCAL_CHAIN: u64 = 97;
+
+fn funded_account<T: Config>(name: &'static str, index: u32) -> T::AccountId {
+    let who = account(name, index, SEED);
+    T::Currency::make_free_balance_be(&who, BalanceOf::<T>::max_value() / 2u32.into());
+    who
+}
+
+fn register_pair<T: Config>() {
+    RegisteredNetworks::<T>::insert(REMOTE_CHAIN, H160::repeat_byte(1));
+    RegisteredNetworks::<T>::insert(LOCAL_CHAIN, H160::repeat_byte(2));
+}
+
+/// Fill the recent records so pushing one more drops the oldest
+fn fill_records<T: Config>(worker: &T::AccountId) {
+    let record = QpBlockRecord {
+        kind: QpBlockRecordKind::Mined,
+        remote_chain: REMOTE_CHAIN,
+        local_chain: LOCAL_CHAIN,
+        nonce: 0,
+        worker: worker.clone(),
+        block_number: Zero::zero(),
+    };
+    let records = sp_std::vec![record; MaxRecentRecords::get() as usize];
+    RecentBlockRecords::<T>::put(BoundedVec::try_from(records).unwrap());
+}
+
+fn signer_address(public: &ecdsa::Public) -> H160 {
+    H160::from_slice(&ChainUtils::eth_address_from_public_key(public.as_ref()))
+}
+
+/// Register a worker signing with a new key of the offchain signer keystore
+fn register_with_key<T: Config>(
+    role: QpWorkerRole,
+    index: u32,
+) -> Result<(T::AccountId, ecdsa::Public), &'static str> {
+    let public = sp_io::crypto::ecdsa_generate(OFFCHAIN_SIGNER_KEY_TYPE, None);
+    let signer = signer_address(&public);
+    let who = match role {
+        QpWorkerRole::Miner => {
+            let who = funded_account::<T>("miner", index);
+            QuantumPortal::<T>::register_miner(RawOrigin::Signed(who.clone()).into(), signer)?;
+            who
+        }
+        QpWorkerRole::Finalizer => {
+            let who = funded_account::<T>("finalizer", index);
+            QuantumPortal::<T>::register_finalizer(RawOrigin::Signed(who.clone()).into(), signer)?;
+            who
+        }
+    };
+    Ok((who, public))
+}
+
+fn sign_report<T: Config>(
+    public: &ecdsa::Public,
+    kind: QpBlockRecordKind,
+    report: &QpWorkReport,
+) -> Result<[u8; 65], &'static str> {
+    let hash = report.signing_hash(kind, &QuantumPortal::<T>::genesis_hash());
+    let signature = sp_io::crypto::ecdsa_sign_prehashed(
+        OFFCHAIN_SIGNER_KEY_TYPE,
+        public,
+        hash.as_fixed_bytes(),
+    )
+    .ok_or("could not sign the report")?;
+    Ok(signature.0)
+}
+
+benchmarks! {
+    register_network {
+    }: _(RawOrigin::Root, REMOTE_CHAIN, H160::repeat_byte(1))
+    verify {
+        assert!(RegisteredNetworks::<T>::contains_key(REMOTE_CHAIN));
+    }
+
+    deregister_network {
+        register_pair::<T>();
+    }: _(RawOrigin::Root, REMOTE_CHAIN)
+    verify {
+        assert!(!RegisteredNetworks::<T>::contains_key(REMOTE_CHAIN));
+    }
+
+    report_mined_block {
+        let (miner, public) = register_with_key::<T>(QpWorkerRole::Miner, 0)?;
+        register_pair::<T>();
+        fill_records::<T>(&miner);
+        let report = QpWorkReport {
+            remote_chain: REMOTE_CHAIN,
+            local_chain: LOCAL_CHAIN,
+            nonce: 1,
+            miner: signer_address(&public),
+        };
+        let signature = sign_report::<T>(&public, QpBlockRecordKind::Mined, &report)?;
+    }: _(RawOrigin::None, report, signature)
+    verify {
+        assert_eq!(LastMinedNonce::<T>::get(REMOTE_CHAIN, LOCAL_CHAIN), 1);
+    }
+
+    report_finalized_block {
+        // Pending attestations of the other finalizers, all of them credited with the caller
+        let f in 0 .. T::MaxFinalizers::get() - 1;
+
+        let (miner, miner_key) = register_with_key::<T>(QpWorkerRole::Miner, 0)?;
+        register_pair::<T>();
+        UnconfirmedMinedBlocks::<T>::insert((REMOTE_CHAIN, LOCAL_CHAIN), &miner, 1);
+        let mut attestations = Vec::new();
+        for i in 1 ..= f {
+            let finalizer = funded_account::<T>("finalizer", i);
+            QuantumPortal::<T>::register_finalizer(
+                RawOrigin::Signed(finalizer.clone()).into(),
+                H160::from_low_u64_be(i as u64),
+            )?;
+            attestations.push((finalizer, 1, signer_address(&miner_key)));
+        }
+        PendingFinalizations::<T>::insert(
+            (REMOTE_CHAIN, LOCAL_CHAIN),
+            BoundedVec::try_from(attestations).unwrap(),
+        );
+        let (finalizer, public) = register_with_key::<T>(QpWorkerRole::Finalizer, 0)?;
+        fill_records::<T>(&finalizer);
+        let report = QpWorkReport {
+            remote_chain: REMOTE_CHAIN,
+            local_chain: LOCAL_CHAIN,
+            nonce: 1,
+            miner: signer_address(&miner_key),
+        };
+        let signature = sign_report::<T>(&public, QpBlockRecordKind::Finalized, &report)?;
+    }: _(RawOrigin::None, report, signature)
+    verify {
+        assert_eq!(LastFinalizedNonce::<T>::get(REMOTE_CHAIN, LOCAL_CHAIN), 1);
+        assert!(!UnconfirmedMinedBlocks::<T>::contains_key((REMOTE_CHAIN, LOCAL_CHAIN), &miner));
+    }
+
+    register_miner {
+        let caller = funded_account::<T>("miner", 0);
+    }: _(RawOrigin::Signed(caller.clone()), H160::repeat_byte(1))
+    verify {
+        assert!(QuantumPortal::<T>::is_miner(&caller));
+    }
+
+    register_finalizer {
+        let caller = funded_account::<T>("finalizer", 0);
+    }: _(RawOrigin::Signed(caller.clone()), H160::repeat_byte(1))
+    verify {
+        assert!(QuantumPortal::<T>::is_finalizer(&caller));
+    }
+
+    unbond {
+        let caller = funded_account::<T>("miner", 0);
+        QuantumPortal::<T>::register_miner(
+            RawOrigin::Signed(caller.clone()).into(),
+            H160::repeat_byte(1),
+        )?;
+    }: _(RawOrigin::Signed(caller.clone()))
+    verify {
+        assert!(!QuantumPortal::<T>::is_miner(&caller));
+    }
+
+    withdraw_unbonded {
+        let caller = funded_account::<T>("miner", 0);
+        QuantumPortal::<T>::register_miner(
+            RawOrigin::Signed(caller.clone()).into(),
+            H160::repeat_byte(1),
+        )?;
+        QuantumPortal::<T>::unbond(RawOrigin::Signed(caller.clone()).into())?;
+        frame_system::Pallet::<T>::set_block_number(
+            frame_system::Pallet::<T>::block_number() + T::UnbondingDelay::get(),
+        );
+    }: _(RawOrigin::Signed(caller.clone()))
+    verify {
+        assert!(!Workers::<T>::contains_key(&caller));
+    }
+
+    submit_fraud_proof {
+        // Attestations
+        let a in 1 .. MAX_FRAUD_ATTESTATIONS as u32;
+        // Active finalizers the attestations are checked against
+        let f in 1 .. T::MaxFinalizers::get();
+
+        let miner = funded_account::<T>("miner", 0);
+        let miner_signer = H160::repeat_byte(0xaa);
+        QuantumPortal::<T>::register_miner(RawOrigin::Signed(miner.clone()).into(), miner_signer)?;
+        UnconfirmedMinedBlocks::<T>::insert((REMOTE_CHAIN, LOCAL_CHAIN), miner, 1);
+
+        let proof = QpFraudProof {
+            remote_chain: REMOTE_CHAIN,
+            local_chain: LOCAL_CHAIN,
+            nonce: 1,
+            miner: miner_signer,
+            mined_txs_hash: H256::repeat_byte(1),
+            source_txs_hash: H256::repeat_byte(2),
+        };
+        let hash = proof.attestation_hash(&QuantumPortal::<T>::genesis_hash());
+        let mut signatures = Vec::new();
+        for i in 0 .. f {
+            let public = sp_io::crypto::ecdsa_generate(OFFCHAIN_SIGNER_KEY_TYPE, None);
+            let signature = sp_io::crypto::ecdsa_sign_prehashed(
+                OFFCHAIN_SIGNER_KEY_TYPE,
+                &public,
+                hash.as_fixed_bytes(),
+            )
+            .ok_or("could not sign the attestation")?;
+            let signer = H160::from_slice(&ChainUtils::eth_address_from_public_key(public.as_ref()));
+            let finalizer = funded_account::<T>("finalizer", i);
+            QuantumPortal::<T>::register_finalizer(RawOrigin::Signed(finalizer).into(), signer)?;
+            signatures.push(signature.0);
+        }
+        // The quorum always attests so the proof is accepted, the remaining attestations repeat
+        // the signatures, each of them is still recovered
+        let quorum = T::FraudProofQuorum::get().mul_ceil(f).max(1);
+        let attestations: Vec<[u8; 65]> = signatures
+            .iter()
+            .cycle()
+            .take(a.max(quorum) as usize)
+            .cloned()
+            .collect();
+        let reporter: T::AccountId = whitelisted_caller();
+    }: _(RawOrigin::Signed(reporter), proof, attestations)
+    verify {
+        assert!(ProvenFrauds::<T>::contains_key((REMOTE_CHAIN, LOCAL_CHAIN, 1)));
+    }
+
+    claim_rewards {
+        let caller: T::AccountId = whitelisted_caller();
+        let amount = T::Currency::minimum_balance() * 10u32.into();
+        T::Currency::make_free_balance_be(
+            &QuantumPortal::<T>::pot_account(),
+            BalanceOf::<T>::max_value() / 2u32.into(),
+        );
+        PendingRewards::<T>::insert(&caller, amount);
+    }: _(RawOrigin::Signed(caller.clone()))
+    verify {
+        assert!(PendingRewards::<T>::get(&caller).is_zero());
+    }
+
+    slash {
+        let who = funded_account::<T>("miner", 0);
+        QuantumPortal::<T>::register_miner(
+            RawOrigin::Signed(who.clone()).into(),
+            H160::repeat_byte(1),
+        )?;
+        let origin = T::SlashOrigin::successful_origin();
+        // Slash below the required bond so the worker is also deactivated
+        let amount = T::MinerBond::get();
+    }: _<T::RuntimeOrigin>(origin, who.clone(), amount)
+    verify {
+        assert!(!QuantumPortal::<T>::is_miner(&who));
+    }
+
+    end_era {
+        CurrentEra::<T>::put(1);
+    }: {
+        QuantumPortal::<T>::end_era();
+    }
+    verify {
+        assert_eq!(PayoutEra::<T>::get(), Some(1));
+    }
+
+    pay_era_rewards {
+        // Workers paid
+        let w in 0 .. MAX_PAYOUTS_PER_BLOCK;
+
+        for i in 0 .. w {
+            let worker: T::AccountId = account("worker", i, SEED);
+            EraWork::<T>::insert(0, worker, 1);
+        }
+        EraTotalWork::<T>::insert(0, w as u64);
+        CurrentEra::<T>::put(1);
+        PayoutEra::<T>::put(0);
+    }: {
+        QuantumPortal::<T>::pay_era_rewards();
+    }
+    verify {
+        assert_eq!(EraWork::<T>::iter_prefix(0).count(), 0);
+    }
+
+    impl_benchmark_test_suite!(QuantumPortal, crate::mock::new_test_ext(), crate::mock::Test);
+}
@@ -1,16 +1,27 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use crate::chain_utils::{ChainRequestError, ChainRequestResult, ChainUtils, JsonSer, ToJson};
+use crate::{
+    chain_utils::{
+        ChainRequestError, ChainRequestResult, ChainUtils, HttpAttemptError, JsonSer, RevertReason,
+        ToJson,
+    },
+    qp_types::QpRpcAuth,
+    LOG_TARGET_RPC,
+};
 use ethereum::TransactionV2;
 use serde::{Deserialize, Deserializer, Serialize};
 use sp_core::H256;
 use sp_runtime::{
     codec::{Decode, Encode},
-    offchain::{http, Duration},
+    offchain::{http, Timestamp},
 };
 use sp_std::{prelude::*, str};
 
 const FETCH_TIMEOUT_PERIOD: u64 = 30000; // in milli-seconds
+/// Attempts of a json rpc request, when its failures are retryable
+const MAX_FETCH_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: u64 = 500; // in milli-seconds
+const RETRY_JITTER_PERCENT: u64 = 50;
 
 pub fn de_string_list_to_bytes_list<'de, D>(de: D) -> Result<Vec<Vec<u8>>, D::Error>
 where
@@ -49,13 +60,75 @@ pub struct JsonRpcResponse<T> {
     pub response: T,
 }
 
+/// The `error` member of a failed json rpc response
+#[derive(Debug, Deserialize)]
+struct JsonRpcErrorResponse {
+    error: JsonRpcErrorObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcErrorObject {
+    code: i64,
+    #[serde(deserialize_with = "de_string_to_bytes")]
+    message: Vec<u8>,
+    /// Hex encoded output of a reverted call, not sent by every node
+    #[serde(default, deserialize_with = "de_string_to_bytes")]
+    data: Vec<u8>,
+}
+
+/// Code of the reverted calls and estimations, per EIP-1474. Nodes also use -32000 with an
+/// `execution reverted` message
+const EXECUTION_REVERTED: i64 = 3;
+
+impl From<JsonRpcErrorObject> for ChainRequestError {
+    fn from(e: JsonRpcErrorObject) -> Self {
+        let reverted = e.code == EXECUTION_REVERTED || e.message.starts_with(b"execution reverted");
+        if !reverted {
+            return ChainRequestError::JsonRpcError(e.message);
+        }
+        let reason = match ChainUtils::hex_to_bytes(&e.data) {
+            Ok(data) if !data.is_empty() => RevertReason::from_revert_data(&data),
+            _ => RevertReason::from_message(&e.message),
+        };
+        ChainRequestError::Reverted(reason)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CallResponse {
     #[serde(deserialize_with = "de_string_to_bytes")]
     pub result: Vec<u8>,
 }
 
-#[allow(dead_code)]
+/// Block whose state a call reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockId {
+    #[default]
+    Latest,
+    Number(u64),
+    /// A block by hash, as defined by EIP-1898. Unlike a number, it cannot silently point to
+    /// another block after a reorg
+    Hash(H256),
+}
+
+impl BlockId {
+    /// The block parameter of a json rpc request
+    pub fn to_param(&self) -> Vec<u8> {
+        match self {
+            BlockId::Latest => b"\"latest\"".to_vec(),
+            BlockId::Number(n) => ChainUtils::wrap_in_quotes(&ChainUtils::u64_to_hex_0x(*n)),
+            BlockId::Hash(hash) => JsonSer::new()
+                .start()
+                .string(
+                    "blockHash",
+                    str::from_utf8(&ChainUtils::h256_to_hex_0x(hash)).unwrap(),
+                )
+                .end()
+                .to_vec(),
+        }
+    }
+}
+
 pub enum TransactionStatus {
     NotFound,
     Pending,
@@ -124,7 +197,14 @@ impl ToJson for TransactionV2 {
     }
 }
 
-fn fetch_json_rpc_body(base_url: &str, req: &JsonRpcRequest) -> Result<Vec<u8>, ChainRequestError> {
+/// Send the request, retrying the failures that may go away. With a `deadline`, in unix millis,
+/// each attempt is cut short at it and no attempt is started past it
+fn fetch_json_rpc_body(
+    base_url: &str,
+    auth: &QpRpcAuth,
+    deadline: Option<u64>,
+    req: &JsonRpcRequest,
+) -> Result<Vec<u8>, ChainRequestError> {
     let mut params = JsonSer::new();
     req.params.iter().for_each(|p| {
         params.arr_val(str::from_utf8(p.as_slice()).unwrap());
@@ -141,95 +221,158 @@ fn fetch_json_rpc_body(base_url: &str, req: &JsonRpcRequest) -> Result<Vec<u8>,
         )
         .end()
         .to_vec();
-    let json_req_str = str::from_utf8(&json_req_s).unwrap();
-    log::info!("About to submit {}", json_req_str);
-    let request: http::Request<Vec<&[u8]>> =
-        http::Request::post(base_url, Vec::from([json_req_s.as_slice()]));
-    let timeout = sp_io::offchain::timestamp().add(Duration::from_millis(FETCH_TIMEOUT_PERIOD));
-
-    let pending = request
-        // .deadline(timeout) // Setting the timeout time
-        .add_header("Content-Type", "application/json")
-        .send() // Sending the request out by the host
-        .map_err(|e| {
-            log::info!("An ERROR HAPPNED!");
-            // println!("ERRROOOORRRR {:?}", e);
-            log::error!("{:?}", e);
-            ChainRequestError::ErrorGettingJsonRpcResponse
-        })?;
+    let method = str::from_utf8(&req.method).unwrap();
+    log::trace!(
+        target: LOG_TARGET_RPC,
+        "About to submit {}",
+        str::from_utf8(&json_req_s).unwrap()
+    );
 
-    // By default, the http request is async from the runtime perspective. So we are asking the
-    //   runtime to wait here
-    // The returning value here is a `Result` of `Result`, so we are unwrapping it twice by two `?`
-    //   ref: https://docs.substrate.io/rustdocs/latest/sp_runtime/offchain/http/struct.PendingRequest.html#method.try_wait
-    let response_a = pending.try_wait(timeout);
-    // let response_0 = pending.wait();
-    let response_0 = match response_a {
-        Ok(r) => {
-            // println!("Result got");
-            // log::info!("Result got");
-            Ok(r)
-        }
-        Err(e) => {
-            // println!("ERRROOOORRRR AFDTER {:?}", e);
-            log::info!("An ERROR HAPPNED!");
-            log::info!("An ERROR HAPPNED UYPOOOOOOOOOOO ! {:?}", e);
-            Err(ChainRequestError::ErrorGettingJsonRpcResponse)
-        }
-    }?;
-    let response = match response_0 {
-        Ok(r) => {
-            // log::info!("Result got 2");
-            Ok(r)
+    let mut attempts = Vec::new();
+    loop {
+        let now = sp_io::offchain::timestamp().unix_millis();
+        let timeout = match deadline {
+            Some(d) if now >= d => {
+                log::warn!(
+                    target: LOG_TARGET_RPC,
+                    "{} request not sent, the run is out of time",
+                    method
+                );
+                attempts.push(HttpAttemptError::Timeout);
+                return Err(ChainRequestError::HttpRequestFailed(attempts));
+            }
+            Some(d) => d.min(now.saturating_add(FETCH_TIMEOUT_PERIOD)),
+            None => now.saturating_add(FETCH_TIMEOUT_PERIOD),
+        };
+        let err = match send_json_rpc_request(
+            base_url,
+            auth,
+            &json_req_s,
+            Timestamp::from_unix_millis(timeout),
+        ) {
+            Ok(body) => return Ok(body),
+            Err(e) => e,
+        };
+        attempts.push(err);
+        let delay = retry_delay(attempts.len() as u32, &sp_io::offchain::random_seed());
+        let retry_at = sp_io::offchain::timestamp()
+            .unix_millis()
+            .saturating_add(delay);
+        if !err.is_retryable()
+            || attempts.len() as u32 >= MAX_FETCH_ATTEMPTS
+            || deadline.map_or(false, |d| retry_at >= d)
+        {
+            log::warn!(
+                target: LOG_TARGET_RPC,
+                "{} request failed after {} attempt(s): {:?}",
+                method,
+                attempts.len(),
+                attempts
+            );
+            return Err(ChainRequestError::HttpRequestFailed(attempts));
         }
-        Err(e) => {
-            log::info!("An ERROR HAPPNED 2!");
-            log::info!("An ERROR HAPPNED UYPOOOOOOOOOOO 2 ! {:?}", e);
-            Err(ChainRequestError::ErrorGettingJsonRpcResponse)
+        log::debug!(
+            target: LOG_TARGET_RPC,
+            "{} request failed with {:?}, retrying in {}ms",
+            method,
+            err,
+            delay
+        );
+        sp_io::offchain::sleep_until(Timestamp::from_unix_millis(retry_at));
+    }
+}
+
+/// A single attempt of the request, which must complete before `timeout`
+fn send_json_rpc_request(
+    base_url: &str,
+    auth: &QpRpcAuth,
+    body: &[u8],
+    timeout: Timestamp,
+) -> Result<Vec<u8>, HttpAttemptError> {
+    let mut request = http::Request::post(base_url, vec![body])
+        .deadline(timeout)
+        .add_header("Content-Type", "application/json");
+    for (name, value) in auth.http_headers() {
+        match (str::from_utf8(&name), str::from_utf8(&value)) {
+            (Ok(name), Ok(value)) => request = request.add_header(name, value),
+            // Never log the value, it is a secret
+            _ => log::warn!(
+                target: LOG_TARGET_RPC,
+                "Skipping the non utf8 header {}",
+                str::from_utf8(&name).unwrap_or("<invalid>")
+            ),
         }
-    }?;
-    // let response = pending
-    // 	.try_wait(timeout)
-    // 	.map_err(|e| {
-    // 		log::info!("An ERROR HAPPNED!");
-    // 		log::info!("An ERROR HAPPNED UYPOOOOOOOOOOO ! {:?}", e);
-    // 		log::error!("{:?}", e);
-    // 		ChainRequestError::ErrorGettingJsonRpcResponse
-    // 	})?
-    // 	.map_err(|e| {
-    // 		log::info!("An ERROR HAPPNED22!");
-    // 		log::error!("{:?}", e);
-    // 		ChainRequestError::ErrorGettingJsonRpcResponse
-    // 	})?;
-
-    // log::info!("Response is ready!");
-    let body = response.body().collect::<Vec<u8>>();
-    log::info!(
+    }
+    let pending = request.send()?;
+
+    // By default, the http request is async from the runtime perspective. So we are asking the
+    //   runtime to wait here. The outer error is the deadline, the inner one the request failure
+    //   ref: https://docs.substrate.io/rustdocs/latest/sp_runtime/offchain/http/struct.PendingRequest.html#method.try_wait
+    let response = pending
+        .try_wait(timeout)
+        .map_err(|_| HttpAttemptError::Timeout)??;
+
+    let mut body_reader = response.body();
+    let body = body_reader.by_ref().collect::<Vec<u8>>();
+    // The body stops early when reading it fails, e.g. on the deadline
+    if let Some(e) = body_reader.error() {
+        return Err(e.into());
+    }
+    log::trace!(
+        target: LOG_TARGET_RPC,
         "Response code got : {}-{}",
         &response.code,
-        str::from_utf8(body.as_slice()).unwrap()
+        str::from_utf8(body.as_slice()).unwrap_or("<non utf8 body>")
     );
 
     if response.code != 200 {
-        log::error!("Unexpected http request status code: {}", response.code);
-        return Err(ChainRequestError::ErrorGettingJsonRpcResponse);
+        return Err(HttpAttemptError::Status(response.code));
     }
-
     Ok(body)
 }
 
-pub fn fetch_json_rpc<T>(base_url: &str, req: &JsonRpcRequest) -> Result<Box<T>, ChainRequestError>
+/// Backoff before the given retry (1 for the first one), doubling with each retry, plus a jitter
+/// of up to `RETRY_JITTER_PERCENT` of it taken from `seed`, so that workers failing at the same
+/// time do not retry in lockstep. The worker passes `sp_io::offchain::random_seed`, the node's
+/// local randomness: it only spreads the retries, so it does not need `T::Randomness`
+pub(crate) fn retry_delay(retry: u32, seed: &[u8; 32]) -> u64 {
+    let backoff = RETRY_BASE_DELAY << retry.saturating_sub(1).min(16);
+    let mut random = [0u8; 8];
+    random.copy_from_slice(&seed[..8]);
+    let jitter = u64::from_le_bytes(random) % (backoff * RETRY_JITTER_PERCENT / 100 + 1);
+    backoff + jitter
+}
+
+pub fn fetch_json_rpc<T>(
+    base_url: &str,
+    auth: &QpRpcAuth,
+    deadline: Option<u64>,
+    req: &JsonRpcRequest,
+) -> Result<Box<T>, ChainRequestError>
 where
     T: for<'de> Deserialize<'de>,
 {
-    // println!("fetchin {} : {:?}", base_url, req);
-    let body = fetch_json_rpc_body(base_url, req)?;
-    // println!("Response body got : {}", str::from_utf8(&body).unwrap());
-    // log::info!("Response body got : {}", str::from_utf8(&body).unwrap());
+    let body = fetch_json_rpc_body(base_url, auth, deadline, req)?;
+    // A failed request has an `error` member instead of the result
+    if let Ok(e) = serde_json::from_slice::<JsonRpcErrorResponse>(&body) {
+        log::debug!(
+            target: LOG_TARGET_RPC,
+            "{} failed with code {}: {}",
+            str::from_utf8(&req.method).unwrap(),
+            e.error.code,
+            str::from_utf8(&e.error.message).unwrap_or("<non utf8 message>")
+        );
+        return Err(e.error.into());
+    }
     let rv: serde_json::Result<T> = serde_json::from_slice(&body);
     match rv {
         Err(err) => {
-            log::error!("Error while parsing json {:?}", err);
+            log::warn!(
+                target: LOG_TARGET_RPC,
+                "Error while parsing {} response {:?}",
+                str::from_utf8(&req.method).unwrap(),
+                err
+            );
             Err(ChainRequestError::ErrorGettingJsonRpcResponse)
         }
         Ok(v) => Ok(Box::new(v)),
@@ -261,30 +404,38 @@ pub struct GetTransactionReceiptResponse {
 pub struct ChainQueries /* <T: Config> */ {}
 
 impl ChainQueries {
-    #[allow(dead_code)]
-    pub fn chain_id(url: &str) -> Result<u32, ChainRequestError> {
-        log::info!("About to get chain_id {}", url);
+    pub fn chain_id(
+        url: &str,
+        auth: &QpRpcAuth,
+        deadline: Option<u64>,
+    ) -> Result<u64, ChainRequestError> {
+        log::debug!(
+            target: LOG_TARGET_RPC,
+            "About to get chain_id {}",
+            ChainUtils::redact_url(url.as_bytes())
+        );
         let req = JsonRpcRequest {
             id: 1,
             params: Vec::new(),
             method: b"eth_chainId".to_vec(),
         };
         // log::info!("Have request {:?}", &req);
-        let res: Box<GetChainIdResponse> = fetch_json_rpc(url, &req)?;
-        log::info!("Result is {:?}", &res);
-        let chain_id = ChainUtils::hex_to_u64(&res.result)?;
-        Ok(chain_id as u32)
+        let res: Box<GetChainIdResponse> = fetch_json_rpc(url, auth, deadline, &req)?;
+        log::trace!(target: LOG_TARGET_RPC, "Result is {:?}", &res);
+        ChainUtils::hex_to_u64(&res.result)
     }
 
     pub fn get_transaction_receipt(
         url: &str,
+        auth: &QpRpcAuth,
+        deadline: Option<u64>,
         tx_id: &H256,
     ) -> ChainRequestResult<Option<GetTransactionReceiptResponseData>> {
-        log::info!("TX_ID is: {:?}", &tx_id.0);
         let tx_id = ChainUtils::h256_to_hex_0x(tx_id);
-        log::info!(
+        log::debug!(
+            target: LOG_TARGET_RPC,
             "About to get eth_getTransactionReceipt {}: {}",
-            url,
+            ChainUtils::redact_url(url.as_bytes()),
             str::from_utf8(tx_id.as_slice()).unwrap()
         );
 
@@ -294,18 +445,51 @@ impl ChainQueries {
             method: b"eth_getTransactionReceipt".to_vec(),
         };
         // log::info!("Have request {:?}", &req);
-        let res: Box<GetTransactionReceiptResponse> = fetch_json_rpc(url, &req)?;
-        log::info!("Result is {:?}", &res);
+        let res: Box<GetTransactionReceiptResponse> = fetch_json_rpc(url, auth, deadline, &req)?;
+        log::trace!(target: LOG_TARGET_RPC, "Result is {:?}", &res);
         Ok(res.result)
     }
 
+    pub fn block_number(
+        url: &str,
+        auth: &QpRpcAuth,
+        deadline: Option<u64>,
+    ) -> ChainRequestResult<u64> {
+        let req = JsonRpcRequest {
+            id: 1,
+            params: Vec::new(),
+            method: b"eth_blockNumber".to_vec(),
+        };
+        let res: Box<CallResponse> = fetch_json_rpc(url, auth, deadline, &req)?;
+        ChainUtils::hex_to_u64(&res.result)
+    }
+
+    /// Status of a transaction. A receipt in one of the last `confirmations` blocks may still be
+    /// reorged out, so the transaction stays pending until it is deeper
     pub fn get_transaction_status(
         url: &str,
+        auth: &QpRpcAuth,
+        deadline: Option<u64>,
         tx_id: &H256,
+        confirmations: u64,
     ) -> ChainRequestResult<TransactionStatus> {
-        let rv = Self::get_transaction_receipt(url, tx_id)?;
+        let rv = Self::get_transaction_receipt(url, auth, deadline, tx_id)?;
         let res = match rv {
             None => TransactionStatus::NotFound,
+            Some(tx)
+                if confirmations > 0
+                    && ChainUtils::hex_to_u64(&tx.blockNumber)?.saturating_add(confirmations)
+                        > Self::block_number(url, auth, deadline)? =>
+            {
+                log::debug!(
+                    target: LOG_TARGET_RPC,
+                    "Transaction {:?} is in block {}, waiting for {} confirmations",
+                    tx_id,
+                    str::from_utf8(&tx.blockNumber).unwrap_or_default(),
+                    confirmations
+                );
+                TransactionStatus::Pending
+            }
             Some(tx) => {
                 let status = ChainUtils::hex_to_u64(tx.status.as_slice())?;
                 if status == 1 {
@@ -3,9 +3,11 @@ use parity_scale_codec::Encode;
 
 pub struct ChainUtils;
 // use crate::OFFCHAIN_SIGNER_KEY_TYPE;
-use ethabi_nostd::{Address, H256, U256}; //vec::{Vec};
+use ethabi_nostd::{decoder::decode, Address, ParamKind, H256, U256}; //vec::{Vec};
 
+use crate::{LOG_TARGET, LOG_TARGET_SIGN};
 use numtoa::NumToA;
+use sp_runtime::offchain::{http, HttpError};
 use sp_std::{prelude::*, str};
 use tiny_keccak::{Hasher, Keccak};
 
@@ -18,6 +20,141 @@ pub enum ChainRequestError {
     RemoteBlockAlreadyMined,
     JsonRpcError(Vec<u8>),
     InvalidHexCharacter,
+    /// The runtime has no native EVM for a `Native` network
+    NativeEvmUnavailable,
+    /// A native EVM call did not succeed, with the data it returned
+    NativeEvmCallFailed(Vec<u8>),
+    /// The transaction pool did not accept a native EVM transaction
+    NativeEvmSubmissionFailed,
+    /// A json rpc http request failed, with the failure of each attempt in order
+    HttpRequestFailed(Vec<HttpAttemptError>),
+    /// The rpc endpoint of a network serves another chain than the configured one
+    ChainIdMismatch {
+        expected: u64,
+        actual: u64,
+    },
+    /// No network is configured for the chain of a pair, or it was skipped as misconfigured
+    NetworkNotConfigured(u64),
+    /// A call or a gas estimation reverted
+    Reverted(RevertReason),
+    /// The ledger manager would reject the transaction, so it was not sent
+    TransactionSkipped(RevertReason),
+}
+
+/// Why the ledger manager rejects a call, classified from its revert reason
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RevertReason {
+    /// The block was mined before, usually by another miner
+    AlreadyMined,
+    /// The block was finalized before
+    AlreadyFinalized,
+    /// The signer is not allowed to mine or finalize
+    NotAuthorized,
+    /// The signature or the block expired
+    Expired,
+    /// Any other reason, as returned by the node
+    Other(Vec<u8>),
+}
+
+impl RevertReason {
+    /// Selector of `Error(string)`, the encoding of the `require` and `revert` messages
+    const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+    /// Classify a revert message, e.g. `execution reverted: block already mined`
+    pub fn from_message(message: &[u8]) -> Self {
+        let reason = message
+            .strip_prefix(b"execution reverted".as_slice())
+            .map(|r| r.strip_prefix(b": ".as_slice()).unwrap_or(r))
+            .unwrap_or(message);
+        let lower = reason.to_ascii_lowercase();
+        let contains = |needle: &[u8]| lower.windows(needle.len()).any(|w| w == needle);
+        if contains(b"already mined") {
+            RevertReason::AlreadyMined
+        } else if contains(b"already finalized") {
+            RevertReason::AlreadyFinalized
+        } else if contains(b"not authorized")
+            || contains(b"unauthorized")
+            || contains(b"not allowed")
+        {
+            RevertReason::NotAuthorized
+        } else if contains(b"expired") {
+            RevertReason::Expired
+        } else {
+            RevertReason::Other(reason.to_vec())
+        }
+    }
+
+    /// Classify the output of a reverted call. Only `Error(string)` carries a message, other
+    /// outputs are kept as they are
+    pub fn from_revert_data(data: &[u8]) -> Self {
+        let message = data
+            .strip_prefix(Self::ERROR_SELECTOR.as_slice())
+            .and_then(|args| decode(&[ParamKind::String], args).ok())
+            .and_then(|tokens| tokens.into_iter().next())
+            .and_then(|token| token.to_string());
+        match message {
+            Some(message) => Self::from_message(&message),
+            None => RevertReason::Other(data.to_vec()),
+        }
+    }
+}
+
+/// Why a single attempt of a json rpc http request failed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HttpAttemptError {
+    /// No response before the deadline
+    Timeout,
+    /// The connection could not be made or was reset
+    Io,
+    /// The host could not run the request, e.g. an invalid url
+    Unknown,
+    /// The server answered with a status code other than 200
+    Status(u16),
+}
+
+impl HttpAttemptError {
+    /// Whether another attempt may succeed. Rate limiting and gateway errors are retried,
+    /// other status codes mean the request itself is wrong
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            HttpAttemptError::Timeout | HttpAttemptError::Io => true,
+            HttpAttemptError::Unknown => false,
+            HttpAttemptError::Status(code) => matches!(code, 429 | 500 | 502 | 503 | 504),
+        }
+    }
+}
+
+impl From<http::Error> for HttpAttemptError {
+    fn from(e: http::Error) -> Self {
+        Self::from(&e)
+    }
+}
+
+impl From<&http::Error> for HttpAttemptError {
+    fn from(e: &http::Error) -> Self {
+        match e {
+            http::Error::DeadlineReached => HttpAttemptError::Timeout,
+            http::Error::IoError => HttpAttemptError::Io,
+            http::Error::Unknown => HttpAttemptError::Unknown,
+        }
+    }
+}
+
+/// Failure to start a request or to read its body
+impl From<HttpError> for HttpAttemptError {
+    fn from(e: HttpError) -> Self {
+        Self::from(&e)
+    }
+}
+
+impl From<&HttpError> for HttpAttemptError {
+    fn from(e: &HttpError) -> Self {
+        match e {
+            HttpError::DeadlineReached => HttpAttemptError::Timeout,
+            HttpError::IoError => HttpAttemptError::Io,
+            HttpError::Invalid => HttpAttemptError::Unknown,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -50,12 +187,10 @@ pub type ChainRequestResult<T> = Result<T, ChainRequestError>;
 fn u64_to_str(num: u64) -> Vec<u8> {
     let mut num_buffer = [0u8; 20];
     num.numtoa_str(10, &mut num_buffer);
-    log::info!("num2str1 : {:?}", num_buffer);
     let mut s: Vec<u8> = Vec::new();
     num_buffer.into_iter().filter(|u| *u != 0).for_each(|u| {
         s.push(u);
     });
-    log::info!("num2str2 : {:?}", s);
     s
 }
 
@@ -78,7 +213,45 @@ pub const EMPTY_HASH: H256 = H256([
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
 ]);
 
+const BASE64_TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
 impl ChainUtils {
+    /// Standard base64 with padding, as used by http basic auth
+    pub fn base64_encode(data: &[u8]) -> Vec<u8> {
+        let mut rv = Vec::with_capacity((data.len() + 2) / 3 * 4);
+        for chunk in data.chunks(3) {
+            let n = ((chunk[0] as u32) << 16)
+                | ((*chunk.get(1).unwrap_or(&0) as u32) << 8)
+                | (*chunk.get(2).unwrap_or(&0) as u32);
+            rv.push(BASE64_TABLE[(n >> 18) as usize & 63]);
+            rv.push(BASE64_TABLE[(n >> 12) as usize & 63]);
+            rv.push(match chunk.len() {
+                1 => b'=',
+                _ => BASE64_TABLE[(n >> 6) as usize & 63],
+            });
+            rv.push(match chunk.len() {
+                3 => BASE64_TABLE[n as usize & 63],
+                _ => b'=',
+            });
+        }
+        rv
+    }
+
+    /// The scheme and host of a url, for logging. The user info, path and query are dropped as
+    /// rpc providers put api keys there
+    pub fn redact_url(url: &[u8]) -> &str {
+        let url = str::from_utf8(url).unwrap_or("<invalid url>");
+        let host_start = url.find("://").map_or(0, |i| i + 3);
+        let host_end = url[host_start..]
+            .find(|c: char| c == '/' || c == '?' || c == '#')
+            .map_or(url.len(), |i| host_start + i);
+        match url[host_start..host_end].rfind('@') {
+            // Scheme and user info are not contiguous, drop the scheme too
+            Some(i) => &url[host_start + i + 1..host_end],
+            None => &url[..host_end],
+        }
+    }
+
     pub fn hex_to_u64(s: &[u8]) -> Result<u64, ChainRequestError> {
         if s.len() < 2 {
             return Err(ChainRequestError::ConversionError);
@@ -89,11 +262,15 @@ impl ChainUtils {
             s
         };
         let hex = str::from_utf8(hexb).map_err(|e| {
-            log::error!("Error when converting from hex: {:?}", e);
+            log::warn!(
+                target: LOG_TARGET,
+                "Error when converting from hex: {:?}",
+                e
+            );
             ChainRequestError::ConversionError
         })?;
         let rv = u64::from_str_radix(hex, 16).map_err(|e| {
-            log::error!("{:?}", e);
+            log::warn!(target: LOG_TARGET, "Error when parsing u64: {:?}", e);
             ChainRequestError::ConversionError
         })?;
         Ok(rv)
@@ -102,11 +279,15 @@ impl ChainUtils {
     pub fn hex_to_u256(s: &[u8]) -> Result<U256, ChainRequestError> {
         let hex = Self::hex_remove_0x(s)?;
         let hex = str::from_utf8(hex).map_err(|e| {
-            log::error!("Error when converting from hex to u256: {:?}", e);
+            log::warn!(
+                target: LOG_TARGET,
+                "Error when converting from hex to u256: {:?}",
+                e
+            );
             ChainRequestError::ConversionError
         })?;
         let rv = U256::from_str_radix(hex, 16).map_err(|e| {
-            log::error!("{:?}", e);
+            log::warn!(target: LOG_TARGET, "Error when parsing u256: {:?}", e);
             ChainRequestError::ConversionError
         })?;
         Ok(rv)
@@ -177,25 +358,22 @@ impl ChainUtils {
         zx
     }
 
+    /// Hex quantity without leading zeros, as json rpc expects for numbers
     pub fn u256_to_hex_0x(i: &U256) -> Vec<u8> {
-        let fmted = i.encode();
-        let mut non_zero: bool = false;
-        let fmted: Vec<u8> = fmted
-            .into_iter()
-            .filter(|u| {
-                let u = *u;
-                if u != 0 {
-                    non_zero = true;
-                }
-                non_zero || u != 0
-            })
-            .collect();
-        if fmted.is_empty() {
-            return vec![b'0', b'x', b'0'];
-        }
-        let mut zx = vec![b'0', b'x'];
-        zx.extend(fmted.into_iter().map(|i| i + b'0'));
-        zx
+        let mut bytes = [0u8; 32];
+        i.to_big_endian(&mut bytes);
+        Self::quantity_hex_0x(&bytes)
+    }
+
+    /// Hex quantity without leading zeros, as json rpc expects for numbers
+    pub fn u64_to_hex_0x(i: u64) -> Vec<u8> {
+        Self::quantity_hex_0x(&i.to_be_bytes())
+    }
+
+    fn quantity_hex_0x(big_endian: &[u8]) -> Vec<u8> {
+        let hex = Self::bytes_to_hex(big_endian);
+        let start = hex.iter().position(|c| *c != b'0').unwrap_or(hex.len() - 1);
+        Self::hex_add_0x(&hex[start..])
     }
 
     pub fn h256_to_hex_0x(i: &H256) -> Vec<u8> {
@@ -233,7 +411,11 @@ impl ChainUtils {
         chain_id: u64,
     ) -> ChainRequestResult<TransactionSignature> {
         let recovery_id = libsecp256k1::RecoveryId::parse(signature[64]).map_err(|e| {
-            log::error!("Error sign_transaction_hash {:?}", e);
+            log::error!(
+                target: LOG_TARGET_SIGN,
+                "Invalid signature recovery id {:?}",
+                e
+            );
             ChainRequestError::ErrorCreatingTransaction(TransactionCreationError::SignatureError)
         })?;
         let rid = chain_id * 2 + 35 + recovery_id.serialize() as u64;
@@ -267,7 +449,6 @@ impl ChainUtils {
         Vec::from(&signed[12..32])
     }
 
-    #[allow(dead_code)]
     pub fn keccack(msg: &[u8]) -> H256 {
         let mut buf: [u8; 32] = [0; 32];
         let mut sponge = Keccak::v256();
@@ -352,7 +533,6 @@ impl JsonSer {
         self
     }
 
-    #[allow(dead_code)]
     pub fn u256(&mut self, name: &str, value: &U256) -> &mut Self {
         self.string(
             name,
@@ -401,6 +581,7 @@ impl JsonSer {
 #[cfg(test)]
 mod tests {
     use crate::chain_utils::{ChainUtils, JsonSer};
+    use sp_core::U256;
     use sp_std::str;
 
     #[test]
@@ -432,4 +613,18 @@ mod tests {
         let addrh = hex::encode(addr.as_slice());
         assert_eq!("1458e7bde6e509e4f8c122642bd61629aa46fa7c", addrh);
     }
+
+    #[test]
+    fn numbers_are_hex_quantities() {
+        assert_eq!(ChainUtils::u256_to_hex_0x(&U256::zero()), b"0x0".to_vec());
+        assert_eq!(
+            ChainUtils::u256_to_hex_0x(&U256::from(0x1234)),
+            b"0x1234".to_vec()
+        );
+        assert_eq!(
+            ChainUtils::u256_to_hex_0x(&U256::MAX),
+            [&b"0x"[..], &[b'f'; 64]].concat()
+        );
+        assert_eq!(ChainUtils::u64_to_hex_0x(16), b"0x10".to_vec());
+    }
 }
@@ -1,6 +1,12 @@
 use crate::{
-    chain_queries::{fetch_json_rpc, CallResponse, JsonRpcRequest},
-    chain_utils::{ChainRequestError, ChainUtils, JsonSer, TransactionCreationError},
+    chain_queries::{fetch_json_rpc, BlockId, CallResponse, ChainQueries, JsonRpcRequest},
+    chain_utils::{
+        ChainRequestError, ChainRequestResult, ChainUtils, JsonSer, ToJson,
+        TransactionCreationError,
+    },
+    qp_metrics::QpMetricsStore,
+    qp_types::QpRpcAuth,
+    LOG_TARGET_RPC, LOG_TARGET_SIGN,
 };
 use ethabi_nostd::{encoder, Address, Token};
 use ethereum::{LegacyTransaction, TransactionAction};
@@ -10,17 +16,200 @@ use rlp::Encodable;
 use serde::Deserialize;
 use sp_core::{ecdsa, H160, H256, U256};
 use sp_io::crypto;
+use sp_runtime::offchain::storage::StorageValueRef;
 use sp_std::{
+    fmt::{self, Debug, Formatter},
     ops::{Div, Mul},
     prelude::*,
     str,
 };
 
-#[derive(Debug, Clone)]
+/// Offchain storage prefix of the rpc endpoints whose chain id was verified
+const VERIFIED_CHAIN_ID_PREFIX: &[u8] = b"quantum-portal::chain-id::";
+/// Gas added over the estimate of a transaction, since the state may change before it is mined
+const GAS_LIMIT_MARGIN_PERCENT: u64 = 20;
+
+#[derive(Clone)]
 pub struct ContractClient {
     pub http_api: Vec<u8>,
+    /// Credentials of the json rpc endpoint
+    pub auth: QpRpcAuth,
     pub contract_address: Address,
     pub chain_id: u64,
+    /// End of the worker run, in unix millis. No request is attempted or retried past it
+    pub deadline: Option<u64>,
+}
+
+/// Keeps the credentials and the url path, which may hold an api key, out of the logs
+impl Debug for ContractClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContractClient")
+            .field("http_api", &ChainUtils::redact_url(&self.http_api))
+            .field("auth", &self.auth)
+            .field("contract_address", &self.contract_address)
+            .field("chain_id", &self.chain_id)
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+/// Options of an `eth_call`. The default is a plain call of the latest state
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallOptions {
+    pub block: BlockId,
+    pub from: Option<Address>,
+    pub gas: Option<U256>,
+    pub value: Option<U256>,
+    /// Account state replaced for the call only, by address
+    pub state_overrides: Vec<(Address, StateOverride)>,
+}
+
+/// Account fields replaced during an `eth_call`, see the geth `eth_call` state override set
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateOverride {
+    pub balance: Option<U256>,
+    pub nonce: Option<u64>,
+    pub code: Option<Vec<u8>>,
+    /// Storage slots replaced, the other slots keep their value
+    pub state_diff: Vec<(H256, H256)>,
+}
+
+impl CallOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn at(mut self, block: BlockId) -> Self {
+        self.block = block;
+        self
+    }
+
+    /// Sender of the call, the zero address by default
+    pub fn from(mut self, from: Address) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    pub fn gas(mut self, gas: U256) -> Self {
+        self.gas = Some(gas);
+        self
+    }
+
+    pub fn value(mut self, value: U256) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    pub fn override_balance(mut self, address: Address, balance: U256) -> Self {
+        self.account_override(address).balance = Some(balance);
+        self
+    }
+
+    pub fn override_nonce(mut self, address: Address, nonce: u64) -> Self {
+        self.account_override(address).nonce = Some(nonce);
+        self
+    }
+
+    pub fn override_code(mut self, address: Address, code: Vec<u8>) -> Self {
+        self.account_override(address).code = Some(code);
+        self
+    }
+
+    pub fn override_storage(mut self, address: Address, slot: H256, value: H256) -> Self {
+        let state_diff = &mut self.account_override(address).state_diff;
+        match state_diff.iter_mut().find(|(s, _)| *s == slot) {
+            Some(entry) => entry.1 = value,
+            None => state_diff.push((slot, value)),
+        }
+        self
+    }
+
+    fn account_override(&mut self, address: Address) -> &mut StateOverride {
+        let idx = match self.state_overrides.iter().position(|(a, _)| *a == address) {
+            Some(idx) => idx,
+            None => {
+                self.state_overrides
+                    .push((address, StateOverride::default()));
+                self.state_overrides.len() - 1
+            }
+        };
+        &mut self.state_overrides[idx].1
+    }
+
+    /// The `eth_call` params calling `to` with `data`: the call object, the block and the state
+    /// overrides when there are some
+    pub fn params(&self, to: Address, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut call = JsonSer::new();
+        call.start()
+            .string(
+                "data",
+                str::from_utf8(&ChainUtils::hex_add_0x(&ChainUtils::bytes_to_hex(data))).unwrap(),
+            )
+            .string(
+                "to",
+                str::from_utf8(&ChainUtils::address_to_hex(to)).unwrap(),
+            );
+        if let Some(from) = self.from {
+            call.string(
+                "from",
+                str::from_utf8(&ChainUtils::address_to_hex(from)).unwrap(),
+            );
+        }
+        if let Some(gas) = &self.gas {
+            call.u256("gas", gas);
+        }
+        if let Some(value) = &self.value {
+            call.u256("value", value);
+        }
+        let mut params = vec![call.end().to_vec(), self.block.to_param()];
+        if !self.state_overrides.is_empty() {
+            let mut overrides = JsonSer::new();
+            overrides.start();
+            for (address, state) in &self.state_overrides {
+                overrides.val(
+                    str::from_utf8(&ChainUtils::address_to_hex(*address)).unwrap(),
+                    str::from_utf8(&state.to_json()).unwrap(),
+                );
+            }
+            params.push(overrides.end().to_vec());
+        }
+        params
+    }
+}
+
+impl ToJson for StateOverride {
+    type BaseType = StateOverride;
+    fn to_json(&self) -> Vec<u8> {
+        let mut j = JsonSer::new();
+        j.start();
+        if let Some(balance) = &self.balance {
+            j.u256("balance", balance);
+        }
+        if let Some(nonce) = self.nonce {
+            j.string(
+                "nonce",
+                str::from_utf8(&ChainUtils::u64_to_hex_0x(nonce)).unwrap(),
+            );
+        }
+        if let Some(code) = &self.code {
+            j.string(
+                "code",
+                str::from_utf8(&ChainUtils::hex_add_0x(&ChainUtils::bytes_to_hex(code))).unwrap(),
+            );
+        }
+        if !self.state_diff.is_empty() {
+            let mut diff = JsonSer::new();
+            diff.start();
+            for (slot, value) in &self.state_diff {
+                diff.string(
+                    str::from_utf8(&ChainUtils::h256_to_hex_0x(slot)).unwrap(),
+                    str::from_utf8(&ChainUtils::h256_to_hex_0x(value)).unwrap(),
+                );
+            }
+            j.val("stateDiff", str::from_utf8(&diff.end().to_vec()).unwrap());
+        }
+        j.end().to_vec()
+    }
 }
 
 // #[derive(Clone)]
@@ -42,10 +231,19 @@ impl ContractClientSignature {
         let signed: Result<ecdsa::Signature, TransactionCreationError> =
             crypto::ecdsa_sign_prehashed(OFFCHAIN_SIGNER_KEY_TYPE, &self._signer, &hash.0)
                 .ok_or(TransactionCreationError::SigningFailed);
+        if signed.is_err() {
+            log::error!(
+                target: LOG_TARGET_SIGN,
+                "Could not sign {:?} with signer {:?}",
+                hash,
+                self.from
+            );
+        }
 
         if signed.is_ok() {
             let sig_bytes = signed.as_ref().unwrap().encode();
-            log::info!(
+            log::trace!(
+                target: LOG_TARGET_SIGN,
                 "Got a signature of size {}: {}",
                 sig_bytes.len(),
                 str::from_utf8(ChainUtils::bytes_to_hex(sig_bytes.as_slice()).as_slice()).unwrap()
@@ -56,14 +254,17 @@ impl ContractClientSignature {
     }
 
     pub fn get_signer_address(&self) -> Vec<u8> {
-        log::info!("Signer address is : {:?}", self.from);
+        log::debug!(
+            target: LOG_TARGET_SIGN,
+            "Signer address is : {:?}",
+            self.from
+        );
         self._signer.as_ref().to_vec()
     }
 }
 
 impl From<ecdsa::Public> for ContractClientSignature {
     fn from(signer: ecdsa::Public) -> Self {
-        log::info!("PUBLIC KEY {:?}", signer);
         let addr = ChainUtils::eth_address_from_public_key(&signer.0);
         let from = H160::from_slice(addr.as_slice());
 
@@ -75,57 +276,126 @@ impl From<ecdsa::Public> for ContractClientSignature {
 }
 
 impl ContractClient {
-    pub fn new(http_api: Vec<u8>, contract_address: &Address, chain_id: u64) -> Self {
+    pub fn new(
+        http_api: Vec<u8>,
+        auth: QpRpcAuth,
+        contract_address: &Address,
+        chain_id: u64,
+    ) -> Self {
         ContractClient {
             http_api,
+            auth,
             contract_address: *contract_address,
             chain_id,
+            deadline: None,
         }
     }
 
+    /// Call a view method of the contract. The state read is only trusted from an endpoint
+    /// serving the configured chain
     pub fn call<T>(
         &self,
         method_signature: &[u8],
         inputs: &[Token],
+        options: &CallOptions,
     ) -> Result<Box<T>, ChainRequestError>
     where
         T: for<'de> Deserialize<'de>,
     {
-        log::info!("CALL : method_signature {:?}", method_signature);
-        log::info!("CALL : inputs {:?}", inputs);
+        self.verify_chain_id()?;
+        log::debug!(
+            target: LOG_TARGET_RPC,
+            "[{}] eth_call {} at {:?}",
+            self.chain_id,
+            str::from_utf8(method_signature).unwrap(),
+            options.block
+        );
+        log::trace!(
+            target: LOG_TARGET_RPC,
+            "eth_call inputs {:?}, options {:?}",
+            inputs,
+            options
+        );
         let encoded_bytes = encoder::encode_function_u8(method_signature, inputs);
-        let encoded_bytes_0x = ChainUtils::bytes_to_hex(encoded_bytes.as_slice());
-        let encoded_bytes_slice = encoded_bytes_0x.as_slice();
-        let encoded_bytes_slice = ChainUtils::hex_add_0x(encoded_bytes_slice);
-        let encoded = str::from_utf8(encoded_bytes_slice.as_slice()).unwrap();
-        log::info!("encoded {}", encoded);
-        log::info!(
-            "contract address is {}",
-            str::from_utf8(ChainUtils::address_to_hex(self.contract_address).as_slice()).unwrap()
+        let params = options.params(self.contract_address, &encoded_bytes);
+        log::trace!(
+            target: LOG_TARGET_RPC,
+            "call_json is {}",
+            str::from_utf8(&params[0]).unwrap()
         );
-        let call_json = JsonSer::new()
-            .start()
-            .string("data", encoded)
-            .string(
-                "to",
-                str::from_utf8(ChainUtils::address_to_hex(self.contract_address).as_slice())
-                    .unwrap(),
-            )
-            .end()
-            .to_vec();
-
-        log::info!("call_json is {}", str::from_utf8(&call_json).unwrap());
         let req = JsonRpcRequest {
             id: 1,
-            params: Vec::from([call_json, Vec::from("\"latest\"".as_bytes())]),
+            params,
             method: b"eth_call".to_vec(),
         };
-        log::info!(
-            "Have request {:?}",
-            str::from_utf8(method_signature).unwrap()
-        );
+        self.fetch(&req)
+    }
+
+    /// Gas limit of a transaction whose gas estimate is `estimate`
+    pub fn gas_limit_with_margin(estimate: U256) -> U256 {
+        estimate
+            .saturating_mul(U256::from(100 + GAS_LIMIT_MARGIN_PERCENT))
+            .div(U256::from(100_u32))
+    }
+
+    pub fn block_number(&self) -> ChainRequestResult<u64> {
+        self.verify_chain_id()?;
+        ChainQueries::block_number(
+            str::from_utf8(&self.http_api[..]).unwrap(),
+            &self.auth,
+            self.deadline,
+        )
+    }
+
+    /// Check that the rpc endpoint serves the configured chain, before its state is read or
+    /// anything is signed for it. A verified endpoint is remembered in offchain storage. A
+    /// mismatch is not, so an endpoint fixed behind the same url is picked up by the next run
+    pub fn verify_chain_id(&self) -> ChainRequestResult<()> {
+        // The url may hold an api key, only its hash is stored
+        let key = [
+            VERIFIED_CHAIN_ID_PREFIX,
+            ChainUtils::keccack(&self.http_api).as_bytes(),
+        ]
+        .concat();
+        let verified = StorageValueRef::persistent(&key);
+        if verified.get::<u64>().ok().flatten() == Some(self.chain_id) {
+            return Ok(());
+        }
+        let actual = ChainQueries::chain_id(
+            str::from_utf8(&self.http_api[..]).unwrap(),
+            &self.auth,
+            self.deadline,
+        )?;
+        if actual != self.chain_id {
+            log::error!(
+                target: LOG_TARGET_RPC,
+                "[{}] {} serves chain {}, refusing to sign transactions for it",
+                self.chain_id,
+                ChainUtils::redact_url(&self.http_api),
+                actual
+            );
+            return Err(ChainRequestError::ChainIdMismatch {
+                expected: self.chain_id,
+                actual,
+            });
+        }
+        verified.set(&actual);
+        Ok(())
+    }
+
+    /// Send a json rpc request to this network, recording its latency and outcome
+    fn fetch<T>(&self, req: &JsonRpcRequest) -> Result<Box<T>, ChainRequestError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
         let http_api = str::from_utf8(&self.http_api[..]).unwrap();
-        fetch_json_rpc(http_api, &req)
+        let start = sp_io::offchain::timestamp().unix_millis();
+        let rv = fetch_json_rpc(http_api, &self.auth, self.deadline, req);
+        let latency = sp_io::offchain::timestamp()
+            .unix_millis()
+            .saturating_sub(start);
+        QpMetricsStore::record_rpc(self.chain_id, latency, rv.is_ok());
+        rv
     }
 
     pub fn send(
@@ -140,6 +410,7 @@ impl ContractClient {
         // encoded_bytes: Vec<u8>,
         signing: &ContractClientSignature,
     ) -> Result<H256, ChainRequestError> {
+        self.verify_chain_id()?;
         let encoded_bytes = encoder::encode_function_u8(method_signature, inputs);
         let encoded_bytes_0x = ChainUtils::bytes_to_hex(encoded_bytes.as_slice());
         let encoded_bytes_slice = encoded_bytes_0x.as_slice();
@@ -150,7 +421,11 @@ impl ContractClient {
             Some(v) => v,
         };
         let gas_limit_val = match gas_limit {
-            None => self.estimate_gas(encoded_bytes_slice.as_slice(), &value, from)?,
+            None => Self::gas_limit_with_margin(self.estimate_gas(
+                encoded_bytes_slice.as_slice(),
+                &value,
+                from,
+            )?),
             Some(v) => v,
         };
         let gas_price_val = match gas_price {
@@ -160,21 +435,14 @@ impl ContractClient {
                 .div(U256::from(100_u32)),
             Some(v) => v,
         };
-        let mut tx = LegacyTransaction {
-            nonce: nonce_val,
-            gas_price: gas_price_val,
-            gas_limit: gas_limit_val,
-            action: TransactionAction::Call(self.contract_address),
+        let raw_tx = self.signed_transaction(
+            encoded_bytes,
+            nonce_val,
+            gas_price_val,
+            gas_limit_val,
             value,
-            input: encoded_bytes,
-            signature: ChainUtils::empty_signature(),
-        };
-        let hash = ChainUtils::tx_hash_to_sign(&tx, self.chain_id);
-        let sig_bytes: ecdsa::Signature = signing.signer(&hash)?;
-        let sig = ChainUtils::decode_transaction_signature(&sig_bytes.0, self.chain_id)?;
-        tx.signature = sig;
-
-        let raw_tx = tx.rlp_bytes();
+            signing,
+        )?;
         let hex_tx = ChainUtils::bytes_to_hex(&raw_tx);
         let hex_tx_fmtd =
             ChainUtils::wrap_in_quotes(ChainUtils::hex_add_0x(hex_tx.as_slice()).as_slice());
@@ -183,15 +451,60 @@ impl ContractClient {
             params: Vec::from([hex_tx_fmtd]),
             method: b"eth_sendRawTransaction".to_vec(),
         };
-        // log::info!("Have request {:?}", &req);
-        let http_api = str::from_utf8(&self.http_api[..]).unwrap();
-        let rv: Box<CallResponse> = fetch_json_rpc(http_api, &req)?;
-        log::info!("Have response {:?}", &rv);
+        let rv: Box<CallResponse> = self.fetch(&req)?;
+        log::debug!(
+            target: LOG_TARGET_RPC,
+            "[{}] eth_sendRawTransaction returned {}",
+            self.chain_id,
+            str::from_utf8(&rv.result).unwrap()
+        );
         Ok(H256::from_slice(
             ChainUtils::hex_to_bytes(rv.result.as_slice())?.as_slice(),
         ))
     }
 
+    /// Build a legacy transaction calling the contract and sign it for this network, returning
+    /// the rlp encoded raw transaction
+    pub fn signed_transaction(
+        &self,
+        input: Vec<u8>,
+        nonce: U256,
+        gas_price: U256,
+        gas_limit: U256,
+        value: U256,
+        signing: &ContractClientSignature,
+    ) -> Result<Vec<u8>, ChainRequestError> {
+        let tx =
+            self.signed_legacy_transaction(input, nonce, gas_price, gas_limit, value, signing)?;
+        Ok(tx.rlp_bytes().to_vec())
+    }
+
+    /// Build a legacy transaction calling the contract and sign it for this network
+    pub fn signed_legacy_transaction(
+        &self,
+        input: Vec<u8>,
+        nonce: U256,
+        gas_price: U256,
+        gas_limit: U256,
+        value: U256,
+        signing: &ContractClientSignature,
+    ) -> Result<LegacyTransaction, ChainRequestError> {
+        let mut tx = LegacyTransaction {
+            nonce,
+            gas_price,
+            gas_limit,
+            action: TransactionAction::Call(self.contract_address),
+            value,
+            input,
+            signature: ChainUtils::empty_signature(),
+        };
+        let hash = ChainUtils::tx_hash_to_sign(&tx, self.chain_id);
+        let sig_bytes: ecdsa::Signature = signing.signer(&hash)?;
+        let sig = ChainUtils::decode_transaction_signature(&sig_bytes.0, self.chain_id)?;
+        tx.signature = sig;
+        Ok(tx)
+    }
+
     pub fn nonce(&self, from: Address) -> Result<U256, ChainRequestError> {
         let req = JsonRpcRequest {
             id: 1,
@@ -201,8 +514,7 @@ impl ContractClient {
             ]),
             method: b"eth_getTransactionCount".to_vec(),
         };
-        let http_api = str::from_utf8(&self.http_api[..]).unwrap();
-        let rv: Box<CallResponse> = fetch_json_rpc(http_api, &req)?;
+        let rv: Box<CallResponse> = self.fetch(&req)?;
         let nonce = ChainUtils::hex_to_u64(rv.result.as_slice())?;
         Ok(U256::from(nonce))
     }
@@ -213,8 +525,7 @@ impl ContractClient {
             params: Vec::new(),
             method: b"eth_gasPrice".to_vec(),
         };
-        let http_api = str::from_utf8(&self.http_api[..]).unwrap();
-        let rv: Box<CallResponse> = fetch_json_rpc(http_api, &req)?;
+        let rv: Box<CallResponse> = self.fetch(&req)?;
         let gp = ChainUtils::hex_to_u256(rv.result.as_slice())?;
         Ok(gp)
     }
@@ -243,7 +554,8 @@ impl ContractClient {
             )
             .end()
             .to_vec();
-        log::info!(
+        log::trace!(
+            target: LOG_TARGET_RPC,
             "estimateGas json is {}",
             str::from_utf8(&call_json).unwrap()
         );
@@ -252,8 +564,7 @@ impl ContractClient {
             params: Vec::from([call_json, Vec::from("\"latest\"".as_bytes())]),
             method: b"eth_estimateGas".to_vec(),
         };
-        let http_api = str::from_utf8(&self.http_api[..]).unwrap();
-        let rv: Box<CallResponse> = fetch_json_rpc(http_api, &req)?;
+        let rv: Box<CallResponse> = self.fetch(&req)?;
         let gp = ChainUtils::hex_to_u256(rv.result.as_slice())?;
         Ok(gp)
     }
@@ -1,8 +1,10 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use crate::{
-    chain_queries::de_string_to_bytes, chain_utils::ChainRequestError,
-    contract_client::ContractClient,
+    chain_queries::de_string_to_bytes,
+    chain_utils::ChainRequestError,
+    contract_client::{CallOptions, ContractClient},
+    LOG_TARGET_RPC,
 };
 use serde::Deserialize;
 use sp_core::U256;
@@ -27,15 +29,15 @@ impl Erc20Client {
 
     pub fn total_supply(&self) -> Result<U256, ChainRequestError> {
         let signature = b"totalSupply()";
-        let mut res: Box<TotalSupplyResponse> = self.contract.call(signature, &[])?;
+        let mut res: Box<TotalSupplyResponse> =
+            self.contract.call(signature, &[], &CallOptions::new())?;
         res.result.remove(0);
         res.result.remove(0);
         let res_str = str::from_utf8(res.result.as_slice()).unwrap();
-        log::info!("result {}", res_str);
+        log::trace!(target: LOG_TARGET_RPC, "result {}", res_str);
         let mut bytes: [u8; 32] = [0_u8; 32];
-        log::info!("result as u256 {:?}", &res.result);
         hex::decode_to_slice(res_str, &mut bytes).unwrap();
-        log::info!("result as u256 {:?}", &bytes);
+        log::trace!(target: LOG_TARGET_RPC, "result as u256 {:?}", &bytes);
         Ok(U256::from(bytes))
     }
 
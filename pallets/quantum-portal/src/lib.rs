@@ -2,75 +2,71 @@
 
 pub use pallet::*;
 
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
 mod chain_queries;
-mod chain_utils;
+pub mod chain_utils;
 mod contract_client;
 mod eip_712_utils;
 mod erc_20_client;
+pub mod migrations;
+#[cfg(test)]
+mod mock;
+pub mod native_evm;
+mod qp_metrics;
+pub mod qp_schedule;
 pub mod qp_types;
 mod quantum_portal_client;
 pub mod quantum_portal_service;
+#[cfg(test)]
+mod tests;
+pub mod weights;
+
+/// Log target for the quantum portal offchain worker
+pub(crate) const LOG_TARGET: &str = "qp";
+/// Log target for the json rpc requests made by the offchain worker
+pub(crate) const LOG_TARGET_RPC: &str = "qp::rpc";
+/// Log target for the transaction and EIP712 signing of the offchain worker
+pub(crate) const LOG_TARGET_SIGN: &str = "qp::sign";
 
 #[frame_support::pallet]
 pub mod pallet {
     //! A demonstration of an offchain worker that sends onchain callbacks
     use crate::{
-        chain_utils::{ChainRequestError, ChainUtils},
+        chain_utils::{ChainRequestError, ChainRequestResult, ChainUtils},
         contract_client::{ContractClient, ContractClientSignature},
+        native_evm::NativeEvm,
+        qp_metrics::QpMetricsStore,
+        qp_schedule::QpScheduler,
         qp_types,
-        qp_types::{EIP712Config, QpConfig, QpNetworkItem, Role},
+        qp_types::{
+            EIP712Config, QpBlockRecord, QpBlockRecordKind, QpConfig, QpFraudProof, QpNetworkItem,
+            QpNetworkType, QpPairInfo, QpWorkReport, QpWorker, QpWorkerRole, QpWorkerStatus, Role,
+        },
         quantum_portal_client::QuantumPortalClient,
-        quantum_portal_service::{PendingTransaction, QuantumPortalService},
+        quantum_portal_service::{QpRunSummary, QuantumPortalService},
+        weights::WeightInfo,
+        LOG_TARGET,
     };
     use core::convert::TryInto;
-    use ferrum_primitives::{OFFCHAIN_SIGNER_CONFIG_KEY, OFFCHAIN_SIGNER_CONFIG_PREFIX};
+    use ferrum_primitives::{
+        OFFCHAIN_SIGNER_CONFIG_KEY, OFFCHAIN_SIGNER_CONFIG_PREFIX, OFFCHAIN_SIGNER_KEY_TYPE,
+    };
     use frame_support::pallet_prelude::*;
     use frame_support::traits::Randomness;
     use frame_support::traits::UnixTime;
-    use frame_system::{
-        offchain::{SignedPayload, SigningTypes},
-        pallet_prelude::*,
-    };
-    use serde::{Deserialize, Deserializer};
+    use frame_support::traits::{Currency, ExistenceRequirement, OnUnbalanced, ReservableCurrency};
+    use frame_support::PalletId;
+    use frame_system::{offchain::SubmitTransaction, pallet_prelude::*};
+    use sp_core::{H160, H256};
     use sp_runtime::offchain::storage::StorageValueRef;
     use sp_runtime::offchain::storage_lock::StorageLock;
     use sp_runtime::offchain::storage_lock::Time;
-    use sp_runtime::RuntimeDebug;
-    use sp_std::{prelude::*, str};
-
-    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
-    pub struct Payload<Public> {
-        number: u64,
-        public: Public,
-    }
-
-    impl<T: SigningTypes> SignedPayload<T> for Payload<T::Public> {
-        fn public(&self) -> T::Public {
-            self.public.clone()
-        }
-    }
-
-    // ref: https://serde.rs/container-attrs.html#crate
-    #[derive(Deserialize, Encode, Decode, Default, RuntimeDebug, scale_info::TypeInfo)]
-    struct SnapshotInfo {
-        // Specify our own deserializing function to convert JSON string to vector of bytes
-        #[serde(deserialize_with = "de_string_to_bytes")]
-        icon_address: Vec<u8>,
-        amount: u32,
-        defi_user: bool,
-        vesting_percentage: u32,
-    }
-
-    #[derive(Debug, Deserialize, Encode, Decode, Default)]
-    struct IndexingData(Vec<u8>, u64);
-
-    pub fn de_string_to_bytes<'de, D>(de: D) -> Result<Vec<u8>, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let s: &str = Deserialize::deserialize(de)?;
-        Ok(s.as_bytes().to_vec())
-    }
+    use sp_runtime::{
+        traits::{AccountIdConversion, Saturating, Zero},
+        Perbill,
+    };
+    use sp_std::prelude::*;
 
     #[pallet::config]
     pub trait Config:
@@ -84,41 +80,319 @@ pub mod pallet {
         type PalletRandomness: Randomness<Self::Hash, Self::BlockNumber>;
         /// Onchain timestamp for the runtime
         type Timestamp: UnixTime;
+        /// Currency bonded by registered miners and finalizers
+        type Currency: ReservableCurrency<Self::AccountId>;
+        /// Stake reserved when registering as a miner
+        #[pallet::constant]
+        type MinerBond: Get<BalanceOf<Self>>;
+        /// Stake reserved when registering as a finalizer
+        #[pallet::constant]
+        type FinalizerBond: Get<BalanceOf<Self>>;
+        /// Number of blocks between `unbond` and the bond becoming withdrawable
+        #[pallet::constant]
+        type UnbondingDelay: Get<Self::BlockNumber>;
+        /// Origin allowed to slash the bond of a misbehaving worker
+        type SlashOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+        /// Handler for the slashed funds
+        type Slash: OnUnbalanced<NegativeImbalanceOf<Self>>;
+        /// Maximum number of active finalizers, bounds the signer set fraud proofs are checked
+        /// against
+        #[pallet::constant]
+        type MaxFinalizers: Get<u32>;
+        /// Share of the active finalizers that must attest a finalized block before it is
+        /// confirmed and its work credited
+        #[pallet::constant]
+        type FinalizeQuorum: Get<Perbill>;
+        /// Share of the active finalizers that must attest a fraud proof
+        #[pallet::constant]
+        type FraudProofQuorum: Get<Perbill>;
+        /// Share of the miner bond slashed for a proven fraud
+        #[pallet::constant]
+        type FraudSlash: Get<Perbill>;
+        /// Id of the pot account rewards are paid from
+        #[pallet::constant]
+        type PalletId: Get<PalletId>;
+        /// Number of blocks in a reward era, must not be zero
+        #[pallet::constant]
+        type EraLength: Get<Self::BlockNumber>;
+        /// Rewards shared between the workers at the end of each era, in proportion to their work
+        #[pallet::constant]
+        type RewardPerEra: Get<BalanceOf<Self>>;
+        /// Weights of the pallet calls
+        type WeightInfo: WeightInfo;
+        /// EVM of this chain, used for the networks of type `Native`
+        type NativeEvm: NativeEvm;
     }
 
+    pub type BalanceOf<T> =
+        <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+    pub type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
+        <T as frame_system::Config>::AccountId,
+    >>::NegativeImbalance;
+
+    /// The current storage version, see `migrations` for the upgrades
+    pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
-    #[pallet::without_storage_info]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
-    // The pallet's runtime storage items.
-    // https://substrate.dev/docs/en/knowledgebase/runtime/storage
+    /// Networks taking part in the portal, by chain id, with their ledger manager contract
+    #[pallet::storage]
+    #[pallet::getter(fn registered_networks)]
+    pub type RegisteredNetworks<T> = StorageMap<_, Twox64Concat, u64, H160, OptionQuery>;
+
+    /// Highest nonce reported mined on the local chain for each (remote chain, local chain) pair.
+    /// Raised by the miners' own reports before any finalizer confirms them, so it is unverified:
+    /// only `LastFinalizedNonce` is backed by a finalizer quorum.
+    #[pallet::storage]
+    #[pallet::getter(fn last_mined_nonce)]
+    pub type LastMinedNonce<T> =
+        StorageDoubleMap<_, Twox64Concat, u64, Twox64Concat, u64, u64, ValueQuery>;
+
+    /// Last nonce confirmed finalized on the local chain by a quorum of the finalizers, for each
+    /// (remote chain, local chain) pair
+    #[pallet::storage]
+    #[pallet::getter(fn last_finalized_nonce)]
+    pub type LastFinalizedNonce<T> =
+        StorageDoubleMap<_, Twox64Concat, u64, Twox64Concat, u64, u64, ValueQuery>;
+
+    /// Finalizer attestations of a block finalized above `LastFinalizedNonce`, by (remote chain,
+    /// local chain), as (finalizer, nonce, miner signer address). One per active finalizer.
+    #[pallet::storage]
+    pub type PendingFinalizations<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        (u64, u64),
+        BoundedVec<(T::AccountId, u64, H160), T::MaxFinalizers>,
+        ValueQuery,
+    >;
+
+    /// Last nonce each worker reported, mined or finalized, by (remote chain, local chain). A
+    /// worker may only report above it, so old reports cannot be replayed.
+    #[pallet::storage]
+    pub type LastReportedNonce<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        (u64, u64),
+        Blake2_128Concat,
+        T::AccountId,
+        u64,
+        OptionQuery,
+    >;
+
+    /// Most recent mined and finalized block reports, oldest first, at most `MaxRecentRecords`
+    #[pallet::storage]
+    pub type RecentBlockRecords<T: Config> = StorageValue<
+        _,
+        BoundedVec<QpBlockRecord<T::AccountId, T::BlockNumber>, MaxRecentRecords>,
+        ValueQuery,
+    >;
+
+    /// Bonded miners that are not unbonding and still hold the full `MinerBond`. Only changed by
+    /// registration, unbonding and slashing.
+    #[pallet::storage]
+    pub type Miners<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, (), OptionQuery>;
+
+    /// Bonded finalizers that are not unbonding and still hold the full `FinalizerBond`. Only
+    /// changed by registration, unbonding and slashing.
+    #[pallet::storage]
+    pub type Finalizers<T: Config> =
+        CountedStorageMap<_, Blake2_128Concat, T::AccountId, (), OptionQuery>;
+
+    /// Miners and finalizers that bonded stake, including the ones unbonding
+    #[pallet::storage]
+    #[pallet::getter(fn workers)]
+    pub type Workers<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        QpWorker<BalanceOf<T>, T::BlockNumber>,
+        OptionQuery,
+    >;
+
+    /// Worker account linked to each EVM signer address
+    #[pallet::storage]
+    #[pallet::getter(fn signer_owner)]
+    pub type Signers<T: Config> = StorageMap<_, Blake2_128Concat, H160, T::AccountId, OptionQuery>;
+
+    /// Reporter of each proven fraud, by (remote chain, local chain, nonce)
+    #[pallet::storage]
+    #[pallet::getter(fn proven_frauds)]
+    pub type ProvenFrauds<T: Config> =
+        StorageMap<_, Twox64Concat, (u64, u64, u64), T::AccountId, OptionQuery>;
+
+    /// Nonce of the latest block each miner reported mining and that is not confirmed yet, by
+    /// (remote chain, local chain) and miner. Mining work is only credited once a quorum of the
+    /// finalizers attests the block was finalized and mined by that miner.
+    #[pallet::storage]
+    pub type UnconfirmedMinedBlocks<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        (u64, u64),
+        Blake2_128Concat,
+        T::AccountId,
+        u64,
+        OptionQuery,
+    >;
+
+    /// Index of the current reward era
+    #[pallet::storage]
+    #[pallet::getter(fn current_era)]
+    pub type CurrentEra<T> = StorageValue<_, u32, ValueQuery>;
+
+    /// Confirmed mined and finalized blocks per worker, by era. Removed once the era is paid out.
+    #[pallet::storage]
+    #[pallet::getter(fn era_work)]
+    pub type EraWork<T: Config> =
+        StorageDoubleMap<_, Twox64Concat, u32, Blake2_128Concat, T::AccountId, u64, ValueQuery>;
+
+    /// Sum of `EraWork` by era
     #[pallet::storage]
-    // Learn more about declaring storage items:
-    // https://substrate.dev/docs/en/knowledgebase/runtime/storage#declaring-storage-i&tems
-    #[pallet::getter(fn numbers)]
-    pub(super) type Numbers<T> = StorageValue<_, u64, ValueQuery>;
+    #[pallet::getter(fn era_total_work)]
+    pub type EraTotalWork<T> = StorageMap<_, Twox64Concat, u32, u64, ValueQuery>;
 
+    /// Oldest ended era whose rewards are still being paid out, `MAX_PAYOUTS_PER_BLOCK` workers
+    /// per block
     #[pallet::storage]
-    #[pallet::getter(fn pending_transactions)]
-    pub(super) type PendingTransactions<T: Config> =
-        StorageMap<_, Identity, u64, PendingTransaction, ValueQuery>;
+    #[pallet::getter(fn payout_era)]
+    pub type PayoutEra<T> = StorageValue<_, u32, OptionQuery>;
+
+    /// Rewards earned by each worker and not claimed yet
+    #[pallet::storage]
+    #[pallet::getter(fn pending_rewards)]
+    pub type PendingRewards<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>, ValueQuery>;
 
     #[pallet::event]
-    // #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
-        NewNumber(Option<T::AccountId>, u64),
+        /// A network was registered or its ledger manager updated
+        NetworkRegistered { chain_id: u64, ledger_manager: H160 },
+        /// A network was removed
+        NetworkDeregistered { chain_id: u64 },
+        /// A miner reported a new mined block for a pair
+        BlockMined {
+            remote_chain: u64,
+            local_chain: u64,
+            nonce: u64,
+            miner: T::AccountId,
+        },
+        /// A finalizer attested a block finalized on the local chain
+        FinalizationAttested {
+            remote_chain: u64,
+            local_chain: u64,
+            nonce: u64,
+            finalizer: T::AccountId,
+        },
+        /// A quorum of the finalizers attested a finalized block, the attesters and its miner
+        /// were credited
+        BlockFinalized {
+            remote_chain: u64,
+            local_chain: u64,
+            nonce: u64,
+            attesters: u32,
+        },
+        /// An account bonded stake to become a miner or finalizer
+        WorkerRegistered {
+            who: T::AccountId,
+            role: QpWorkerRole,
+            signer: H160,
+            bond: BalanceOf<T>,
+        },
+        /// A worker stopped working, its bond can be withdrawn from `unlock_at`
+        WorkerUnbonded {
+            who: T::AccountId,
+            unlock_at: T::BlockNumber,
+        },
+        /// A worker withdrew its bond after the unbonding delay
+        BondWithdrawn {
+            who: T::AccountId,
+            amount: BalanceOf<T>,
+        },
+        /// Part of a worker bond was slashed
+        WorkerSlashed {
+            who: T::AccountId,
+            amount: BalanceOf<T>,
+        },
+        /// The rewards of an era were paid out to the workers
+        EraRewarded {
+            era: u32,
+            reward: BalanceOf<T>,
+            total_work: u64,
+        },
+        /// A worker claimed its rewards
+        RewardsClaimed {
+            who: T::AccountId,
+            amount: BalanceOf<T>,
+        },
+        /// A miner was proven to have mined a block that differs from the source block
+        FraudProven {
+            remote_chain: u64,
+            local_chain: u64,
+            nonce: u64,
+            miner: T::AccountId,
+            reporter: T::AccountId,
+            slashed: BalanceOf<T>,
+        },
     }
 
     // Errors inform users that something went wrong.
     #[pallet::error]
-    pub enum Error<T> {}
+    pub enum Error<T> {
+        /// The chain id is not a registered network
+        NetworkNotRegistered,
+        /// The report signer is not an active miner, or not the miner of the reported block
+        NotMiner,
+        /// The report signer is not an active finalizer
+        NotFinalizer,
+        /// The reported nonce is not above the last confirmed finalized one
+        NonceNotIncreasing,
+        /// The account already bonded stake as a miner or finalizer
+        AlreadyRegistered,
+        /// The signer address is linked to another worker
+        SignerInUse,
+        /// Not enough free balance to reserve the bond
+        InsufficientBalance,
+        /// The account has no bonded stake
+        NotRegistered,
+        /// The worker is already unbonding
+        AlreadyUnbonding,
+        /// The worker has not unbonded
+        NotUnbonding,
+        /// The unbonding delay has not passed yet
+        BondLocked,
+        /// Both transaction hashes of the fraud proof are the same
+        ProofNotFraudulent,
+        /// A fraud was already proven for this block
+        FraudAlreadyProven,
+        /// The fraud proof miner is not a bonded miner
+        UnknownMiner,
+        /// Too many attestations
+        TooManyAttestations,
+        /// Not enough valid finalizer attestations to reach the quorum
+        NotEnoughAttestations,
+        /// The account has no rewards to claim
+        NoRewards,
+        /// The reward pot does not hold enough funds to pay the rewards
+        InsufficientPot,
+        /// `MaxFinalizers` finalizers are already active
+        TooManyFinalizers,
+        /// The work report signature does not recover to a registered signer
+        InvalidReportSignature,
+        /// The mined nonce is more than `MAX_NONCE_JUMP` above the last confirmed finalized one
+        NonceTooFar,
+        /// The worker already reported this nonce, or a higher one, for the pair
+        AlreadyReported,
+    }
 
     /// Error which may occur while executing the off-chain code.
     #[cfg_attr(test, derive(PartialEq))]
     pub enum OffchainErr {
         RPCError(ChainRequestError),
         FailedSigning,
+        NoSignerKey,
     }
 
     impl sp_std::fmt::Debug for OffchainErr {
@@ -126,6 +400,9 @@ pub mod pallet {
             match *self {
                 OffchainErr::FailedSigning => write!(fmt, "Unable to sign transaction"),
                 OffchainErr::RPCError(ref error) => write!(fmt, "RPC error : {error:?}"),
+                OffchainErr::NoSignerKey => {
+                    write!(fmt, "Signer key not found in the keystore")
+                }
             }
         }
     }
@@ -138,78 +415,207 @@ pub mod pallet {
             network_item: QpNetworkItem,
             signer_public_key: Vec<u8>,
             eip_712_config: EIP712Config,
-        ) -> QuantumPortalClient<T> {
+        ) -> ChainRequestResult<QuantumPortalClient<T>> {
             let rpc_endpoint = network_item.url;
             let id = network_item.id;
+            // Transactions for the native network are signed for the EVM chain id, a different
+            // configured id would mix up the pairs and their pending transactions
+            if network_item.network_type == QpNetworkType::Native && id != T::NativeEvm::chain_id()
+            {
+                log::error!(
+                    target: LOG_TARGET,
+                    "Native network configured with id {}, the EVM chain id is {}",
+                    id,
+                    T::NativeEvm::chain_id()
+                );
+                return Err(ChainRequestError::ChainIdMismatch {
+                    expected: id,
+                    actual: T::NativeEvm::chain_id(),
+                });
+            }
 
             let signer = ChainUtils::hex_to_ecdsa_pub_key(&signer_public_key[..]);
             let lgr_mgr = ChainUtils::hex_to_address(&network_item.ledger_manager[..]);
-            let client = ContractClient::new(rpc_endpoint, &lgr_mgr, id);
-            QuantumPortalClient::new(
+            let client = ContractClient::new(rpc_endpoint, network_item.auth, &lgr_mgr, id);
+            Ok(QuantumPortalClient::new(
                 client,
                 ContractClientSignature::from(signer),
                 sp_io::offchain::timestamp().unix_millis(),
                 block_number,
                 eip_712_config,
-            )
+                network_item.network_type,
+                network_item.confirmations,
+            ))
+        }
+
+        /// Whether the configured signer key is available in the offchain signer keystore. A
+        /// found key is remembered for `SIGNER_KEY_CHECK_INTERVAL`, a missing one is looked up
+        /// again on the next run
+        pub fn signer_key_available(signer_public_key: &[u8]) -> bool {
+            let now = sp_io::offchain::timestamp().unix_millis();
+            let mut checked = StorageValueRef::persistent(SIGNER_KEY_CHECK_KEY);
+            if let Ok(Some((key, found_at))) = checked.get::<(Vec<u8>, u64)>() {
+                if key == signer_public_key
+                    && found_at.saturating_add(SIGNER_KEY_CHECK_INTERVAL) > now
+                {
+                    return true;
+                }
+            }
+            let signer = ChainUtils::hex_to_ecdsa_pub_key(signer_public_key);
+            let available =
+                sp_io::crypto::ecdsa_public_keys(OFFCHAIN_SIGNER_KEY_TYPE).contains(&signer);
+            if available {
+                checked.set(&(signer_public_key, now));
+            } else {
+                checked.clear();
+            }
+            available
         }
 
         pub fn test_qp(
             block_number: u64,
             qp_config_item: qp_types::QpConfig,
+            scheduler: &mut QpScheduler,
         ) -> OffchainResult<()> {
+            // Check once per run, before any rpc call is spent on a transaction we cannot sign
+            if !Self::signer_key_available(&qp_config_item.signer_public_key) {
+                QpMetricsStore::record_status(QpWorkerStatus::NoSignerKey);
+                return Err(OffchainErr::NoSignerKey);
+            }
+            QpMetricsStore::record_status(QpWorkerStatus::Running);
+
+            let deadline = scheduler.deadline(sp_io::offchain::timestamp().unix_millis());
             let client_vec: Vec<_> = qp_config_item
                 .network_vec
                 .into_iter()
-                .map(|item| {
-                    Self::configure_network(
+                .filter_map(|item| {
+                    let chain_id = item.id;
+                    match Self::configure_network(
                         block_number,
                         item,
                         qp_config_item.signer_public_key.clone(),
                         qp_config_item.eip_712_config.clone(),
-                    )
+                    ) {
+                        Ok(mut client) => {
+                            // A request in flight must not hold the run past its budget either
+                            client.contract.deadline = deadline;
+                            Some(client)
+                        }
+                        // The pairs of the other networks still run
+                        Err(e) => {
+                            log::error!(
+                                target: LOG_TARGET,
+                                "[{}] Skipping the network, it is misconfigured: {:?}",
+                                chain_id,
+                                e
+                            );
+                            None
+                        }
+                    }
                 })
                 .collect();
             let svc = QuantumPortalService::<T>::new(client_vec);
-            let _res: Vec<_> = qp_config_item
-                .pair_vec
-                .into_iter()
-                .map(|(remote_chain, local_chain)| {
-                    let proces_pair_res = svc.process_pair_with_lock(
+            let mut summary = QpRunSummary::default();
+            let pairs = qp_config_item.pair_vec;
+            for idx in scheduler.pair_order(pairs.len()) {
+                let (remote_chain, local_chain) = pairs[idx];
+                if !scheduler.pair_due(remote_chain, local_chain, block_number) {
+                    summary.not_due += 1;
+                    continue;
+                }
+                // Once out of time, the remaining pairs wait for the next run, which starts with
+                // the first of them
+                if summary.deferred > 0
+                    || deadline.map_or(false, |d| sp_io::offchain::timestamp().unix_millis() >= d)
+                {
+                    summary.deferred += 1;
+                    continue;
+                }
+                QpMetricsStore::record_pair_due(remote_chain, local_chain);
+                let process_pair_res = svc.process_pair_with_lock(
+                    remote_chain,
+                    local_chain,
+                    qp_config_item.role.clone(),
+                );
+                if let Err(e) = &process_pair_res {
+                    log::warn!(
+                        target: LOG_TARGET,
+                        "[{}=>{}] Error : {:?}",
                         remote_chain,
                         local_chain,
-                        qp_config_item.role.clone(),
-                    );
-                    if let Err(e) = proces_pair_res {
-                        log::warn!("Error : {:?}", e,)
-                    }
-                })
-                .collect();
+                        e
+                    )
+                }
+                summary.record(&process_pair_res);
+                scheduler.record_pair(&pairs, idx, block_number);
+            }
+            log::info!(
+                target: LOG_TARGET,
+                "QP run summary: block {}, role {:?}, pairs {}, mined {}, finalized {}, pending {}, locked {}, idle {}, skipped {}, errors {}, not due {}, deferred {}",
+                block_number,
+                qp_config_item.role,
+                summary.pairs,
+                summary.mined,
+                summary.finalized,
+                summary.pending,
+                summary.locked,
+                summary.idle,
+                summary.skipped,
+                summary.errors,
+                summary.not_due,
+                summary.deferred
+            );
             Ok(())
         }
     }
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(block_number: T::BlockNumber) -> Weight {
+            let mut weight = T::DbWeight::get().reads(1);
+            let era_length = T::EraLength::get();
+            if !block_number.is_zero()
+                && !era_length.is_zero()
+                && (block_number % era_length).is_zero()
+            {
+                Self::end_era();
+                weight = weight.saturating_add(T::WeightInfo::end_era());
+            }
+            if let Some(paid) = Self::pay_era_rewards() {
+                weight = weight.saturating_add(T::WeightInfo::pay_era_rewards(paid));
+            }
+            weight
+        }
+
+        fn integrity_test() {
+            assert!(!T::EraLength::get().is_zero(), "EraLength must not be zero");
+        }
+
         fn offchain_worker(block_number: T::BlockNumber) {
-            log::info!("OffchainWorker : Start Execution");
-            log::info!("Reading configuration from storage");
+            log::debug!(target: LOG_TARGET, "OffchainWorker : Start Execution");
 
             let mut lock = StorageLock::<Time>::new(OFFCHAIN_SIGNER_CONFIG_PREFIX);
             {
                 if let Ok(_guard) = lock.try_lock() {
                     let network_config = StorageValueRef::persistent(OFFCHAIN_SIGNER_CONFIG_KEY);
-                    //log::info!("Netweork config is {:?}", network_config);
                     let decoded_config = network_config.get::<QpConfig>();
-                    log::info!("Decoded config is {:?}", decoded_config);
+                    log::trace!(target: LOG_TARGET, "Decoded config is {:?}", decoded_config);
 
                     if let Err(_e) = decoded_config {
-                        log::info!("Error reading configuration, exiting offchain worker");
+                        QpMetricsStore::record_status(QpWorkerStatus::NoConfig);
+                        log::warn!(
+                            target: LOG_TARGET,
+                            "Error reading configuration, exiting offchain worker"
+                        );
                         return;
                     }
 
                     if let Ok(None) = decoded_config {
-                        log::info!("Configuration not found, exiting offchain worker");
+                        QpMetricsStore::record_status(QpWorkerStatus::NoConfig);
+                        log::debug!(
+                            target: LOG_TARGET,
+                            "Configuration not found, exiting offchain worker"
+                        );
                         return;
                     }
 
@@ -217,27 +623,715 @@ pub mod pallet {
                         let expected_role = config.role.clone();
 
                         if expected_role == Role::None {
-                            log::info!("Not a miner or finalizer, exiting offchain worker");
+                            QpMetricsStore::record_status(QpWorkerStatus::NoRole);
+                            log::debug!(
+                                target: LOG_TARGET,
+                                "Not a miner or finalizer, exiting offchain worker"
+                            );
                             return;
                         }
 
                         let now = block_number.try_into().map_or(0_u64, |f| f);
-                        log::info!("Current block: {:?}", block_number);
-                        if let Err(e) = Self::test_qp(now, config) {
+                        let mut scheduler = QpScheduler::load(config.schedule.clone());
+                        if !scheduler.start_run(now) {
+                            log::debug!(
+                                target: LOG_TARGET,
+                                "Not scheduled at block {}, last run at block {:?}",
+                                now,
+                                scheduler.state().last_run_block
+                            );
+                            return;
+                        }
+                        if let Err(e) = Self::test_qp(now, config, &mut scheduler) {
                             log::warn!(
+                                target: LOG_TARGET,
                                 "Offchain worker failed to execute at block {:?} with error : {:?}",
                                 now,
                                 e,
                             )
                         }
+                        scheduler.save();
                     }
+                } else {
+                    QpMetricsStore::record_lock_contention();
+                    log::info!(
+                        target: LOG_TARGET,
+                        "Previous offchain worker run still holds the lock, skipping"
+                    );
                 }
             }
 
-            log::info!("OffchainWorker : End Execution");
+            log::debug!(target: LOG_TARGET, "OffchainWorker : End Execution");
+        }
+    }
+
+    /// Number of block reports kept in `RecentBlockRecords`
+    pub type MaxRecentRecords = ConstU32<100>;
+
+    /// Maximum number of finalizer attestations in a fraud proof
+    pub const MAX_FRAUD_ATTESTATIONS: usize = 100;
+
+    /// Maximum distance between a reported mined nonce and the last confirmed finalized nonce of
+    /// the pair
+    pub const MAX_NONCE_JUMP: u64 = 1_000;
+
+    /// Maximum number of workers paid per block while an era is paid out
+    pub const MAX_PAYOUTS_PER_BLOCK: u32 = 64;
+
+    /// Priority of the unsigned work reports
+    const UNSIGNED_REPORT_PRIORITY: TransactionPriority = TransactionPriority::max_value() / 2;
+
+    /// Number of blocks a work report stays valid in the transaction pool
+    const UNSIGNED_REPORT_LONGEVITY: TransactionLongevity = 64;
+
+    /// Offchain storage of the signer key found in the keystore, and when, so a remote keystore
+    /// is not asked on every run
+    const SIGNER_KEY_CHECK_KEY: &[u8] = b"quantum-portal::signer-key-check";
+
+    /// Time a found signer key is trusted before the keystore is asked again, in milliseconds
+    const SIGNER_KEY_CHECK_INTERVAL: u64 = 10 * 60 * 1000;
+
+    impl<T: Config> Pallet<T> {
+        /// All registered networks with their ledger manager
+        pub fn networks() -> Vec<(u64, H160)> {
+            RegisteredNetworks::<T>::iter().collect()
+        }
+
+        pub fn is_miner(who: &T::AccountId) -> bool {
+            Miners::<T>::contains_key(who)
+        }
+
+        pub fn is_finalizer(who: &T::AccountId) -> bool {
+            Finalizers::<T>::contains_key(who)
+        }
+
+        pub fn miners() -> Vec<T::AccountId> {
+            Miners::<T>::iter_keys().collect()
+        }
+
+        pub fn finalizers() -> Vec<T::AccountId> {
+            Finalizers::<T>::iter_keys().collect()
+        }
+
+        /// All pairs with a mined or finalized block
+        pub fn pairs() -> Vec<QpPairInfo> {
+            let mut pairs: Vec<QpPairInfo> = LastMinedNonce::<T>::iter()
+                .map(|(remote_chain, local_chain, last_mined_nonce)| QpPairInfo {
+                    remote_chain,
+                    local_chain,
+                    last_mined_nonce,
+                    last_finalized_nonce: LastFinalizedNonce::<T>::get(remote_chain, local_chain),
+                })
+                .collect();
+            for (remote_chain, local_chain, last_finalized_nonce) in LastFinalizedNonce::<T>::iter()
+            {
+                if !LastMinedNonce::<T>::contains_key(remote_chain, local_chain) {
+                    pairs.push(QpPairInfo {
+                        remote_chain,
+                        local_chain,
+                        last_mined_nonce: 0,
+                        last_finalized_nonce,
+                    });
+                }
+            }
+            pairs
+        }
+
+        /// The `limit` most recent block reports, newest first
+        pub fn recent_records(limit: u32) -> Vec<QpBlockRecord<T::AccountId, T::BlockNumber>> {
+            RecentBlockRecords::<T>::get()
+                .into_iter()
+                .rev()
+                .take(limit as usize)
+                .collect()
+        }
+
+        fn push_record(
+            kind: QpBlockRecordKind,
+            remote_chain: u64,
+            local_chain: u64,
+            nonce: u64,
+            worker: T::AccountId,
+        ) {
+            RecentBlockRecords::<T>::mutate(|records| {
+                if records.len() >= MaxRecentRecords::get() as usize {
+                    records.remove(0);
+                }
+                // Cannot fail, the oldest record was removed if needed
+                let _ = records.try_push(QpBlockRecord {
+                    kind,
+                    remote_chain,
+                    local_chain,
+                    nonce,
+                    worker,
+                    block_number: <frame_system::Pallet<T>>::block_number(),
+                });
+            });
+        }
+
+        /// EVM signer addresses of the active miners
+        pub fn miner_signers() -> Vec<H160> {
+            Miners::<T>::iter_keys()
+                .filter_map(|who| Workers::<T>::get(who).map(|w| w.signer))
+                .collect()
+        }
+
+        /// EVM signer addresses of the active finalizers, at most `MaxFinalizers`
+        pub fn finalizer_signers() -> Vec<H160> {
+            Finalizers::<T>::iter_keys()
+                .filter_map(|who| Workers::<T>::get(who).map(|w| w.signer))
+                .collect()
+        }
+
+        /// Hash of the genesis block, separates the fraud proof attestations and the work reports
+        /// of this chain from the ones of other chains running the pallet
+        pub fn genesis_hash() -> T::Hash {
+            <frame_system::Pallet<T>>::block_hash(T::BlockNumber::zero())
+        }
+
+        fn set_active(who: &T::AccountId, role: QpWorkerRole, active: bool) {
+            match (role, active) {
+                (QpWorkerRole::Miner, true) => Miners::<T>::insert(who, ()),
+                (QpWorkerRole::Miner, false) => Miners::<T>::remove(who),
+                (QpWorkerRole::Finalizer, true) => Finalizers::<T>::insert(who, ()),
+                (QpWorkerRole::Finalizer, false) => Finalizers::<T>::remove(who),
+            }
+        }
+
+        fn bond_for(role: QpWorkerRole) -> BalanceOf<T> {
+            match role {
+                QpWorkerRole::Miner => T::MinerBond::get(),
+                QpWorkerRole::Finalizer => T::FinalizerBond::get(),
+            }
+        }
+
+        fn do_register(who: T::AccountId, role: QpWorkerRole, signer: H160) -> DispatchResult {
+            ensure!(
+                !Workers::<T>::contains_key(&who),
+                Error::<T>::AlreadyRegistered
+            );
+            ensure!(!Signers::<T>::contains_key(signer), Error::<T>::SignerInUse);
+            ensure!(
+                role != QpWorkerRole::Finalizer
+                    || Finalizers::<T>::count() < T::MaxFinalizers::get(),
+                Error::<T>::TooManyFinalizers
+            );
+            let bond = Self::bond_for(role);
+            T::Currency::reserve(&who, bond).map_err(|_| Error::<T>::InsufficientBalance)?;
+
+            Workers::<T>::insert(
+                &who,
+                QpWorker {
+                    role,
+                    signer,
+                    bond,
+                    unlock_at: None,
+                },
+            );
+            Signers::<T>::insert(signer, &who);
+            Self::set_active(&who, role, true);
+            Self::deposit_event(Event::WorkerRegistered {
+                who,
+                role,
+                signer,
+                bond,
+            });
+            Ok(())
+        }
+
+        /// Slash up to `amount` of a worker bond and return the amount actually slashed.
+        /// A worker left with less than the required bond stops being a miner or finalizer.
+        pub fn slash_worker(who: &T::AccountId, amount: BalanceOf<T>) -> BalanceOf<T> {
+            let mut worker = match Workers::<T>::get(who) {
+                Some(worker) => worker,
+                None => return Default::default(),
+            };
+            let (imbalance, missing) = T::Currency::slash_reserved(who, amount.min(worker.bond));
+            let slashed = amount.min(worker.bond) - missing;
+            T::Slash::on_unbalanced(imbalance);
+
+            worker.bond -= slashed;
+            if worker.bond < Self::bond_for(worker.role) {
+                Self::set_active(who, worker.role, false);
+            }
+            Workers::<T>::insert(who, worker);
+            Self::deposit_event(Event::WorkerSlashed {
+                who: who.clone(),
+                amount: slashed,
+            });
+            slashed
+        }
+
+        /// Account holding the funds rewards are paid from
+        pub fn pot_account() -> T::AccountId {
+            T::PalletId::get().into_account_truncating()
+        }
+
+        fn credit_work(who: &T::AccountId, work: u64) {
+            let era = CurrentEra::<T>::get();
+            EraWork::<T>::mutate(era, who, |w| *w = w.saturating_add(work));
+            EraTotalWork::<T>::mutate(era, |w| *w = w.saturating_add(work));
+        }
+
+        /// Start the next era, the ended one is paid out from the next blocks once the eras
+        /// before it are
+        pub(crate) fn end_era() {
+            let era = CurrentEra::<T>::get();
+            CurrentEra::<T>::put(era.wrapping_add(1));
+            if !PayoutEra::<T>::exists() {
+                PayoutEra::<T>::put(era);
+            }
+        }
+
+        /// Share `RewardPerEra` between up to `MAX_PAYOUTS_PER_BLOCK` workers of the era being
+        /// paid out, in proportion to their work. Returns the number of workers paid, or `None`
+        /// when no era is being paid out.
+        pub(crate) fn pay_era_rewards() -> Option<u32> {
+            let era = PayoutEra::<T>::get()?;
+            let total_work = EraTotalWork::<T>::get(era);
+            let reward = T::RewardPerEra::get();
+            let mut paid = 0;
+            for (who, work) in EraWork::<T>::drain_prefix(era).take(MAX_PAYOUTS_PER_BLOCK as usize)
+            {
+                let share = Perbill::from_rational(work, total_work) * reward;
+                PendingRewards::<T>::mutate(&who, |pending| {
+                    *pending = pending.saturating_add(share)
+                });
+                paid += 1;
+            }
+            if paid < MAX_PAYOUTS_PER_BLOCK {
+                EraTotalWork::<T>::remove(era);
+                let next = era.wrapping_add(1);
+                if next < CurrentEra::<T>::get() {
+                    PayoutEra::<T>::put(next);
+                } else {
+                    PayoutEra::<T>::kill();
+                }
+                if total_work > 0 {
+                    Self::deposit_event(Event::EraRewarded {
+                        era,
+                        reward,
+                        total_work,
+                    });
+                }
+            }
+            Some(paid)
+        }
+
+        /// Number of distinct `finalizers` among the signers of `hash` in `attestations`
+        fn count_attestations(hash: H256, finalizers: &[H160], attestations: &[[u8; 65]]) -> u32 {
+            let mut attesters: Vec<H160> = attestations
+                .iter()
+                .filter_map(|signature| {
+                    sp_io::crypto::secp256k1_ecdsa_recover(signature, hash.as_fixed_bytes()).ok()
+                })
+                .map(|public| H160::from_slice(&ChainUtils::eth_address_from_public_key(&public)))
+                .filter(|address| finalizers.contains(address))
+                .collect();
+            attesters.sort();
+            attesters.dedup();
+            attesters.len() as u32
+        }
+
+        fn ensure_pair_registered(remote_chain: u64, local_chain: u64) -> Result<(), Error<T>> {
+            ensure!(
+                RegisteredNetworks::<T>::contains_key(remote_chain)
+                    && RegisteredNetworks::<T>::contains_key(local_chain),
+                Error::<T>::NetworkNotRegistered
+            );
+            Ok(())
+        }
+
+        /// Check a work report can be recorded and return the worker that signed it
+        fn check_report(
+            kind: QpBlockRecordKind,
+            report: &QpWorkReport,
+            signature: &[u8; 65],
+        ) -> Result<T::AccountId, Error<T>> {
+            let hash = report.signing_hash(kind, &Self::genesis_hash());
+            let public = sp_io::crypto::secp256k1_ecdsa_recover(signature, hash.as_fixed_bytes())
+                .map_err(|_| Error::<T>::InvalidReportSignature)?;
+            let signer = H160::from_slice(&ChainUtils::eth_address_from_public_key(&public));
+            let who = Signers::<T>::get(signer).ok_or(Error::<T>::InvalidReportSignature)?;
+            Self::ensure_pair_registered(report.remote_chain, report.local_chain)?;
+            let pair = (report.remote_chain, report.local_chain);
+            let last_finalized = LastFinalizedNonce::<T>::get(pair.0, pair.1);
+            ensure!(
+                report.nonce > last_finalized,
+                Error::<T>::NonceNotIncreasing
+            );
+            match kind {
+                QpBlockRecordKind::Mined => {
+                    ensure!(
+                        Self::is_miner(&who) && report.miner == signer,
+                        Error::<T>::NotMiner
+                    );
+                    ensure!(
+                        report.nonce <= last_finalized.saturating_add(MAX_NONCE_JUMP),
+                        Error::<T>::NonceTooFar
+                    );
+                }
+                QpBlockRecordKind::Finalized => {
+                    ensure!(Self::is_finalizer(&who), Error::<T>::NotFinalizer);
+                }
+            }
+            ensure!(
+                LastReportedNonce::<T>::get(pair, &who).map_or(true, |last| report.nonce > last),
+                Error::<T>::AlreadyReported
+            );
+            Ok(who)
+        }
+
+        /// Record that a quorum of the finalizers attested the block `nonce` of a pair was
+        /// finalized and mined by `miner`. Credits the attesters, and the miner if it reported
+        /// the block.
+        fn confirm_finalized(
+            pair: (u64, u64),
+            nonce: u64,
+            miner: H160,
+            attesters: &[T::AccountId],
+        ) {
+            LastFinalizedNonce::<T>::insert(pair.0, pair.1, nonce);
+            PendingFinalizations::<T>::remove(pair);
+            for finalizer in attesters {
+                Self::credit_work(finalizer, 1);
+            }
+            if let Some(mined_by) = Signers::<T>::get(miner) {
+                if UnconfirmedMinedBlocks::<T>::get(pair, &mined_by) == Some(nonce) {
+                    UnconfirmedMinedBlocks::<T>::remove(pair, &mined_by);
+                    Self::credit_work(&mined_by, 1);
+                }
+            }
+            Self::deposit_event(Event::BlockFinalized {
+                remote_chain: pair.0,
+                local_chain: pair.1,
+                nonce,
+                attesters: attesters.len() as u32,
+            });
+        }
+
+        /// Sign a work report with the worker signer key and submit it to the transaction pool
+        /// of this node as an unsigned transaction
+        pub fn submit_work_report(
+            signer: &ContractClientSignature,
+            kind: QpBlockRecordKind,
+            report: QpWorkReport,
+        ) -> Result<(), ChainRequestError> {
+            let hash = report.signing_hash(kind, &Self::genesis_hash());
+            let signature = signer.signer(&hash)?.0;
+            let call = match kind {
+                QpBlockRecordKind::Mined => Call::report_mined_block { report, signature },
+                QpBlockRecordKind::Finalized => Call::report_finalized_block { report, signature },
+            };
+            if SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into()).is_err() {
+                // Usually the same report is already in the pool
+                log::debug!(target: LOG_TARGET, "Work report not accepted by the pool");
+            }
+            Ok(())
         }
     }
 
     #[pallet::call]
-    impl<T: Config> Pallet<T> {}
+    impl<T: Config> Pallet<T> {
+        /// Register a network, or update the ledger manager of a registered one
+        #[pallet::weight(T::WeightInfo::register_network())]
+        pub fn register_network(
+            origin: OriginFor<T>,
+            chain_id: u64,
+            ledger_manager: H160,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            RegisteredNetworks::<T>::insert(chain_id, ledger_manager);
+            Self::deposit_event(Event::NetworkRegistered {
+                chain_id,
+                ledger_manager,
+            });
+            Ok(())
+        }
+
+        /// Remove a registered network
+        #[pallet::weight(T::WeightInfo::deregister_network())]
+        pub fn deregister_network(origin: OriginFor<T>, chain_id: u64) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(
+                RegisteredNetworks::<T>::contains_key(chain_id),
+                Error::<T>::NetworkNotRegistered
+            );
+            RegisteredNetworks::<T>::remove(chain_id);
+            Self::deposit_event(Event::NetworkDeregistered { chain_id });
+            Ok(())
+        }
+
+        /// Record the latest block a miner mined on the local chain. Submitted unsigned by the
+        /// offchain worker of the miner, with a signature of its signer key over
+        /// `QpWorkReport::signing_hash`. Replaces the previous unconfirmed block of the miner,
+        /// which must have a lower nonce.
+        #[pallet::weight(T::WeightInfo::report_mined_block())]
+        pub fn report_mined_block(
+            origin: OriginFor<T>,
+            report: QpWorkReport,
+            signature: [u8; 65],
+        ) -> DispatchResult {
+            ensure_none(origin)?;
+            let miner = Self::check_report(QpBlockRecordKind::Mined, &report, &signature)?;
+            let (remote_chain, local_chain, nonce) =
+                (report.remote_chain, report.local_chain, report.nonce);
+            LastMinedNonce::<T>::mutate(remote_chain, local_chain, |last| *last = nonce.max(*last));
+            UnconfirmedMinedBlocks::<T>::insert((remote_chain, local_chain), &miner, nonce);
+            LastReportedNonce::<T>::insert((remote_chain, local_chain), &miner, nonce);
+            Self::push_record(
+                QpBlockRecordKind::Mined,
+                remote_chain,
+                local_chain,
+                nonce,
+                miner.clone(),
+            );
+            Self::deposit_event(Event::BlockMined {
+                remote_chain,
+                local_chain,
+                nonce,
+                miner,
+            });
+            Ok(())
+        }
+
+        /// Attest the latest block finalized on the local chain and its miner, as recorded by the
+        /// ledger manager. Submitted unsigned by the offchain worker of the finalizer, with a
+        /// signature of its signer key over `QpWorkReport::signing_hash`, above the nonce of its
+        /// previous attestation for the pair. Once `FinalizeQuorum`
+        /// of the active finalizers attest the same block and miner, the block is confirmed: the
+        /// finalizers that attested it and its miner are credited for the current era.
+        #[pallet::weight(T::WeightInfo::report_finalized_block(T::MaxFinalizers::get()))]
+        pub fn report_finalized_block(
+            origin: OriginFor<T>,
+            report: QpWorkReport,
+            signature: [u8; 65],
+        ) -> DispatchResultWithPostInfo {
+            ensure_none(origin)?;
+            let finalizer = Self::check_report(QpBlockRecordKind::Finalized, &report, &signature)?;
+            let pair = (report.remote_chain, report.local_chain);
+            let last_finalized = LastFinalizedNonce::<T>::get(pair.0, pair.1);
+
+            // Each active finalizer keeps its latest attestation above the confirmed nonce
+            let mut attestations = PendingFinalizations::<T>::get(pair);
+            let previous = attestations.len() as u32;
+            attestations.retain(|(who, nonce, _)| {
+                *who != finalizer && *nonce > last_finalized && Self::is_finalizer(who)
+            });
+            attestations
+                .try_push((finalizer.clone(), report.nonce, report.miner))
+                .map_err(|_| Error::<T>::TooManyFinalizers)?;
+            LastReportedNonce::<T>::insert(pair, &finalizer, report.nonce);
+            Self::push_record(
+                QpBlockRecordKind::Finalized,
+                pair.0,
+                pair.1,
+                report.nonce,
+                finalizer.clone(),
+            );
+            Self::deposit_event(Event::FinalizationAttested {
+                remote_chain: pair.0,
+                local_chain: pair.1,
+                nonce: report.nonce,
+                finalizer,
+            });
+
+            let attesters: Vec<T::AccountId> = attestations
+                .iter()
+                .filter(|(_, nonce, miner)| *nonce == report.nonce && *miner == report.miner)
+                .map(|(who, _, _)| who.clone())
+                .collect();
+            let quorum = T::FinalizeQuorum::get()
+                .mul_ceil(Finalizers::<T>::count())
+                .max(1);
+            if attesters.len() as u32 >= quorum {
+                Self::confirm_finalized(pair, report.nonce, report.miner, &attesters);
+            } else {
+                PendingFinalizations::<T>::insert(pair, attestations);
+            }
+            Ok(Some(T::WeightInfo::report_finalized_block(previous)).into())
+        }
+
+        /// Bond `MinerBond` and register as a miner signing with `signer`
+        #[pallet::weight(T::WeightInfo::register_miner())]
+        pub fn register_miner(origin: OriginFor<T>, signer: H160) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::do_register(who, QpWorkerRole::Miner, signer)
+        }
+
+        /// Bond `FinalizerBond` and register as a finalizer signing with `signer`
+        #[pallet::weight(T::WeightInfo::register_finalizer())]
+        pub fn register_finalizer(origin: OriginFor<T>, signer: H160) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::do_register(who, QpWorkerRole::Finalizer, signer)
+        }
+
+        /// Stop working, the bond can be withdrawn after `UnbondingDelay` blocks
+        #[pallet::weight(T::WeightInfo::unbond())]
+        pub fn unbond(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let unlock_at = Workers::<T>::try_mutate(&who, |worker| {
+                let worker = worker.as_mut().ok_or(Error::<T>::NotRegistered)?;
+                ensure!(worker.unlock_at.is_none(), Error::<T>::AlreadyUnbonding);
+                let unlock_at =
+                    <frame_system::Pallet<T>>::block_number() + T::UnbondingDelay::get();
+                worker.unlock_at = Some(unlock_at);
+                Self::set_active(&who, worker.role, false);
+                Ok::<_, Error<T>>(unlock_at)
+            })?;
+            Self::deposit_event(Event::WorkerUnbonded { who, unlock_at });
+            Ok(())
+        }
+
+        /// Release the bond of an unbonded worker once the unbonding delay has passed
+        #[pallet::weight(T::WeightInfo::withdraw_unbonded())]
+        pub fn withdraw_unbonded(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let worker = Workers::<T>::get(&who).ok_or(Error::<T>::NotRegistered)?;
+            let unlock_at = worker.unlock_at.ok_or(Error::<T>::NotUnbonding)?;
+            ensure!(
+                <frame_system::Pallet<T>>::block_number() >= unlock_at,
+                Error::<T>::BondLocked
+            );
+
+            T::Currency::unreserve(&who, worker.bond);
+            Workers::<T>::remove(&who);
+            Signers::<T>::remove(worker.signer);
+            Self::deposit_event(Event::BondWithdrawn {
+                who,
+                amount: worker.bond,
+            });
+            Ok(())
+        }
+
+        /// Prove that a miner mined a block whose transactions differ from the source chain block
+        /// with the same nonce. Both transaction hashes must be attested by a quorum of the
+        /// active finalizers, the miner bond is then slashed by `FraudSlash`. The attestations name
+        /// the miner, so a block it never reported, or whose work was already confirmed, is
+        /// proven the same way. Each nonce of a pair is proven once.
+        #[pallet::weight(T::WeightInfo::submit_fraud_proof(
+            attestations.len() as u32,
+            T::MaxFinalizers::get()
+        ))]
+        pub fn submit_fraud_proof(
+            origin: OriginFor<T>,
+            proof: QpFraudProof,
+            attestations: Vec<[u8; 65]>,
+        ) -> DispatchResultWithPostInfo {
+            let reporter = ensure_signed(origin)?;
+            ensure!(
+                attestations.len() <= MAX_FRAUD_ATTESTATIONS,
+                Error::<T>::TooManyAttestations
+            );
+            ensure!(
+                proof.mined_txs_hash != proof.source_txs_hash,
+                Error::<T>::ProofNotFraudulent
+            );
+            let key = (proof.remote_chain, proof.local_chain, proof.nonce);
+            ensure!(
+                !ProvenFrauds::<T>::contains_key(key),
+                Error::<T>::FraudAlreadyProven
+            );
+            let miner = Signers::<T>::get(proof.miner).ok_or(Error::<T>::UnknownMiner)?;
+            let worker = Workers::<T>::get(&miner).ok_or(Error::<T>::UnknownMiner)?;
+            ensure!(worker.role == QpWorkerRole::Miner, Error::<T>::UnknownMiner);
+
+            // Loaded once, bounded by `MaxFinalizers`
+            let finalizers = Self::finalizer_signers();
+            let quorum = T::FraudProofQuorum::get()
+                .mul_ceil(finalizers.len() as u32)
+                .max(1);
+            let hash = proof.attestation_hash(&Self::genesis_hash());
+            ensure!(
+                Self::count_attestations(hash, &finalizers, &attestations) >= quorum,
+                Error::<T>::NotEnoughAttestations
+            );
+
+            // The attestations name the miner, whether it reported the block or not
+            let slashed = Self::slash_worker(&miner, T::FraudSlash::get() * worker.bond);
+            // A fraudulent block earns no mining reward once finalized
+            let pair = (proof.remote_chain, proof.local_chain);
+            if UnconfirmedMinedBlocks::<T>::get(pair, &miner) == Some(proof.nonce) {
+                UnconfirmedMinedBlocks::<T>::remove(pair, &miner);
+            }
+            ProvenFrauds::<T>::insert(key, &reporter);
+            Self::deposit_event(Event::FraudProven {
+                remote_chain: proof.remote_chain,
+                local_chain: proof.local_chain,
+                nonce: proof.nonce,
+                miner,
+                reporter,
+                slashed,
+            });
+            Ok(Some(T::WeightInfo::submit_fraud_proof(
+                attestations.len() as u32,
+                finalizers.len() as u32,
+            ))
+            .into())
+        }
+
+        /// Pay the caller its pending rewards from the pot account
+        #[pallet::weight(T::WeightInfo::claim_rewards())]
+        pub fn claim_rewards(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let amount = PendingRewards::<T>::get(&who);
+            ensure!(!amount.is_zero(), Error::<T>::NoRewards);
+            T::Currency::transfer(
+                &Self::pot_account(),
+                &who,
+                amount,
+                ExistenceRequirement::KeepAlive,
+            )
+            .map_err(|_| Error::<T>::InsufficientPot)?;
+            PendingRewards::<T>::remove(&who);
+            Self::deposit_event(Event::RewardsClaimed { who, amount });
+            Ok(())
+        }
+
+        /// Slash up to `amount` of a worker bond for proven misbehaviour
+        #[pallet::weight(T::WeightInfo::slash())]
+        pub fn slash(
+            origin: OriginFor<T>,
+            who: T::AccountId,
+            amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            T::SlashOrigin::ensure_origin(origin)?;
+            ensure!(Workers::<T>::contains_key(&who), Error::<T>::NotRegistered);
+            Self::slash_worker(&who, amount);
+            Ok(())
+        }
+    }
+
+    #[pallet::validate_unsigned]
+    impl<T: Config> ValidateUnsigned for Pallet<T> {
+        type Call = Call<T>;
+
+        /// Only work reports signed by the signer of an active worker, that can still be
+        /// recorded, enter the pool. The pool holds one report per worker, kind and pair.
+        fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+            let (kind, report, signature) = match call {
+                Call::report_mined_block { report, signature } => {
+                    (QpBlockRecordKind::Mined, report, signature)
+                }
+                Call::report_finalized_block { report, signature } => {
+                    (QpBlockRecordKind::Finalized, report, signature)
+                }
+                _ => return InvalidTransaction::Call.into(),
+            };
+            let worker = Self::check_report(kind, report, signature).map_err(|e| match e {
+                Error::<T>::NonceNotIncreasing | Error::<T>::AlreadyReported => {
+                    InvalidTransaction::Stale
+                }
+                Error::<T>::NonceTooFar => InvalidTransaction::Future,
+                Error::<T>::InvalidReportSignature => InvalidTransaction::BadProof,
+                _ => InvalidTransaction::Call,
+            })?;
+            ValidTransaction::with_tag_prefix("QuantumPortal")
+                .priority(UNSIGNED_REPORT_PRIORITY)
+                .and_provides((kind, worker, report.remote_chain, report.local_chain))
+                .longevity(UNSIGNED_REPORT_LONGEVITY)
+                .propagate(true)
+                .build()
+        }
+    }
 }
@@ -0,0 +1,77 @@
+//! Storage migrations of the quantum portal pallet
+
+pub mod v1 {
+    use crate::{quantum_portal_service::PendingTransaction, Config, Pallet, STORAGE_VERSION};
+    use frame_support::{
+        pallet_prelude::*,
+        storage_alias,
+        traits::{GetStorageVersion, OnRuntimeUpgrade},
+    };
+    use sp_std::marker::PhantomData;
+    #[cfg(feature = "try-runtime")]
+    use sp_std::vec::Vec;
+
+    /// Test storage left over from the node template
+    #[storage_alias]
+    pub(crate) type Numbers<T: Config> = StorageValue<Pallet<T>, u64, ValueQuery>;
+
+    /// Pending transactions, now only kept in offchain storage by the worker
+    #[storage_alias]
+    pub(crate) type PendingTransactions<T: Config> =
+        StorageMap<Pallet<T>, Identity, u64, PendingTransaction, ValueQuery>;
+
+    /// Remove the unused `Numbers` and `PendingTransactions` items and set the storage version to 1.
+    /// `RecentBlockRecords` keeps its encoding as a bounded vec, it was already capped at 100 records.
+    pub struct MigrateToV1<T>(PhantomData<T>);
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+        fn on_runtime_upgrade() -> Weight {
+            let on_chain = Pallet::<T>::on_chain_storage_version();
+            if on_chain >= 1 {
+                log::info!(
+                    target: crate::LOG_TARGET,
+                    "Skipping migration to v1, storage is at {:?}",
+                    on_chain
+                );
+                return T::DbWeight::get().reads(1);
+            }
+
+            Numbers::<T>::kill();
+            let removed = PendingTransactions::<T>::clear(u32::MAX, None).unique;
+            STORAGE_VERSION.put::<Pallet<T>>();
+            log::info!(
+                target: crate::LOG_TARGET,
+                "Migrated to v1, removed {} pending transactions",
+                removed
+            );
+
+            T::DbWeight::get().reads_writes(1, removed as u64 + 2)
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+            let count = PendingTransactions::<T>::iter_keys().count() as u32;
+            Ok(count.encode())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+            let count = u32::decode(&mut &state[..]).map_err(|_| "invalid pre upgrade state")?;
+            log::info!(
+                target: crate::LOG_TARGET,
+                "{} pending transactions before upgrade",
+                count
+            );
+            ensure!(
+                Pallet::<T>::on_chain_storage_version() >= 1,
+                "storage version not updated"
+            );
+            ensure!(!Numbers::<T>::exists(), "Numbers not removed");
+            ensure!(
+                PendingTransactions::<T>::iter_keys().next().is_none(),
+                "PendingTransactions not removed"
+            );
+            Ok(())
+        }
+    }
+}
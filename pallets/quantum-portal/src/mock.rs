@@ -0,0 +1,427 @@
+//! Mock runtime for the quantum portal pallet tests
+
+use crate as pallet_quantum_portal;
+use crate::{
+    chain_utils::{ChainRequestError, ChainRequestResult, ChainUtils},
+    native_evm::NativeEvm,
+    qp_types::{EIP712Config, QpNetworkItem, QpNetworkType},
+    quantum_portal_client::QuantumPortalClient,
+    quantum_portal_service::QuantumPortalService,
+};
+use ethereum::LegacyTransaction;
+use ferrum_primitives::OFFCHAIN_SIGNER_KEY_TYPE;
+use frame_support::{
+    parameter_types,
+    traits::{ConstU32, ConstU64, Everything, Randomness, UnixTime},
+    PalletId,
+};
+use frame_system::EnsureRoot;
+use parking_lot::RwLock;
+use rlp::Encodable;
+use sp_core::{
+    ecdsa,
+    offchain::{
+        testing::{OffchainState, PendingRequest, TestOffchainExt},
+        OffchainDbExt, OffchainWorkerExt, Timestamp,
+    },
+    sr25519::Signature,
+    H160, H256, U256,
+};
+use sp_keystore::{testing::KeyStore, KeystoreExt, SyncCryptoStore};
+use sp_runtime::{
+    testing::{Header, TestXt},
+    traits::{BlakeTwo256, Extrinsic as ExtrinsicT, Hash, IdentifyAccount, IdentityLookup, Verify},
+    Perbill,
+};
+use std::{
+    cell::{Cell, RefCell},
+    sync::Arc,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+type Extrinsic = TestXt<RuntimeCall, ()>;
+pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
+
+frame_support::construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+        Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+        QuantumPortal: pallet_quantum_portal::{Pallet, Call, Storage, Event<T>, ValidateUnsigned},
+    }
+);
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = AccountId;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ();
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+    type Balance = u64;
+    type DustRemoval = ();
+    type RuntimeEvent = RuntimeEvent;
+    type ExistentialDeposit = ConstU64<1>;
+    type AccountStore = System;
+    type WeightInfo = ();
+    type MaxLocks = ();
+    type MaxReserves = ConstU32<50>;
+    type ReserveIdentifier = [u8; 8];
+}
+
+impl frame_system::offchain::SigningTypes for Test {
+    type Public = <Signature as Verify>::Signer;
+    type Signature = Signature;
+}
+
+impl<LocalCall> frame_system::offchain::SendTransactionTypes<LocalCall> for Test
+where
+    RuntimeCall: From<LocalCall>,
+{
+    type OverarchingCall = RuntimeCall;
+    type Extrinsic = Extrinsic;
+}
+
+impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Test
+where
+    RuntimeCall: From<LocalCall>,
+{
+    fn create_transaction<C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>>(
+        call: RuntimeCall,
+        _public: <Signature as Verify>::Signer,
+        _account: AccountId,
+        nonce: u64,
+    ) -> Option<(RuntimeCall, <Extrinsic as ExtrinsicT>::SignaturePayload)> {
+        Some((call, (nonce, ())))
+    }
+}
+
+/// Randomness derived from the subject only, the tests do not rely on its quality
+pub struct TestRandomness;
+
+impl Randomness<H256, u64> for TestRandomness {
+    fn random(subject: &[u8]) -> (H256, u64) {
+        (BlakeTwo256::hash(subject), 0)
+    }
+}
+
+/// Onchain time, fixed at `NOW`
+pub struct TestTime;
+
+impl UnixTime for TestTime {
+    fn now() -> core::time::Duration {
+        core::time::Duration::from_millis(NOW)
+    }
+}
+
+parameter_types! {
+    pub const QpPalletId: PalletId = PalletId(*b"qp/pot00");
+    pub const QpFinalizeQuorum: Perbill = Perbill::from_percent(66);
+    pub const QpFraudProofQuorum: Perbill = Perbill::from_percent(66);
+    pub const QpFraudSlash: Perbill = Perbill::from_percent(50);
+}
+
+impl pallet_quantum_portal::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type RuntimeCall = RuntimeCall;
+    type PalletRandomness = TestRandomness;
+    type Timestamp = TestTime;
+    type Currency = Balances;
+    type MinerBond = ConstU64<100>;
+    type FinalizerBond = ConstU64<50>;
+    type UnbondingDelay = ConstU64<10>;
+    type SlashOrigin = EnsureRoot<AccountId>;
+    type Slash = ();
+    type MaxFinalizers = ConstU32<100>;
+    type FinalizeQuorum = QpFinalizeQuorum;
+    type FraudProofQuorum = QpFraudProofQuorum;
+    type FraudSlash = QpFraudSlash;
+    type PalletId = QpPalletId;
+    type EraLength = ConstU64<100>;
+    type RewardPerEra = ConstU64<1_000>;
+    type WeightInfo = ();
+    type NativeEvm = TestNativeEvm;
+}
+
+thread_local! {
+    static NATIVE_CALLS: RefCell<Vec<(Vec<u8>, Vec<u8>)>> = RefCell::new(vec![]);
+    static NATIVE_SUBMITTED: RefCell<Vec<LegacyTransaction>> = RefCell::new(vec![]);
+    /// Hashes of the transactions included in the current block
+    static NATIVE_BLOCK: RefCell<Vec<H256>> = RefCell::new(vec![]);
+    static NATIVE_ACCOUNT_NONCE: Cell<u64> = Cell::new(NATIVE_NONCE);
+}
+
+/// Native EVM answering the calls scripted with `expect_native_call`. Submitted transactions
+/// are recorded and succeed in the current block, see `next_native_block`
+pub struct TestNativeEvm;
+
+impl NativeEvm for TestNativeEvm {
+    fn chain_id() -> u64 {
+        NATIVE_CHAIN
+    }
+
+    fn call(
+        _from: H160,
+        _to: H160,
+        data: Vec<u8>,
+        _value: U256,
+        estimate: bool,
+    ) -> ChainRequestResult<(Vec<u8>, U256)> {
+        if estimate {
+            return Ok((vec![], U256::from(NATIVE_GAS)));
+        }
+        NATIVE_CALLS
+            .with(|calls| {
+                calls
+                    .borrow()
+                    .iter()
+                    .find(|(d, _)| *d == data)
+                    .map(|(_, output)| (output.clone(), U256::zero()))
+            })
+            .ok_or_else(|| ChainRequestError::NativeEvmCallFailed(b"Revert".to_vec()))
+    }
+
+    fn nonce(_address: H160) -> U256 {
+        U256::from(NATIVE_ACCOUNT_NONCE.with(|nonce| nonce.get()))
+    }
+
+    fn gas_price() -> U256 {
+        U256::from(NATIVE_GAS_PRICE)
+    }
+
+    fn submit_transaction(tx: LegacyTransaction) -> ChainRequestResult<()> {
+        NATIVE_BLOCK.with(|block| {
+            block
+                .borrow_mut()
+                .push(ChainUtils::keccack(&tx.rlp_bytes()))
+        });
+        NATIVE_SUBMITTED.with(|txs| txs.borrow_mut().push(tx));
+        Ok(())
+    }
+
+    fn transaction_succeeded(tx_hash: H256) -> Option<bool> {
+        NATIVE_BLOCK.with(|block| block.borrow().contains(&tx_hash).then_some(true))
+    }
+}
+
+/// Move the native EVM to the next block. The transactions of the current block move the
+/// account nonce past them if `included`, otherwise they were dropped
+pub fn next_native_block(included: bool) {
+    let txs = NATIVE_BLOCK.with(|block| block.take().len() as u64);
+    if included {
+        NATIVE_ACCOUNT_NONCE.with(|nonce| nonce.set(nonce.get() + txs));
+    }
+}
+
+/// Answer the native EVM call with the given data with `output`
+pub fn expect_native_call(data: Vec<u8>, output: Vec<u8>) {
+    NATIVE_CALLS.with(|calls| calls.borrow_mut().push((data, output)));
+}
+
+/// The transactions submitted to the native EVM
+pub fn native_submitted() -> Vec<LegacyTransaction> {
+    NATIVE_SUBMITTED.with(|txs| txs.borrow().clone())
+}
+
+/// Offchain time of the test runs, in milliseconds
+pub const NOW: u64 = 1_670_000_000_000;
+/// Chain the mined transactions originate from
+pub const REMOTE_CHAIN: u64 = 4;
+/// Chain the offchain worker sends its transactions to
+pub const LOCAL_CHAIN: u64 = 97;
+/// Chain id of the native EVM
+pub const NATIVE_CHAIN: u64 = 26000;
+/// Answers of the native EVM to the transaction parameters queries
+pub const NATIVE_NONCE: u64 = 3;
+pub const NATIVE_GAS: u64 = 150_000;
+pub const NATIVE_GAS_PRICE: u64 = 2_000_000_000;
+pub const REMOTE_RPC: &str = "http://remote.rpc";
+pub const LOCAL_RPC: &str = "http://local.rpc";
+pub const REMOTE_LEDGER_MANAGER: &str = "d36312d594852462d6760042e779164eb97301cd";
+pub const LOCAL_LEDGER_MANAGER: &str = "a2d7ea2b1c06a07c11d7e29b2aaf4b87c62b4e4f";
+/// Seed of the `ofsg` signer key
+pub const SIGNER_PHRASE: &str =
+    "news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+/// Build the externalities for the pallet calls and benchmarks, with an empty keystore
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let t = frame_system::GenesisConfig::default()
+        .build_storage::<Test>()
+        .unwrap();
+    let mut ext = sp_io::TestExternalities::new(t);
+    ext.register_extension(KeystoreExt(Arc::new(KeyStore::new())));
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
+
+/// Build the externalities for an offchain worker run: an offchain http/storage extension with
+/// its scripted state, and a keystore holding the `ofsg` ECDSA signer key
+pub fn new_offchain_ext() -> (
+    sp_io::TestExternalities,
+    Arc<RwLock<OffchainState>>,
+    ecdsa::Public,
+) {
+    let (offchain, state) = TestOffchainExt::new();
+    state.write().timestamp = Timestamp::from_unix_millis(NOW);
+
+    let keystore = KeyStore::new();
+    let signer = SyncCryptoStore::ecdsa_generate_new(
+        &keystore,
+        OFFCHAIN_SIGNER_KEY_TYPE,
+        Some(&format!("{}//qp", SIGNER_PHRASE)),
+    )
+    .unwrap();
+
+    let mut ext = new_test_ext();
+    ext.register_extension(OffchainWorkerExt::new(offchain.clone()));
+    ext.register_extension(OffchainDbExt::new(offchain));
+    ext.register_extension(KeystoreExt(Arc::new(keystore)));
+    (ext, state, signer)
+}
+
+/// The EIP712 domain of the ledger manager finalize signatures
+pub fn eip_712_config() -> EIP712Config {
+    EIP712Config {
+        contract_name: b"FERRUM_QUANTUM_PORTAL_AUTHORITY_MGR".to_vec(),
+        contract_version: b"000.010".to_vec(),
+        verifying_address: LOCAL_LEDGER_MANAGER.as_bytes().to_vec(),
+    }
+}
+
+/// Configure a client the way the offchain worker does. Must run inside the externalities
+pub fn client(
+    url: &str,
+    ledger_manager: &str,
+    chain_id: u64,
+    signer: &ecdsa::Public,
+) -> QuantumPortalClient<Test> {
+    network_client(url, ledger_manager, chain_id, QpNetworkType::Http, signer)
+}
+
+/// A client of the native EVM, with the local ledger manager. Must run inside the externalities
+pub fn native_client(signer: &ecdsa::Public) -> QuantumPortalClient<Test> {
+    network_client(
+        "",
+        LOCAL_LEDGER_MANAGER,
+        NATIVE_CHAIN,
+        QpNetworkType::Native,
+        signer,
+    )
+}
+
+fn network_client(
+    url: &str,
+    ledger_manager: &str,
+    chain_id: u64,
+    network_type: QpNetworkType,
+    signer: &ecdsa::Public,
+) -> QuantumPortalClient<Test> {
+    QuantumPortal::configure_network(
+        1,
+        QpNetworkItem {
+            url: url.as_bytes().to_vec(),
+            ledger_manager: ledger_manager.as_bytes().to_vec(),
+            id: chain_id,
+            network_type,
+            auth: Default::default(),
+            confirmations: 0,
+        },
+        ChainUtils::bytes_to_hex(&signer.0),
+        eip_712_config(),
+    )
+    .unwrap()
+}
+
+/// The remote and local clients of the test pair. Must run inside the externalities
+pub fn clients(signer: &ecdsa::Public) -> (QuantumPortalClient<Test>, QuantumPortalClient<Test>) {
+    (
+        client(REMOTE_RPC, REMOTE_LEDGER_MANAGER, REMOTE_CHAIN, signer),
+        client(LOCAL_RPC, LOCAL_LEDGER_MANAGER, LOCAL_CHAIN, signer),
+    )
+}
+
+/// A service over the remote and local clients of the test pair. Must run inside the
+/// externalities
+pub fn service(signer: &ecdsa::Public) -> QuantumPortalService<Test> {
+    let (remote, local) = clients(signer);
+    QuantumPortalService::new(vec![remote, local])
+}
+
+/// The ledger manager address of the given network, as configured in `clients`
+pub fn ledger_manager(chain_id: u64) -> H160 {
+    let hex = if chain_id == REMOTE_CHAIN {
+        REMOTE_LEDGER_MANAGER
+    } else {
+        LOCAL_LEDGER_MANAGER
+    };
+    ChainUtils::hex_to_address(hex.as_bytes())
+}
+
+/// Expect a json rpc request with the given method and raw json params, answered with `result`
+pub fn expect_rpc(state: &mut OffchainState, url: &str, method: &str, params: &str, result: &str) {
+    expect_rpc_response(
+        state,
+        url,
+        method,
+        params,
+        &format!(r#""result":{}"#, result),
+    );
+}
+
+/// Expect a json rpc request answered with the given raw json error object
+pub fn expect_rpc_error(
+    state: &mut OffchainState,
+    url: &str,
+    method: &str,
+    params: &str,
+    error: &str,
+) {
+    expect_rpc_response(state, url, method, params, &format!(r#""error":{}"#, error));
+}
+
+fn expect_rpc_response(
+    state: &mut OffchainState,
+    url: &str,
+    method: &str,
+    params: &str,
+    member: &str,
+) {
+    let body = format!(
+        r#"{{"id":1,"method":"{}","jsonrpc":"2.0","params":[{}]}}"#,
+        method, params
+    );
+    state.expect_request(PendingRequest {
+        method: "POST".into(),
+        uri: url.into(),
+        headers: vec![("Content-Type".into(), "application/json".into())],
+        body: body.into_bytes(),
+        response: Some(format!(r#"{{"jsonrpc":"2.0","id":1,{}}}"#, member).into_bytes()),
+        sent: true,
+        ..Default::default()
+    });
+}
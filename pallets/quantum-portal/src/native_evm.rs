@@ -0,0 +1,203 @@
+//! Access to the EVM of the chain running the pallet, for networks of type
+//! `QpNetworkType::Native`. The offchain worker reads the ledger manager through direct EVM calls
+//! and submits its transactions as self-contained Ethereum extrinsics, so it never goes through
+//! the node's own JSON-RPC.
+
+use crate::{
+    chain_queries::{CallResponse, TransactionStatus},
+    chain_utils::{ChainRequestError, ChainRequestResult, ChainUtils},
+    contract_client::{ContractClient, ContractClientSignature},
+    quantum_portal_service::TIMEOUT,
+    LOG_TARGET_RPC,
+};
+use ethabi_nostd::{encoder, Token};
+pub use ethereum::LegacyTransaction;
+use rlp::Encodable;
+use sp_core::{H160, H256, U256};
+use sp_runtime::offchain::storage::StorageValueRef;
+use sp_std::{
+    marker::PhantomData,
+    ops::{Div, Mul},
+    prelude::*,
+    str,
+};
+
+/// Offchain storage of the signer and nonce of each submitted native transaction, by hash
+const NATIVE_TX_PREFIX: &[u8] = b"quantum-portal::native-tx::";
+
+fn native_tx_key(tx_hash: &H256) -> Vec<u8> {
+    [NATIVE_TX_PREFIX, tx_hash.as_bytes()].concat()
+}
+
+/// Offchain storage of the last nonce each signer used for a native transaction, and when
+const NATIVE_NONCE_PREFIX: &[u8] = b"quantum-portal::native-nonce::";
+
+fn native_nonce_key(from: &H160) -> Vec<u8> {
+    [NATIVE_NONCE_PREFIX, from.as_bytes()].concat()
+}
+
+/// The EVM of this chain, implemented by the runtime
+pub trait NativeEvm {
+    /// Chain id the transactions are signed for
+    fn chain_id() -> u64;
+    /// Run a call against the current state without keeping its changes, returning the output
+    /// and the gas used. Fails if the call does not succeed
+    fn call(
+        from: H160,
+        to: H160,
+        data: Vec<u8>,
+        value: U256,
+        estimate: bool,
+    ) -> ChainRequestResult<(Vec<u8>, U256)>;
+    /// Transaction count of the account. Once it is past the nonce of a submitted transaction,
+    /// that transaction was included in a block
+    fn nonce(address: H160) -> U256;
+    /// Minimum gas price of the transactions
+    fn gas_price() -> U256;
+    /// Submit the signed transaction to the transaction pool as a self-contained extrinsic
+    fn submit_transaction(tx: LegacyTransaction) -> ChainRequestResult<()>;
+    /// Outcome of a transaction of the current block, `None` if the block does not include it
+    fn transaction_succeeded(tx_hash: H256) -> Option<bool>;
+}
+
+/// No native EVM, for runtimes without `pallet_evm`
+impl NativeEvm for () {
+    fn chain_id() -> u64 {
+        0
+    }
+
+    fn call(
+        _from: H160,
+        _to: H160,
+        _data: Vec<u8>,
+        _value: U256,
+        _estimate: bool,
+    ) -> ChainRequestResult<(Vec<u8>, U256)> {
+        Err(ChainRequestError::NativeEvmUnavailable)
+    }
+
+    fn nonce(_address: H160) -> U256 {
+        U256::zero()
+    }
+
+    fn gas_price() -> U256 {
+        U256::zero()
+    }
+
+    fn submit_transaction(_tx: LegacyTransaction) -> ChainRequestResult<()> {
+        Err(ChainRequestError::NativeEvmUnavailable)
+    }
+
+    fn transaction_succeeded(_tx_hash: H256) -> Option<bool> {
+        None
+    }
+}
+
+/// The `ContractClient` calls, answered by the native EVM
+pub struct NativeContractClient<E: NativeEvm> {
+    _phantom: PhantomData<E>,
+}
+
+impl<E: NativeEvm> NativeContractClient<E> {
+    /// Call a view method of the contract. The output is hex encoded like a json rpc result, so
+    /// it is decoded the same way for both network types
+    pub fn call(
+        contract: &ContractClient,
+        from: H160,
+        method_signature: &[u8],
+        inputs: &[Token],
+    ) -> ChainRequestResult<Box<CallResponse>> {
+        log::debug!(
+            target: LOG_TARGET_RPC,
+            "[{}] native call {}",
+            contract.chain_id,
+            str::from_utf8(method_signature).unwrap()
+        );
+        let data = encoder::encode_function_u8(method_signature, inputs);
+        let (output, _) = E::call(from, contract.contract_address, data, U256::zero(), false)?;
+        Ok(Box::new(CallResponse {
+            result: ChainUtils::hex_add_0x(&ChainUtils::bytes_to_hex(&output)),
+        }))
+    }
+
+    /// Sign a transaction calling the contract and submit it, with the same gas and price rules
+    /// as `ContractClient::send`
+    pub fn send(
+        contract: &ContractClient,
+        method_signature: &[u8],
+        inputs: &[Token],
+        signing: &ContractClientSignature,
+    ) -> ChainRequestResult<H256> {
+        let input = encoder::encode_function_u8(method_signature, inputs);
+        let from = signing.from;
+        let now = sp_io::offchain::timestamp().unix_millis();
+        let nonce = Self::next_nonce(from, now);
+        let (_, gas_used) = E::call(
+            from,
+            contract.contract_address,
+            input.clone(),
+            U256::zero(),
+            true,
+        )?;
+        let gas_limit = ContractClient::gas_limit_with_margin(gas_used);
+        let gas_price = E::gas_price()
+            .mul(U256::from(125_u32))
+            .div(U256::from(100_u32));
+        let tx: LegacyTransaction = contract.signed_legacy_transaction(
+            input,
+            nonce,
+            gas_price,
+            gas_limit,
+            U256::zero(),
+            signing,
+        )?;
+        let tx_hash = ChainUtils::keccack(&tx.rlp_bytes());
+        E::submit_transaction(tx)?;
+        StorageValueRef::persistent(&native_nonce_key(&from)).set(&(nonce, now));
+        StorageValueRef::persistent(&native_tx_key(&tx_hash)).set(&(from, nonce));
+        log::debug!(
+            target: LOG_TARGET_RPC,
+            "[{}] Submitted native transaction {:?}",
+            contract.chain_id,
+            tx_hash
+        );
+        Ok(tx_hash)
+    }
+
+    /// Nonce of the next transaction of `from`. The transactions still in the pool do not show
+    /// in the state nonce, so it is above the last nonce used, unless that was more than
+    /// `TIMEOUT` ago: the service gave up on that transaction, and a dropped one leaves no gap
+    fn next_nonce(from: H160, now: u64) -> U256 {
+        let state_nonce = E::nonce(from);
+        let last_used = StorageValueRef::persistent(&native_nonce_key(&from))
+            .get::<(U256, u64)>()
+            .ok()
+            .flatten();
+        match last_used {
+            Some((last, used_at)) if used_at.saturating_add(TIMEOUT) >= now => {
+                state_nonce.max(last.saturating_add(U256::one()))
+            }
+            _ => state_nonce,
+        }
+    }
+
+    /// Status of a sent transaction. Its outcome is only known in the block including it. Once
+    /// the signer nonce moved past its nonce without the outcome being seen, another transaction
+    /// may have taken the nonce, so it is reported failed and the next run reads the ledger
+    /// manager state again
+    pub fn transaction_status(tx_hash: &H256) -> TransactionStatus {
+        let mut submitted = StorageValueRef::persistent(&native_tx_key(tx_hash));
+        let status = match E::transaction_succeeded(*tx_hash) {
+            Some(true) => TransactionStatus::Confirmed,
+            Some(false) => TransactionStatus::Failed,
+            None => match submitted.get::<(H160, U256)>().ok().flatten() {
+                Some((from, nonce)) if E::nonce(from) > nonce => TransactionStatus::Failed,
+                _ => TransactionStatus::NotFound,
+            },
+        };
+        if !matches!(status, TransactionStatus::NotFound) {
+            submitted.clear();
+        }
+        status
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{
+    qp_types::{QpMetrics, QpNetworkMetrics, QpPairMetrics, QpWorkerStatus},
+    LOG_TARGET,
+};
+use ferrum_primitives::OFFCHAIN_QP_METRICS_KEY;
+use sp_runtime::offchain::storage::{MutateStorageError, StorageValueRef};
+use sp_std::prelude::*;
+
+/// Records the offchain worker metrics in offchain storage.
+/// The node periodically reads the snapshot and exposes it on its prometheus endpoint.
+pub struct QpMetricsStore;
+
+impl QpMetricsStore {
+    /// Record the outcome of a mine step for the given pair
+    pub fn record_mine(remote_chain: u64, local_chain: u64, success: bool, tx_sent: bool) {
+        let now = sp_io::offchain::timestamp().unix_millis();
+        Self::update_pair(remote_chain, local_chain, |p| {
+            p.mine_attempts += 1;
+            if success {
+                p.mine_successes += 1;
+                p.last_success_timestamp = now;
+            }
+            if tx_sent {
+                p.mine_transactions += 1;
+            }
+        });
+    }
+
+    /// Record the outcome of a finalize step for the given pair
+    pub fn record_finalize(remote_chain: u64, local_chain: u64, success: bool, tx_sent: bool) {
+        let now = sp_io::offchain::timestamp().unix_millis();
+        Self::update_pair(remote_chain, local_chain, |p| {
+            p.finalize_attempts += 1;
+            if success {
+                p.finalize_successes += 1;
+                p.last_success_timestamp = now;
+            }
+            if tx_sent {
+                p.finalize_transactions += 1;
+            }
+        });
+    }
+
+    /// Record how far the local chain is behind the source chain
+    pub fn record_nonce_lag(
+        remote_chain: u64,
+        local_chain: u64,
+        source_nonce: u64,
+        mined_nonce: u64,
+    ) {
+        Self::update_pair(remote_chain, local_chain, |p| {
+            p.nonce_lag = source_nonce.saturating_sub(mined_nonce);
+        });
+    }
+
+    /// Record a pair about to be processed, so it is tracked before any of its steps completes
+    pub fn record_pair_due(remote_chain: u64, local_chain: u64) {
+        Self::update_pair(remote_chain, local_chain, |_| {});
+    }
+
+    /// Record a single json rpc request against the given network
+    pub fn record_rpc(chain_id: u64, latency_ms: u64, success: bool) {
+        Self::update_network(chain_id, |n| {
+            n.rpc_requests += 1;
+            n.rpc_latency_ms = n.rpc_latency_ms.saturating_add(latency_ms);
+            if !success {
+                n.rpc_errors += 1;
+            }
+        });
+    }
+
+    /// Record the age of the oldest pending transaction on the given network, 0 if none
+    pub fn record_pending_tx_age(chain_id: u64, age_ms: u64) {
+        Self::update_network(chain_id, |n| n.pending_tx_age_ms = age_ms);
+    }
+
+    /// Record a run or a pair being skipped because the lock was held
+    pub fn record_lock_contention() {
+        Self::update(|m| m.lock_contention += 1);
+    }
+
+    /// Record the outcome of the current run
+    pub fn record_status(status: QpWorkerStatus) {
+        let now = sp_io::offchain::timestamp().unix_millis();
+        Self::update(|m| {
+            m.status = status;
+            m.status_timestamp = now;
+        });
+    }
+
+    pub fn get() -> QpMetrics {
+        StorageValueRef::persistent(OFFCHAIN_QP_METRICS_KEY)
+            .get::<QpMetrics>()
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    fn update_pair<F: Fn(&mut QpPairMetrics)>(remote_chain: u64, local_chain: u64, f: F) {
+        Self::update(|m| {
+            let idx = match m
+                .pairs
+                .iter()
+                .position(|p| p.remote_chain == remote_chain && p.local_chain == local_chain)
+            {
+                Some(idx) => idx,
+                None => {
+                    m.pairs.push(QpPairMetrics {
+                        remote_chain,
+                        local_chain,
+                        first_seen_timestamp: sp_io::offchain::timestamp().unix_millis(),
+                        ..Default::default()
+                    });
+                    m.pairs.len() - 1
+                }
+            };
+            f(&mut m.pairs[idx]);
+        });
+    }
+
+    fn update_network<F: Fn(&mut QpNetworkMetrics)>(chain_id: u64, f: F) {
+        Self::update(|m| {
+            let idx = match m.networks.iter().position(|n| n.chain_id == chain_id) {
+                Some(idx) => idx,
+                None => {
+                    m.networks.push(QpNetworkMetrics {
+                        chain_id,
+                        ..Default::default()
+                    });
+                    m.networks.len() - 1
+                }
+            };
+            f(&mut m.networks[idx]);
+        });
+    }
+
+    fn update<F: Fn(&mut QpMetrics)>(f: F) {
+        let res = StorageValueRef::persistent(OFFCHAIN_QP_METRICS_KEY).mutate(
+            |current: Result<Option<QpMetrics>, _>| -> Result<QpMetrics, ()> {
+                // A snapshot we cannot decode is dropped rather than blocking the metrics forever
+                let mut metrics = current.ok().flatten().unwrap_or_default();
+                f(&mut metrics);
+                Ok(metrics)
+            },
+        );
+        if let Err(MutateStorageError::ConcurrentModification(_)) = res {
+            log::warn!(
+                target: LOG_TARGET,
+                "Could not update the QP metrics, concurrent modification"
+            );
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    qp_types::{QpSchedule, QpScheduleState},
+    LOG_TARGET,
+};
+use ferrum_primitives::OFFCHAIN_QP_SCHEDULE_KEY;
+use sp_runtime::offchain::storage::StorageValueRef;
+use sp_std::prelude::*;
+
+/// Decides which runs and pairs the offchain worker processes, following the configured
+/// `QpSchedule`. The state is persisted in offchain storage, so it survives blocks the worker
+/// did not run on and node restarts.
+pub struct QpScheduler {
+    schedule: QpSchedule,
+    state: QpScheduleState,
+}
+
+impl QpScheduler {
+    pub fn new(schedule: QpSchedule, state: QpScheduleState) -> Self {
+        QpScheduler { schedule, state }
+    }
+
+    /// The scheduler with the state left by the previous run
+    pub fn load(schedule: QpSchedule) -> Self {
+        let state = StorageValueRef::persistent(OFFCHAIN_QP_SCHEDULE_KEY)
+            .get::<QpScheduleState>()
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        Self::new(schedule, state)
+    }
+
+    pub fn save(&self) {
+        StorageValueRef::persistent(OFFCHAIN_QP_SCHEDULE_KEY).set(&self.state);
+    }
+
+    pub fn state(&self) -> &QpScheduleState {
+        &self.state
+    }
+
+    /// Whether the worker runs at this block, recording the run if so. A block below the last
+    /// run, after a revert or a chain reset, always runs
+    pub fn start_run(&mut self, block_number: u64) -> bool {
+        if !Self::is_due(
+            self.state.last_run_block,
+            block_number,
+            self.schedule.run_every_blocks,
+        ) {
+            return false;
+        }
+        self.state.last_run_block = Some(block_number);
+        true
+    }
+
+    /// When the run started at `now` must stop processing pairs, in millis
+    pub fn deadline(&self, now: u64) -> Option<u64> {
+        match self.schedule.run_budget_ms {
+            0 => None,
+            budget => Some(now.saturating_add(budget)),
+        }
+    }
+
+    /// Indexes of `pair_count` pairs in processing order. Starts with the pair after the last
+    /// one processed, so pairs left out by a run that ran out of time come first next time
+    pub fn pair_order(&self, pair_count: usize) -> Vec<usize> {
+        if pair_count == 0 {
+            return vec![];
+        }
+        let start = self.state.next_pair as usize % pair_count;
+        (0..pair_count).map(|i| (start + i) % pair_count).collect()
+    }
+
+    /// Whether the pair is due at this block
+    pub fn pair_due(&self, remote_chain: u64, local_chain: u64, block_number: u64) -> bool {
+        let last_run = self
+            .state
+            .pair_runs
+            .iter()
+            .find(|(r, l, _)| *r == remote_chain && *l == local_chain)
+            .map(|(_, _, block)| *block);
+        Self::is_due(last_run, block_number, self.schedule.pair_interval_blocks)
+    }
+
+    /// Record that the pair at index `idx` of `pairs` was processed at this block
+    pub fn record_pair(&mut self, pairs: &[(u64, u64)], idx: usize, block_number: u64) {
+        let (remote_chain, local_chain) = pairs[idx];
+        // Forget the pairs removed from the config
+        self.state
+            .pair_runs
+            .retain(|(r, l, _)| pairs.contains(&(*r, *l)));
+        match self
+            .state
+            .pair_runs
+            .iter_mut()
+            .find(|(r, l, _)| *r == remote_chain && *l == local_chain)
+        {
+            Some(run) => run.2 = block_number,
+            None => self
+                .state
+                .pair_runs
+                .push((remote_chain, local_chain, block_number)),
+        }
+        self.state.next_pair = ((idx + 1) % pairs.len()) as u32;
+        log::trace!(
+            target: LOG_TARGET,
+            "[{}=>{}] Processed at block {}, next run starts with pair {}",
+            remote_chain,
+            local_chain,
+            block_number,
+            self.state.next_pair
+        );
+    }
+
+    fn is_due(last_run: Option<u64>, block_number: u64, interval: u64) -> bool {
+        match last_run {
+            Some(last) if last <= block_number => block_number - last >= interval.max(1),
+            _ => true,
+        }
+    }
+}
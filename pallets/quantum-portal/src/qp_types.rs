@@ -1,8 +1,13 @@
-use ethabi_nostd::Address;
-use parity_scale_codec::{Decode, Encode};
+use crate::chain_utils::ChainUtils;
+use ethabi_nostd::{Address, Token};
+use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
 use serde::{Deserialize, Serialize};
-use sp_core::{H256, U256};
-use sp_std::{prelude::*, str};
+use sp_core::{H160, H256, U256};
+use sp_std::{
+    fmt::{self, Debug, Formatter},
+    prelude::*,
+    str,
+};
 
 #[derive(Debug, Default)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
@@ -17,6 +22,29 @@ pub struct QpTransaction {
     pub gas: u64,
 }
 
+impl QpTransaction {
+    /// Hash identifying a list of transactions: keccak256 of their ABI encoding as
+    /// `(uint64,address,address,address,address,uint256,bytes,uint256)[]`
+    pub fn transactions_hash(txs: &[QpTransaction]) -> H256 {
+        let txs = txs
+            .iter()
+            .map(|tx| {
+                Token::Tuple(vec![
+                    Token::Uint(U256::from(tx.timestamp)),
+                    Token::Address(tx.remote_contract),
+                    Token::Address(tx.source_msg_sender),
+                    Token::Address(tx.source_beneficiary),
+                    Token::Address(tx.token),
+                    Token::Uint(tx.amount),
+                    Token::Bytes(tx.method.clone()),
+                    Token::Uint(U256::from(tx.gas)),
+                ])
+            })
+            .collect();
+        ChainUtils::keccack(&ethabi_nostd::encode(&[Token::Array(txs)]))
+    }
+}
+
 #[derive(Debug)]
 pub struct QpLocalBlock {
     pub chain_id: u64,
@@ -50,17 +78,148 @@ pub struct QpConfig {
     pub signer_public_key: Vec<u8>,
     pub eip_712_config: EIP712Config,
     pub role: Role,
+    pub schedule: QpSchedule,
 }
 
-#[derive(
-    Clone, Eq, PartialEq, Decode, Encode, Debug, Serialize, Deserialize, scale_info::TypeInfo,
-)]
+#[derive(Clone, Eq, PartialEq, Decode, Encode, Serialize, Deserialize, scale_info::TypeInfo)]
 pub struct QpNetworkItem {
     // #[serde(with = "serde_bytes")]
     pub url: Vec<u8>,
     // #[serde(with = "serde_bytes")]
     pub ledger_manager: Vec<u8>,
     pub id: u64,
+    pub network_type: QpNetworkType,
+    pub auth: QpRpcAuth,
+    /// Depth a block of this network must have before its state is used, as protection
+    /// against reorgs
+    pub confirmations: u64,
+}
+
+/// Only shows the scheme and host of the url, which may hold an api key
+impl Debug for QpNetworkItem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QpNetworkItem")
+            .field("url", &ChainUtils::redact_url(&self.url))
+            .field("ledger_manager", &str::from_utf8(&self.ledger_manager))
+            .field("id", &self.id)
+            .field("network_type", &self.network_type)
+            .field("auth", &self.auth)
+            .field("confirmations", &self.confirmations)
+            .finish()
+    }
+}
+
+/// Credentials sent with every json rpc request of a network
+#[derive(
+    Clone, Eq, PartialEq, Decode, Encode, Serialize, Deserialize, scale_info::TypeInfo, Default,
+)]
+pub struct QpRpcAuth {
+    /// Extra headers as `(name, value)`, e.g. `x-api-key` or `Authorization: Bearer <token>`
+    pub headers: Vec<(Vec<u8>, Vec<u8>)>,
+    /// User and password for http basic auth
+    pub basic_auth: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl QpRpcAuth {
+    /// The headers to add to each request, basic auth included
+    pub fn http_headers(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut headers = self.headers.clone();
+        if let Some((user, password)) = &self.basic_auth {
+            let credentials = ChainUtils::base64_encode(&[&user[..], b":", &password[..]].concat());
+            headers.push((
+                b"Authorization".to_vec(),
+                [&b"Basic "[..], &credentials[..]].concat(),
+            ));
+        }
+        headers
+    }
+}
+
+/// Only shows the header names, never the values
+impl Debug for QpRpcAuth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self
+            .headers
+            .iter()
+            .map(|(name, _)| str::from_utf8(name).unwrap_or("<invalid>"))
+            .collect();
+        f.debug_struct("QpRpcAuth")
+            .field("headers", &names)
+            .field("basic_auth", &self.basic_auth.is_some())
+            .finish()
+    }
+}
+
+/// How the offchain worker reaches a network
+#[derive(
+    Clone,
+    Copy,
+    Eq,
+    PartialEq,
+    Decode,
+    Encode,
+    Debug,
+    Serialize,
+    Deserialize,
+    scale_info::TypeInfo,
+    Default,
+)]
+pub enum QpNetworkType {
+    /// An external EVM chain, through its JSON-RPC `url`
+    #[default]
+    Http,
+    /// The EVM of this chain, through the runtime. The `url` is not used
+    Native,
+}
+
+/// Parse the `type` of a configured network. Empty means a remote network, anything unknown is
+/// rejected rather than silently treated as one
+impl TryFrom<&[u8]> for QpNetworkType {
+    type Error = &'static str;
+
+    fn try_from(v: &[u8]) -> Result<Self, Self::Error> {
+        match v {
+            b"" | b"http" => Ok(Self::Http),
+            b"native" => Ok(Self::Native),
+            _ => Err("unknown network type, expected `http` or `native`"),
+        }
+    }
+}
+
+/// When the offchain worker runs and how long a run may spend on the pairs
+#[derive(
+    Clone, Eq, PartialEq, Decode, Encode, Debug, Serialize, Deserialize, scale_info::TypeInfo,
+)]
+#[serde(default)]
+pub struct QpSchedule {
+    /// Run at most once every this many blocks
+    pub run_every_blocks: u64,
+    /// Minimum number of blocks between two runs of the same pair
+    pub pair_interval_blocks: u64,
+    /// Time a run may spend processing pairs, in millis. The pairs not reached are processed
+    /// first by the next run. 0 for no limit
+    pub run_budget_ms: u64,
+}
+
+impl Default for QpSchedule {
+    fn default() -> Self {
+        QpSchedule {
+            run_every_blocks: 1,
+            pair_interval_blocks: 1,
+            run_budget_ms: 0,
+        }
+    }
+}
+
+/// Scheduling state of the offchain worker, kept in offchain storage between runs
+#[derive(Clone, Eq, PartialEq, Decode, Encode, Debug, Default)]
+pub struct QpScheduleState {
+    /// Block of the last run
+    pub last_run_block: Option<u64>,
+    /// Index in `pair_vec` of the pair the next run starts with
+    pub next_pair: u32,
+    /// `(remote_chain, local_chain, block)` of the last run of each pair
+    pub pair_runs: Vec<(u64, u64, u64)>,
 }
 
 #[derive(
@@ -110,3 +269,204 @@ impl From<&[u8]> for Role {
         }
     }
 }
+
+/// Counters for a single (remote_chain, local_chain) pair, kept by the offchain worker
+#[derive(Clone, Eq, PartialEq, Decode, Encode, Debug, Default, scale_info::TypeInfo)]
+pub struct QpPairMetrics {
+    pub remote_chain: u64,
+    pub local_chain: u64,
+    pub mine_attempts: u64,
+    pub mine_successes: u64,
+    pub mine_transactions: u64,
+    pub finalize_attempts: u64,
+    pub finalize_successes: u64,
+    pub finalize_transactions: u64,
+    /// Unix millis of the last mine or finalize step that completed without error
+    pub last_success_timestamp: u64,
+    /// Source block nonce minus the last nonce mined on the local chain
+    pub nonce_lag: u64,
+    /// Unix millis the worker first processed the pair
+    pub first_seen_timestamp: u64,
+}
+
+/// Counters for the RPC endpoint of a single network
+#[derive(Clone, Eq, PartialEq, Decode, Encode, Debug, Default, scale_info::TypeInfo)]
+pub struct QpNetworkMetrics {
+    pub chain_id: u64,
+    pub rpc_requests: u64,
+    pub rpc_errors: u64,
+    /// Sum of the latency of all requests, in millis
+    pub rpc_latency_ms: u64,
+    /// Age of the oldest pending transaction on this network, in millis
+    pub pending_tx_age_ms: u64,
+}
+
+/// Outcome of the last offchain worker run, used to tell a misconfigured worker from an idle one
+#[derive(Clone, Copy, Eq, PartialEq, Decode, Encode, Debug, scale_info::TypeInfo)]
+pub enum QpWorkerStatus {
+    /// The worker has not run yet
+    Unknown,
+    /// The worker processed its pairs
+    Running,
+    /// No QP configuration in offchain storage
+    NoConfig,
+    /// The node is neither a miner nor a finalizer
+    NoRole,
+    /// The configured signer key is not in the keystore
+    NoSignerKey,
+}
+
+impl Default for QpWorkerStatus {
+    fn default() -> Self {
+        QpWorkerStatus::Unknown
+    }
+}
+
+/// Snapshot of the offchain worker metrics as stored in offchain storage
+#[derive(Clone, Eq, PartialEq, Decode, Encode, Debug, Default, scale_info::TypeInfo)]
+pub struct QpMetrics {
+    pub pairs: Vec<QpPairMetrics>,
+    pub networks: Vec<QpNetworkMetrics>,
+    /// Number of times a run or a pair was skipped because the lock was held
+    pub lock_contention: u64,
+    /// Outcome of the last run
+    pub status: QpWorkerStatus,
+    /// When the status was recorded, in millis
+    pub status_timestamp: u64,
+}
+
+/// On chain state of a (remote_chain, local_chain) pair
+#[derive(
+    Clone,
+    Eq,
+    PartialEq,
+    Decode,
+    Encode,
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    scale_info::TypeInfo,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct QpPairInfo {
+    pub remote_chain: u64,
+    pub local_chain: u64,
+    /// Highest nonce a miner reported mined, unverified until the finalizers confirm it
+    pub last_mined_nonce: u64,
+    /// Last nonce a quorum of the finalizers confirmed finalized
+    pub last_finalized_nonce: u64,
+}
+
+/// Whether a block record was reported by a miner or a finalizer
+#[derive(
+    Clone,
+    Copy,
+    Eq,
+    PartialEq,
+    Decode,
+    Encode,
+    MaxEncodedLen,
+    Debug,
+    Serialize,
+    Deserialize,
+    scale_info::TypeInfo,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum QpBlockRecordKind {
+    Mined,
+    Finalized,
+}
+
+/// A mined or finalized block reported on chain
+#[derive(
+    Clone,
+    Eq,
+    PartialEq,
+    Decode,
+    Encode,
+    MaxEncodedLen,
+    Debug,
+    Serialize,
+    Deserialize,
+    scale_info::TypeInfo,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct QpBlockRecord<AccountId, BlockNumber> {
+    pub kind: QpBlockRecordKind,
+    pub remote_chain: u64,
+    pub local_chain: u64,
+    pub nonce: u64,
+    /// Miner or finalizer that reported the block
+    pub worker: AccountId,
+    /// Local block the report was included in
+    pub block_number: BlockNumber,
+}
+
+/// Role a worker bonded stake for
+#[derive(
+    Clone, Copy, Eq, PartialEq, Decode, Encode, MaxEncodedLen, Debug, scale_info::TypeInfo,
+)]
+pub enum QpWorkerRole {
+    Miner,
+    Finalizer,
+}
+
+/// A miner or finalizer registered on chain with bonded stake
+#[derive(Clone, Eq, PartialEq, Decode, Encode, MaxEncodedLen, Debug, scale_info::TypeInfo)]
+pub struct QpWorker<Balance, BlockNumber> {
+    pub role: QpWorkerRole,
+    /// EVM address the worker signs its mine and finalize transactions with
+    pub signer: H160,
+    /// Stake currently reserved
+    pub bond: Balance,
+    /// Block from which the bond can be withdrawn, set once the worker unbonds
+    pub unlock_at: Option<BlockNumber>,
+}
+
+/// Evidence that the transactions of a mined block differ from the transactions of the source
+/// chain block with the same nonce, as returned by `localBlockByNonce`
+#[derive(Clone, Eq, PartialEq, Decode, Encode, Debug, scale_info::TypeInfo)]
+pub struct QpFraudProof {
+    pub remote_chain: u64,
+    pub local_chain: u64,
+    pub nonce: u64,
+    /// Signer address of the miner that mined the block
+    pub miner: H160,
+    /// `QpTransaction::transactions_hash` of the mined block on the local chain
+    pub mined_txs_hash: H256,
+    /// `QpTransaction::transactions_hash` of the source block on the remote chain
+    pub source_txs_hash: H256,
+}
+
+impl QpFraudProof {
+    /// Message finalizers sign to attest both sides of the proof, an EIP-191 personal message
+    /// over keccak256("QP_FRAUD_PROOF" ++ Ferrum genesis hash ++ SCALE encoded proof). The
+    /// genesis hash keeps an attestation from being replayed on another chain.
+    pub fn attestation_hash<H: Encode>(&self, genesis_hash: &H) -> H256 {
+        let digest = ChainUtils::keccack(&(b"QP_FRAUD_PROOF", genesis_hash, self).encode());
+        let mut message = b"\x19Ethereum Signed Message:\n32".to_vec();
+        message.extend_from_slice(digest.as_bytes());
+        ChainUtils::keccack(&message)
+    }
+}
+
+/// Work a miner or finalizer reports on Ferrum. Its offchain worker signs the report with the
+/// worker signer key and submits it as an unsigned transaction.
+#[derive(Clone, Eq, PartialEq, Decode, Encode, Debug, scale_info::TypeInfo)]
+pub struct QpWorkReport {
+    pub remote_chain: u64,
+    pub local_chain: u64,
+    /// Nonce of the mined block on the local chain
+    pub nonce: u64,
+    /// Signer address of the miner of the block, as recorded by the local ledger manager
+    pub miner: H160,
+}
+
+impl QpWorkReport {
+    /// Hash the worker signs, keccak256("QP_WORK_REPORT" ++ Ferrum genesis hash ++ SCALE encoded
+    /// kind and report)
+    pub fn signing_hash<H: Encode>(&self, kind: QpBlockRecordKind, genesis_hash: &H) -> H256 {
+        ChainUtils::keccack(&(b"QP_WORK_REPORT", genesis_hash, kind, self).encode())
+    }
+}
@@ -1,27 +1,42 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 use crate::{
-    chain_queries::CallResponse,
-    chain_utils::{ChainRequestError, ChainRequestResult, ChainUtils, TransactionCreationError},
-    contract_client::{ContractClient, ContractClientSignature},
+    chain_queries::{BlockId, CallResponse, ChainQueries, TransactionStatus},
+    chain_utils::{
+        ChainRequestError, ChainRequestResult, ChainUtils, RevertReason, TransactionCreationError,
+    },
+    contract_client::{CallOptions, ContractClient, ContractClientSignature},
     eip_712_utils::EIP712Utils,
-    qp_types::{EIP712Config, QpLocalBlock, QpRemoteBlock, QpTransaction},
-    Config,
+    native_evm::NativeContractClient,
+    qp_metrics::QpMetricsStore,
+    qp_types::{EIP712Config, QpLocalBlock, QpNetworkType, QpRemoteBlock, QpTransaction},
+    Config, LOG_TARGET, LOG_TARGET_SIGN,
 };
 use ethabi_nostd::{decoder::decode, ParamKind, Token};
 use sp_core::{H256, U256};
 use sp_std::marker::PhantomData;
 use sp_std::prelude::*;
+use sp_std::str;
 
 #[allow(dead_code)]
 const DUMMY_HASH: H256 = H256::zero();
 const ZERO_HASH: H256 = H256::zero();
 
+/// Ledger manager method mining a block of remote transactions
+pub(crate) const MINE_METHOD_SIGNATURE: &[u8] = b"mineRemoteBlock(uint64,uint64,(uint64,address,address,address,address,uint256,bytes,uint256)[],bytes32,uint64,bytes)";
+/// Ledger manager method finalizing a mined block with a single finalizer signature
+pub(crate) const FINALIZE_METHOD_SIGNATURE: &[u8] =
+    b"finalizeSingleSigner(uint256,uint256,bytes32,address[],bytes32,uint64,bytes)";
+
 pub struct QuantumPortalClient<T: Config> {
     pub contract: ContractClient,
     pub signer: ContractClientSignature,
     pub now: u64,
     pub block_number: u64,
     pub eip_712_config: EIP712Config,
+    pub network_type: QpNetworkType,
+    /// Depth of the blocks whose state is read as a source network, and of the receipts of the
+    /// transactions sent to this network
+    pub confirmations: u64,
     _phantom: PhantomData<T>,
 }
 
@@ -49,7 +64,7 @@ fn decode_remote_block_and_txs<T, F>(
 where
     F: Fn(Token) -> ChainRequestResult<T>,
 {
-    log::info!("decode_remote_block_and_txs {:?}", data);
+    log::trace!(target: LOG_TARGET, "decode_remote_block_and_txs {:?}", data);
     // let dec = decode(
     //     &[
     //         ParamKind::Tuple(vec![
@@ -86,7 +101,12 @@ where
         ChainUtils::hex_to_bytes(data)?.as_slice(),
     )
     .unwrap();
-    log::info!("decoded {:?}, - {}", dec, dec.as_slice().len());
+    log::trace!(
+        target: LOG_TARGET,
+        "decoded {:?}, - {}",
+        dec,
+        dec.as_slice().len()
+    );
     let dec: ChainRequestResult<Vec<Token>> = match dec.as_slice() {
         [tuple, txs] => Ok(vec![tuple.clone(), txs.clone()]),
         _ => Err(
@@ -96,14 +116,22 @@ where
         ),
     };
     let dec = dec?;
-    log::info!("decoded = 2 | {:?}, - {}", dec, dec.as_slice().len());
+    log::trace!(
+        target: LOG_TARGET,
+        "decoded = 2 | {:?}, - {}",
+        dec,
+        dec.as_slice().len()
+    );
     match dec.as_slice() {
         [mined_block, remote_transactions] => {
             let mined_block = mined_block.clone();
             let remote_transactions = remote_transactions.clone();
-            log::info!("PRE = Mined block is opened up");
             let block = block_tuple_decoder(mined_block)?;
-            log::info!("Mined block is opened up == {:?}", remote_transactions);
+            log::trace!(
+                target: LOG_TARGET,
+                "Mined block is opened up == {:?}",
+                remote_transactions
+            );
             let remote_transactions = remote_transactions
                 .to_array()
                 .unwrap()
@@ -156,6 +184,8 @@ impl<T: Config> QuantumPortalClient<T> {
         now: u64,
         block_number: u64,
         eip_712_config: EIP712Config,
+        network_type: QpNetworkType,
+        confirmations: u64,
     ) -> Self {
         QuantumPortalClient {
             contract,
@@ -163,40 +193,155 @@ impl<T: Config> QuantumPortalClient<T> {
             now,
             block_number,
             eip_712_config,
+            network_type,
+            confirmations,
             _phantom: Default::default(),
         }
     }
 
-    pub fn is_local_block_ready(&self, chain_id: u64) -> ChainRequestResult<bool> {
+    /// Call a view method of the ledger manager with the latest state
+    fn call(
+        &self,
+        method_signature: &[u8],
+        inputs: &[Token],
+    ) -> ChainRequestResult<Box<CallResponse>> {
+        self.call_with(method_signature, inputs, &CallOptions::new())
+    }
+
+    /// Call a view method of the ledger manager, over json rpc or on the native EVM. The native
+    /// EVM only runs plain calls of the current state from `options.from`, the signer by default
+    fn call_with(
+        &self,
+        method_signature: &[u8],
+        inputs: &[Token],
+        options: &CallOptions,
+    ) -> ChainRequestResult<Box<CallResponse>> {
+        match self.network_type {
+            QpNetworkType::Http => self.contract.call(method_signature, inputs, options),
+            QpNetworkType::Native => NativeContractClient::<T::NativeEvm>::call(
+                &self.contract,
+                options.from.unwrap_or(self.signer.from),
+                method_signature,
+                inputs,
+            ),
+        }
+    }
+
+    /// Run a transaction of the ledger manager as a call from the signer, returning why the
+    /// ledger manager would reject it
+    pub fn preflight(
+        &self,
+        method_signature: &[u8],
+        inputs: &[Token],
+    ) -> ChainRequestResult<Option<RevertReason>> {
+        let options = CallOptions::new().from(self.signer.from);
+        match self.call_with(method_signature, inputs, &options) {
+            Ok(_) => Ok(None),
+            Err(ChainRequestError::Reverted(reason)) => Ok(Some(reason)),
+            Err(ChainRequestError::NativeEvmCallFailed(output)) => {
+                Ok(Some(RevertReason::from_revert_data(&output)))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Send a transaction calling the ledger manager, with the network nonce, gas estimate and
+    /// gas price. A transaction the pre-flight call shows would revert is not sent, so no gas
+    /// is paid for it
+    fn send(&self, method_signature: &[u8], inputs: &[Token]) -> ChainRequestResult<H256> {
+        if let Some(reason) = self.preflight(method_signature, inputs)? {
+            log::warn!(
+                target: LOG_TARGET,
+                "[{}] Not sending {}, the ledger manager would reject it: {:?}",
+                self.contract.chain_id,
+                str::from_utf8(method_signature)
+                    .unwrap_or_default()
+                    .split('(')
+                    .next()
+                    .unwrap_or_default(),
+                reason
+            );
+            return Err(ChainRequestError::TransactionSkipped(reason));
+        }
+        match self.network_type {
+            QpNetworkType::Http => self.contract.send(
+                method_signature,
+                inputs,
+                None,
+                None,
+                U256::zero(),
+                None,
+                self.signer.from,
+                &self.signer,
+            ),
+            QpNetworkType::Native => NativeContractClient::<T::NativeEvm>::send(
+                &self.contract,
+                method_signature,
+                inputs,
+                &self.signer,
+            ),
+        }
+    }
+
+    /// Status of a transaction sent to this network
+    pub fn transaction_status(&self, tx_id: &H256) -> ChainRequestResult<TransactionStatus> {
+        match self.network_type {
+            QpNetworkType::Http => ChainQueries::get_transaction_status(
+                str::from_utf8(&self.contract.http_api[..]).unwrap(),
+                &self.contract.auth,
+                self.contract.deadline,
+                tx_id,
+                self.confirmations,
+            ),
+            QpNetworkType::Native => Ok(NativeContractClient::<T::NativeEvm>::transaction_status(
+                tx_id,
+            )),
+        }
+    }
+
+    /// The latest block with the configured confirmations, whose state is safe to mine from
+    pub fn confirmed_block(&self) -> ChainRequestResult<BlockId> {
+        if self.confirmations == 0 || self.network_type == QpNetworkType::Native {
+            return Ok(BlockId::Latest);
+        }
+        let latest = self.contract.block_number()?;
+        Ok(BlockId::Number(latest.saturating_sub(self.confirmations)))
+    }
+
+    pub fn is_local_block_ready(&self, chain_id: u64, block: BlockId) -> ChainRequestResult<bool> {
         let signature = b"isLocalBlockReady(uint64)";
-        let res: Box<CallResponse> = self
-            .contract
-            .call(signature, &[Token::Uint(U256::from(chain_id))])?;
+        let res: Box<CallResponse> = self.call_with(
+            signature,
+            &[Token::Uint(U256::from(chain_id))],
+            &CallOptions::new().at(block),
+        )?;
         let val = ChainUtils::hex_to_u256(&res.result)?;
         Ok(!val.is_zero())
     }
 
     pub fn last_remote_mined_block(&self, chain_id: u64) -> ChainRequestResult<QpLocalBlock> {
         let signature = b"lastRemoteMinedBlock(uint64)";
-        let res: Box<CallResponse> = self
-            .contract
-            .call(signature, &[Token::Uint(U256::from(chain_id))])?;
+        let res: Box<CallResponse> = self.call(signature, &[Token::Uint(U256::from(chain_id))])?;
         self.decode_local_block(res.result.as_slice())
     }
 
     pub fn last_finalized_block(&self, chain_id: u64) -> ChainRequestResult<QpLocalBlock> {
         let signature = b"lastFinalizedBlock(uint256)";
-        let res: Box<CallResponse> = self
-            .contract
-            .call(signature, &[Token::Uint(U256::from(chain_id))])?;
+        let res: Box<CallResponse> = self.call(signature, &[Token::Uint(U256::from(chain_id))])?;
         self.decode_local_block(res.result.as_slice())
     }
 
-    pub fn last_local_block(&self, chain_id: u64) -> ChainRequestResult<QpLocalBlock> {
+    pub fn last_local_block(
+        &self,
+        chain_id: u64,
+        block: BlockId,
+    ) -> ChainRequestResult<QpLocalBlock> {
         let signature = b"lastLocalBlock(uint256)";
-        let res: Box<CallResponse> = self
-            .contract
-            .call(signature, &[Token::Uint(U256::from(chain_id))])?;
+        let res: Box<CallResponse> = self.call_with(
+            signature,
+            &[Token::Uint(U256::from(chain_id))],
+            &CallOptions::new().at(block),
+        )?;
         self.decode_local_block(res.result.as_slice())
     }
 
@@ -204,20 +349,22 @@ impl<T: Config> QuantumPortalClient<T> {
         &self,
         chain_id: u64,
         last_block_nonce: u64,
+        block: BlockId,
     ) -> ChainRequestResult<(QpLocalBlock, Vec<QpTransaction>)> {
         let signature = b"localBlockByNonce(uint64,uint64)";
-        let res: Box<CallResponse> = self.contract.call(
+        let res: Box<CallResponse> = self.call_with(
             signature,
             &[
                 Token::Uint(U256::from(chain_id)),
                 Token::Uint(U256::from(last_block_nonce)),
             ],
+            &CallOptions::new().at(block),
         )?;
         decode_remote_block_and_txs(res.result.as_slice(), local_block_tuple(), |block| {
-            log::info!("1-DECODING BLOCK {:?}", block);
+            log::trace!(target: LOG_TARGET, "1-DECODING BLOCK {:?}", block);
             let block = block.to_tuple();
             let block = block.unwrap();
-            log::info!("2-DECODING BLOCK {:?}", block);
+            log::trace!(target: LOG_TARGET, "2-DECODING BLOCK {:?}", block);
             Self::decode_local_block_from_tuple(block.as_slice())
         })
     }
@@ -228,7 +375,7 @@ impl<T: Config> QuantumPortalClient<T> {
         last_block_nonce: u64,
     ) -> ChainRequestResult<(QpRemoteBlock, Vec<QpTransaction>)> {
         let signature = b"minedBlockByNonce(uint64,uint64)";
-        let res: Box<CallResponse> = self.contract.call(
+        let res: Box<CallResponse> = self.call(
             signature,
             &[
                 Token::Uint(U256::from(chain_id)),
@@ -251,7 +398,7 @@ impl<T: Config> QuantumPortalClient<T> {
         //                                    local_block_tuple()
         // ];
         decode_remote_block_and_txs(res.result.as_slice(), mined_block_tuple, |block| {
-            log::info!("Decoding local block, {:?}", block);
+            log::trace!(target: LOG_TARGET, "Decoding local block, {:?}", block);
             Self::decode_mined_block_from_tuple(block.to_tuple().unwrap().as_slice())
         })
     }
@@ -273,12 +420,21 @@ impl<T: Config> QuantumPortalClient<T> {
         // ) ...
         // The last item is a bit complicated, but for now we pass an empty array.
         // Support buytes and dynamic arrays in future
-        let finalizer_list: Vec<Token> = vec![];
-
         let (block_details, _) = self.mined_block_by_nonce(remote_chain_id, block_nonce)?;
+        let inputs = self.finalize_inputs(remote_chain_id, block_nonce, &block_details)?;
 
-        let method_signature =
-            b"finalizeSingleSigner(uint256,uint256,bytes32,address[],bytes32,uint64,bytes)";
+        self.send(FINALIZE_METHOD_SIGNATURE, &inputs)
+    }
+
+    /// Inputs of the `finalizeSingleSigner` call for the given mined block, signed by the
+    /// configured signer
+    pub(crate) fn finalize_inputs(
+        &self,
+        remote_chain_id: u64,
+        block_nonce: u64,
+        block_details: &QpRemoteBlock,
+    ) -> ChainRequestResult<Vec<Token>> {
+        let finalizer_list: Vec<Token> = vec![];
 
         // generate randomness for salt
         // let (random_hash, _) = T::PalletRandomness::random_seed();
@@ -304,13 +460,14 @@ impl<T: Config> QuantumPortalClient<T> {
             expiry.clone(),
         )?;
 
-        log::info!(
+        log::trace!(
+            target: LOG_TARGET_SIGN,
             "Encoded Multisig generated : {:?}",
             sp_std::str::from_utf8(ChainUtils::bytes_to_hex(multi_sig.as_slice()).as_slice())
                 .unwrap()
         );
 
-        let inputs = [
+        Ok(vec![
             Token::Uint(U256::from(remote_chain_id)),
             Token::Uint(U256::from(block_nonce)),
             finalizer_hash,
@@ -318,19 +475,7 @@ impl<T: Config> QuantumPortalClient<T> {
             salt,
             expiry,
             Token::Bytes(multi_sig),
-        ];
-
-        let res = self.contract.send(
-            method_signature,
-            &inputs,
-            None, //Some(U256::from(1000000 as u64)), // None,
-            None, // Some(U256::from(10000000000 as u64)), // None,
-            U256::zero(),
-            None,
-            self.signer.from,
-            &self.signer,
-        )?;
-        Ok(res)
+        ])
     }
 
     /// Returns the multiSignature to sign finalize transactions
@@ -354,22 +499,31 @@ impl<T: Config> QuantumPortalClient<T> {
             self.contract.chain_id,                 // ChainId
             &self.eip_712_config.verifying_address, // VerifyingAddress
         );
-        log::info!("domain_seperator_hash {:?}", domain_seperator_hash);
+        log::trace!(
+            target: LOG_TARGET_SIGN,
+            "domain_seperator_hash {:?}",
+            domain_seperator_hash
+        );
 
         // Generate the finalize method sigature to encode the finalize call
         let finalize_method_signature = b"Finalize(uint256 remoteChainId,uint256 blockNonce,bytes32 finalizersHash,address[] finalizers,bytes32 salt,uint64 expiry)";
         let finalize_method_signature_hash = ChainUtils::keccack(finalize_method_signature);
-        log::info!(
+        log::trace!(
+            target: LOG_TARGET_SIGN,
             "finalize_method_signature_hash {:?}",
             finalize_method_signature_hash
         );
 
-        log::info!("remote_chain_id {:?}", remote_chain_id);
-        log::info!("block_nonde {:?}", block_nonce);
-        log::info!("finalizer_hash {:?}", finalizer_hash);
-        log::info!("finalizer_list {:?}", finalizer_list);
-        log::info!("salt {:?}", salt);
-        log::info!("expiry {:?}", expiry);
+        log::trace!(
+            target: LOG_TARGET_SIGN,
+            "Finalize args: remote_chain_id {:?}, block_nonce {:?}, finalizer_hash {:?}, finalizer_list {:?}, salt {:?}, expiry {:?}",
+            remote_chain_id,
+            block_nonce,
+            finalizer_hash,
+            finalizer_list,
+            salt,
+            expiry
+        );
 
         // encode the finalize call to the expected format
         let encoded_message_hash = EIP712Utils::get_encoded_hash(vec![
@@ -381,12 +535,16 @@ impl<T: Config> QuantumPortalClient<T> {
             salt.clone(),                             // salt
             expiry.clone(),                           // expiry
         ]);
-        log::info!("encoded_message_hash {:?}", encoded_message_hash);
+        log::trace!(
+            target: LOG_TARGET_SIGN,
+            "encoded_message_hash {:?}",
+            encoded_message_hash
+        );
 
         // Generate the ValidateAuthoritySignature method signature to encode the eip_args
         let method_signature = b"ValidateAuthoritySignature(uint256 action,bytes32 msgHash,bytes32 salt,uint64 expiry)";
         let method_hash = ChainUtils::keccack(method_signature);
-        log::info!("method_hash {:?}", method_hash);
+        log::trace!(target: LOG_TARGET_SIGN, "method_hash {:?}", method_hash);
 
         // Generate the encoded eip message
         let eip_args_hash = EIP712Utils::get_encoded_hash(vec![
@@ -396,11 +554,11 @@ impl<T: Config> QuantumPortalClient<T> {
             salt,                                                 // salt
             expiry,                                               // expiry
         ]);
-        log::info!("eip_args_hash {:?}", eip_args_hash);
+        log::trace!(target: LOG_TARGET_SIGN, "eip_args_hash {:?}", eip_args_hash);
 
         let eip_712_hash =
             EIP712Utils::generate_eip_712_hash(&domain_seperator_hash[..], &eip_args_hash[..]);
-        log::info!("EIP712 Hash {:?}", eip_712_hash);
+        log::debug!(target: LOG_TARGET_SIGN, "EIP712 Hash {:?}", eip_712_hash);
 
         // Sign the eip message, we only consider a single signer here since we only expect a single key in the keystore
         // TODO : Add the ability for multiple signers
@@ -413,7 +571,8 @@ impl<T: Config> QuantumPortalClient<T> {
         multisig_compressed.extend([28u8]);
         multisig_compressed.extend([0u8; 31]);
 
-        log::info!(
+        log::trace!(
+            target: LOG_TARGET_SIGN,
             "Extended signature of size {}: {}",
             multisig_compressed.len(),
             sp_std::str::from_utf8(
@@ -425,14 +584,12 @@ impl<T: Config> QuantumPortalClient<T> {
         Ok(multisig_compressed)
     }
 
-    pub fn create_mine_transaction(
-        &self,
+    /// Inputs of the `mineRemoteBlock` call for the given source block transactions
+    pub(crate) fn mine_inputs(
         remote_chain_id: u64,
         block_nonce: u64,
-        txs: &Vec<QpTransaction>,
-    ) -> ChainRequestResult<H256> {
-        let method_signature = b"mineRemoteBlock(uint64,uint64,(uint64,address,address,address,address,uint256,bytes,uint256)[],bytes32,uint64,bytes)";
-
+        txs: &[QpTransaction],
+    ) -> Vec<Token> {
         let salt = Token::FixedBytes(vec![
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 1,
@@ -459,34 +616,43 @@ impl<T: Config> QuantumPortalClient<T> {
             })
             .collect();
 
-        let res = self.contract.send(
-            method_signature,
-            &[
-                Token::Uint(U256::from(remote_chain_id)),
-                Token::Uint(U256::from(block_nonce)),
-                Token::Array(tx_vec),
-                salt,
-                expiry,
-                multi_sig,
-            ],
-            None, // Some(U256::from(1000000 as u32)), // None,
-            None, // Some(U256::from(60000000000 as u64)), // None,
-            U256::zero(),
-            None,
-            self.signer.from,
-            &self.signer,
-        )?;
-        Ok(res)
+        vec![
+            Token::Uint(U256::from(remote_chain_id)),
+            Token::Uint(U256::from(block_nonce)),
+            Token::Array(tx_vec),
+            salt,
+            expiry,
+            multi_sig,
+        ]
+    }
+
+    pub fn create_mine_transaction(
+        &self,
+        remote_chain_id: u64,
+        block_nonce: u64,
+        txs: &Vec<QpTransaction>,
+    ) -> ChainRequestResult<H256> {
+        self.send(
+            MINE_METHOD_SIGNATURE,
+            &Self::mine_inputs(remote_chain_id, block_nonce, txs),
+        )
     }
 
     pub fn finalize(&self, chain_id: u64) -> ChainRequestResult<Option<H256>> {
-        log::info!("finalize({})", chain_id);
+        let local_chain = self.contract.chain_id;
         let block = self.last_remote_mined_block(chain_id)?;
-        log::info!("finalize-last_remote_mined_block({:?})", &block);
+        log::trace!(target: LOG_TARGET, "Last remote mined block {:?}", &block);
         let last_fin = self.last_finalized_block(chain_id)?;
-        log::info!("finalize-last_finalized_block({:?})", &last_fin);
+        log::trace!(target: LOG_TARGET, "Last finalized block {:?}", &last_fin);
         if block.nonce > last_fin.nonce {
-            log::info!("Calling mgr.finalize({}, {})", chain_id, block.nonce);
+            log::info!(
+                target: LOG_TARGET,
+                "[{}=>{}] Finalizing nonce {} (last finalized {})",
+                chain_id,
+                local_chain,
+                block.nonce,
+                last_fin.nonce
+            );
             Ok(Some(self.create_finalize_transaction(
                 chain_id,
                 block.nonce,
@@ -494,7 +660,12 @@ impl<T: Config> QuantumPortalClient<T> {
                 &[self.signer.get_signer_address()],
             )?))
         } else {
-            log::info!("Nothing to finalize for ({})", chain_id);
+            log::debug!(
+                target: LOG_TARGET,
+                "[{}=>{}] Nothing to finalize",
+                chain_id,
+                local_chain
+            );
             Ok(None)
         }
     }
@@ -502,37 +673,56 @@ impl<T: Config> QuantumPortalClient<T> {
     pub fn mine(&self, remote_client: &QuantumPortalClient<T>) -> ChainRequestResult<Option<H256>> {
         let local_chain = self.contract.chain_id;
         let remote_chain = remote_client.contract.chain_id;
-        log::info!("mine({} => {})", remote_chain, local_chain);
-        let block_ready = remote_client.is_local_block_ready(local_chain)?;
-        log::info!("local block ready? {}", block_ready);
+        // Source blocks closed in the last `confirmations` blocks wait for a later run
+        let source_state = remote_client.confirmed_block()?;
+        let block_ready = remote_client.is_local_block_ready(local_chain, source_state)?;
         if !block_ready {
+            log::debug!(
+                target: LOG_TARGET,
+                "[{}=>{}] No local block ready to mine at {:?}",
+                remote_chain,
+                local_chain,
+                source_state
+            );
             return Ok(None);
         }
-        log::info!("Getting last local block");
-        let last_block = remote_client.last_local_block(local_chain)?;
-        log::info!("Last local block is {:?}", last_block);
+        let last_block = remote_client.last_local_block(local_chain, source_state)?;
+        log::trace!(target: LOG_TARGET, "Last local block is {:?}", last_block);
         let last_mined_block = self.last_remote_mined_block(remote_chain)?;
-        log::info!("Local block f remote (chain {}) nonce is {}. Remote mined block on local (chain {}) is {}",
-			remote_chain, last_block.nonce, local_chain, last_mined_block.nonce);
+        log::debug!(
+            target: LOG_TARGET,
+            "[{}=>{}] Source nonce is {}, last mined nonce is {}",
+            remote_chain,
+            local_chain,
+            last_block.nonce,
+            last_mined_block.nonce
+        );
+        QpMetricsStore::record_nonce_lag(
+            remote_chain,
+            local_chain,
+            last_block.nonce,
+            last_mined_block.nonce,
+        );
         if last_mined_block.nonce >= last_block.nonce {
-            log::info!("Nothing to mine!");
+            log::debug!(
+                target: LOG_TARGET,
+                "[{}=>{}] Nothing to mine",
+                remote_chain,
+                local_chain
+            );
             return Ok(None);
         }
-        log::info!(
-            "Last block is on chain1 for target {} is {}",
-            local_chain,
-            last_block.nonce
-        );
         let mined_block = self.mined_block_by_nonce(remote_chain, last_block.nonce)?;
         let already_mined = !mined_block.0.block_hash.eq(&ZERO_HASH);
         if already_mined {
             return Err(ChainRequestError::RemoteBlockAlreadyMined);
         }
-        log::info!("Getting source block?");
-        let source_block = remote_client.local_block_by_nonce(local_chain, last_block.nonce)?;
+        let source_block =
+            remote_client.local_block_by_nonce(local_chain, last_block.nonce, source_state)?;
         let default_qp_transaction = QpTransaction::default();
-        log::info!(
-            "Source block is GOT\n{:?}\n{:?}",
+        log::trace!(
+            target: LOG_TARGET,
+            "Source block {:?}, first transaction {:?}",
             source_block.0,
             if !source_block.1.is_empty() {
                 source_block.1.get(0).unwrap()
@@ -542,9 +732,12 @@ impl<T: Config> QuantumPortalClient<T> {
         );
         let txs = source_block.1;
         log::info!(
-            "About to mine block {}:{}",
+            target: LOG_TARGET,
+            "[{}=>{}] Mining nonce {} with {} transaction(s)",
             remote_chain,
-            source_block.0.nonce
+            local_chain,
+            source_block.0.nonce,
+            txs.len()
         );
         Ok(Some(self.create_mine_transaction(
             remote_chain,
@@ -564,7 +757,7 @@ impl<T: Config> QuantumPortalClient<T> {
     }
 
     fn decode_local_block_from_tuple(dec: &[Token]) -> ChainRequestResult<QpLocalBlock> {
-        log::info!("Decoding local block, {:?}", dec);
+        log::trace!(target: LOG_TARGET, "Decoding local block, {:?}", dec);
         match dec {
             [chain_id, nonce, timestamp] => {
                 let chain_id = chain_id.clone().to_uint();
@@ -583,11 +776,16 @@ impl<T: Config> QuantumPortalClient<T> {
     }
 
     fn decode_mined_block_from_tuple(dec: &[Token]) -> ChainRequestResult<QpRemoteBlock> {
-        log::info!("decode_mined_block_from_tuple {:?}", dec);
+        log::trace!(
+            target: LOG_TARGET,
+            "decode_mined_block_from_tuple {:?}",
+            dec
+        );
         match dec {
             [block_hash, miner, stake, total_value, block_metadata] => {
-                log::info!(
-                    "D {:?}::{:?}:{:?}:{:?}::{:?}",
+                log::trace!(
+                    target: LOG_TARGET,
+                    "Mined block {:?}::{:?}:{:?}:{:?}::{:?}",
                     block_hash,
                     miner,
                     stake,
@@ -599,10 +797,8 @@ impl<T: Config> QuantumPortalClient<T> {
                 let stake = stake.clone();
                 let total_value = total_value.clone();
                 let block_metadata = block_metadata.clone();
-                log::info!("Decoding block metadata");
                 let block_metadata =
                     Self::decode_local_block_from_tuple(&block_metadata.to_tuple().unwrap())?;
-                log::info!("DecodED block metadata");
                 Ok(QpRemoteBlock {
                     block_hash: H256::from_slice(block_hash.to_fixed_bytes().unwrap().as_slice()),
                     miner: miner.to_address().unwrap(),
@@ -1,9 +1,10 @@
 use crate::{
-    chain_queries::{ChainQueries, TransactionStatus},
-    chain_utils::{ChainRequestResult, ChainUtils},
-    qp_types::Role,
+    chain_queries::TransactionStatus,
+    chain_utils::{ChainRequestError, ChainRequestResult, ChainUtils, RevertReason},
+    qp_metrics::QpMetricsStore,
+    qp_types::{QpBlockRecordKind, QpWorkReport, Role},
     quantum_portal_client::QuantumPortalClient,
-    Config,
+    Config, LastFinalizedNonce, LastReportedNonce, Pallet, Signers, LOG_TARGET, MAX_NONCE_JUMP,
 };
 use frame_support::codec::{Decode, Encode};
 use parity_scale_codec::MaxEncodedLen;
@@ -11,7 +12,11 @@ use sp_core::H256;
 use sp_runtime::offchain::storage::StorageValueRef;
 use sp_std::{marker::PhantomData, prelude::*, str};
 
-const TIMEOUT: u64 = 3600 * 1000;
+/// Time after which a transaction the network does not know about is dropped, in milliseconds
+pub(crate) const TIMEOUT: u64 = 3600 * 1000;
+/// Age after which the process lock is considered left behind by a run that panicked, in
+/// milliseconds. Well above the time a pair takes with its rpc timeouts and retries
+pub(crate) const LOCK_EXPIRY: u64 = 10 * 60 * 1000;
 
 #[derive(Debug, Encode, Decode, Clone, PartialEq, MaxEncodedLen, scale_info::TypeInfo)]
 pub enum PendingTransaction {
@@ -27,6 +32,66 @@ impl Default for PendingTransaction {
     }
 }
 
+impl PendingTransaction {
+    /// The time the transaction was sent, 0 for `None`
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            PendingTransaction::MineTransaction(_, _, timestamp, _) => *timestamp,
+            PendingTransaction::FinalizeTransaction(_, timestamp, _) => *timestamp,
+            PendingTransaction::None => 0,
+        }
+    }
+}
+
+/// What happened to a pair during a single offchain worker run
+#[derive(Debug, Clone, PartialEq)]
+pub enum PairOutcome {
+    /// Another run holds the process lock
+    Locked,
+    /// Waiting for the given number of pending transactions
+    Pending(usize),
+    /// Mine transaction sent
+    Mined(H256),
+    /// Finalize transaction sent
+    Finalized(H256),
+    /// Nothing to mine or finalize
+    Idle,
+    /// The mine or finalize transaction was not sent, since the ledger manager would reject it
+    Skipped(RevertReason),
+}
+
+/// Counts of the pair outcomes of a single offchain worker run, logged once per run
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct QpRunSummary {
+    pub pairs: u32,
+    pub mined: u32,
+    pub finalized: u32,
+    pub pending: u32,
+    pub locked: u32,
+    pub idle: u32,
+    pub skipped: u32,
+    pub errors: u32,
+    /// Pairs skipped because they ran less than `pair_interval_blocks` ago
+    pub not_due: u32,
+    /// Pairs left for the next run because the time budget was spent
+    pub deferred: u32,
+}
+
+impl QpRunSummary {
+    pub fn record(&mut self, outcome: &ChainRequestResult<PairOutcome>) {
+        self.pairs += 1;
+        match outcome {
+            Ok(PairOutcome::Locked) => self.locked += 1,
+            Ok(PairOutcome::Pending(_)) => self.pending += 1,
+            Ok(PairOutcome::Mined(_)) => self.mined += 1,
+            Ok(PairOutcome::Finalized(_)) => self.finalized += 1,
+            Ok(PairOutcome::Idle) => self.idle += 1,
+            Ok(PairOutcome::Skipped(_)) => self.skipped += 1,
+            Err(_) => self.errors += 1,
+        }
+    }
+}
+
 pub struct QuantumPortalService<T: Config> {
     pub clients: Vec<QuantumPortalClient<T>>,
     _phantom: PhantomData<T>,
@@ -42,28 +107,36 @@ impl<T: Config> QuantumPortalService<T> {
 
     fn lock_is_open(&self) -> ChainRequestResult<bool> {
         // Save a None tx.
-        let tx = self.stored_pending_transactions(9999)?;
-        log::info!("Current pending txs {:?}", tx);
-        if tx.is_empty() {
-            log::info!("No lock! We can go ahead");
+        let tx = match self.stored_pending_transactions(9999)?.pop() {
+            None => return Ok(true),
+            Some(tx) => tx,
+        };
+        let now = sp_io::offchain::timestamp().unix_millis();
+        if tx.timestamp().saturating_add(LOCK_EXPIRY) < now {
+            // Left by a run that did not get to release it
+            log::warn!(
+                target: LOG_TARGET,
+                "Process lock taken at {} expired",
+                tx.timestamp()
+            );
             return Ok(true);
         }
-        log::info!("LOCKED! {:?}", tx.get(0).unwrap());
+        log::debug!(target: LOG_TARGET, "Process lock held {:?}", tx);
         Ok(false)
     }
 
-    fn lock(&self) -> ChainRequestResult<()> {
-        log::info!("Saving a lock!");
+    pub(crate) fn lock(&self) -> ChainRequestResult<()> {
+        log::trace!(target: LOG_TARGET, "Saving the process lock");
         self.save_tx(PendingTransaction::FinalizeTransaction(
             9999,
-            0,
+            sp_io::offchain::timestamp().unix_millis(),
             H256::zero(),
         ))?;
         Ok(())
     }
 
     fn remove_lock(&self) -> ChainRequestResult<()> {
-        log::info!("Removing a lock!");
+        log::trace!(target: LOG_TARGET, "Removing the process lock");
         let tx = PendingTransaction::FinalizeTransaction(9999, 0, H256::zero());
         self.remove_transaction_from_db(&tx)?;
         Ok(())
@@ -74,132 +147,195 @@ impl<T: Config> QuantumPortalService<T> {
         remote_chain: u64,
         local_chain: u64,
         role: Role,
-    ) -> ChainRequestResult<()> {
+    ) -> ChainRequestResult<PairOutcome> {
         if !self.lock_is_open()? {
+            QpMetricsStore::record_lock_contention();
             log::info!(
-                "We will not proceed because we have a process lock lock. Processing {} => {}",
+                target: LOG_TARGET,
+                "[{}=>{}] Skipping, the process lock is held",
                 remote_chain,
                 local_chain
             );
-            return Ok(());
+            return Ok(PairOutcome::Locked);
         }
         self.lock()?;
-        let tx = self.stored_pending_transactions(9999)?;
-        log::info!("RESULTAT OF PENDING_TX {:?}", tx);
         let rv = self.process_pair(remote_chain, local_chain, role);
         self.remove_lock()?;
         rv
     }
 
-    pub fn test_tx_storage_and_status(&self) -> ChainRequestResult<()> {
-        // TODO: Move this to a proper integ test
-        // Get the status of non-existing tx
-        // Get the status of an existing transaction... (successful)
-        // Get the status of an existing transaction... (failed)
-        // Save an extisting tx and set the timeout number
-        let recent_time = self.clients.get(0).unwrap().now - 10000;
-        let old_time = recent_time - 30 * 3600 * 1000;
-        let ip = self.is_tx_pending(&PendingTransaction::FinalizeTransaction(
-            4_u64,
-            recent_time,
-            H256::from_slice(
-                ChainUtils::hex_to_bytes(
-                    b"0x3eadda1dfb4daaaa42865b154afa24ff7517e1e05db20e2b4200000000000000",
-                )
-                .unwrap()
-                .as_slice(),
-            ),
-        ))?;
-        log::info!("Non existing recent tx is pending? {}", ip);
-        let ip = self.is_tx_pending(&PendingTransaction::FinalizeTransaction(
-            4_u64,
-            old_time,
-            H256::from_slice(
-                ChainUtils::hex_to_bytes(
-                    b"0x3eadda1dfb4daaaa42865b154afa24ff7517e1e05db20e2b4200000000000000",
-                )
-                .unwrap()
-                .as_slice(),
-            ),
-        ))?;
-        log::info!("Non existing [TIEMD OUT] recent tx is pending? {}", ip);
-        let ip = self.is_tx_pending(&PendingTransaction::FinalizeTransaction(
-            4_u64,
-            old_time,
-            H256::from_slice(
-                ChainUtils::hex_to_bytes(
-                    b"0x029729a1d69ddeaa8f6c2417ae0e799d5784a12f04675785432d6441c5e5b881",
-                )
-                .unwrap()
-                .as_slice(),
-            ),
-        ))?;
-        log::info!("Existing successful tx is pending? {}", ip);
-        Ok(())
-    }
-
     pub fn process_pair(
         &self,
         remote_chain: u64,
         local_chain: u64,
         role: Role,
-    ) -> ChainRequestResult<()> {
+    ) -> ChainRequestResult<PairOutcome> {
         // Processes between two chains.
         // If there is an existing pending tx, for this pair, it will wait until the pending is
         // completed or timed out.
         // Nonce management? :: V1. No special nonce management
         //                      V2. TODO: record and re-use the nonce to ensure controlled timeouts
 
-        log::info!("process_pair: {} -> {}", remote_chain, local_chain);
         let live_txs = self.pending_transactions(local_chain)?; // TODO: Consider having separate config per pair
         if !live_txs.is_empty() {
             log::info!(
-                "There are already {} pending transactions. Ignoring this round",
+                target: LOG_TARGET,
+                "[{}=>{}] Waiting for {} pending transaction(s)",
+                remote_chain,
+                local_chain,
                 live_txs.len()
             );
-            return Ok(());
+            return Ok(PairOutcome::Pending(live_txs.len()));
         }
-        let local_client: &QuantumPortalClient<T> =
-            &self.clients[self.find_client_idx(local_chain)];
-        let remote_client: &QuantumPortalClient<T> =
-            &self.clients[self.find_client_idx(remote_chain)];
-        log::info!(
+        let local_client: &QuantumPortalClient<T> = self.client(local_chain)?;
+        let remote_client: &QuantumPortalClient<T> = self.client(remote_chain)?;
+        log::debug!(
+            target: LOG_TARGET,
             "Clients: {} <> {} :: {} <> {}",
             local_client.block_number,
             remote_client.block_number,
-            str::from_utf8(&local_client.contract.http_api[..]).unwrap(),
-            str::from_utf8(&remote_client.contract.http_api[..]).unwrap()
+            ChainUtils::redact_url(&local_client.contract.http_api),
+            ChainUtils::redact_url(&remote_client.contract.http_api)
         );
         let now = local_client.now;
+        let mut outcome = PairOutcome::Idle;
+
+        self.report_work(local_client, remote_chain, local_chain, &role)?;
 
         // mine if role is miner
         if role == Role::QP_MINER {
-            let mine_tx = local_client.mine(remote_client)?;
-            if mine_tx.is_some() {
+            let mine_res = local_client.mine(remote_client);
+            let skipped = matches!(mine_res, Err(ChainRequestError::TransactionSkipped(_)));
+            QpMetricsStore::record_mine(
+                remote_chain,
+                local_chain,
+                mine_res.is_ok() || skipped,
+                matches!(mine_res, Ok(Some(_))),
+            );
+            let mine_tx = match mine_res {
+                Err(ChainRequestError::TransactionSkipped(reason)) => {
+                    outcome = PairOutcome::Skipped(reason);
+                    None
+                }
+                res => res?,
+            };
+            if let Some(tx_id) = mine_tx {
+                log::info!(
+                    target: LOG_TARGET,
+                    "[{}=>{}] Sent mine transaction {:?}",
+                    remote_chain,
+                    local_chain,
+                    tx_id
+                );
                 self.save_tx(PendingTransaction::MineTransaction(
                     local_chain,
                     remote_chain,
                     now,
-                    mine_tx.unwrap(),
-                ))?
+                    tx_id,
+                ))?;
+                outcome = PairOutcome::Mined(tx_id);
             }
         }
         // finalize if role is finalizer
         if role == Role::QP_FINALIZER {
-            let fin_tx = local_client.finalize(remote_chain)?;
-            if fin_tx.is_some() {
+            let fin_res = local_client.finalize(remote_chain);
+            let skipped = matches!(fin_res, Err(ChainRequestError::TransactionSkipped(_)));
+            QpMetricsStore::record_finalize(
+                remote_chain,
+                local_chain,
+                fin_res.is_ok() || skipped,
+                matches!(fin_res, Ok(Some(_))),
+            );
+            let fin_tx = match fin_res {
+                Err(ChainRequestError::TransactionSkipped(reason)) => {
+                    outcome = PairOutcome::Skipped(reason);
+                    None
+                }
+                res => res?,
+            };
+            if let Some(tx_id) = fin_tx {
+                log::info!(
+                    target: LOG_TARGET,
+                    "[{}=>{}] Sent finalize transaction {:?}",
+                    remote_chain,
+                    local_chain,
+                    tx_id
+                );
                 // Save tx
-                // MineTransaction(chain, remote_chain, timestamp, tx_id)
+                // FinalizeTransaction(chain, timestamp, tx_id)
                 self.save_tx(PendingTransaction::FinalizeTransaction(
                     local_chain,
                     now,
-                    fin_tx.unwrap(),
-                ))?
+                    tx_id,
+                ))?;
+                outcome = PairOutcome::Finalized(tx_id);
             }
         }
 
         self.remove_lock()?;
-        Ok(())
+        Ok(outcome)
+    }
+
+    /// Report the latest block of the pair mined by the worker, or attest the latest block
+    /// finalized on the local chain, so the work is credited on Ferrum. Nothing is read from the
+    /// ledger manager until the signer is registered as an active worker.
+    fn report_work(
+        &self,
+        local_client: &QuantumPortalClient<T>,
+        remote_chain: u64,
+        local_chain: u64,
+        role: &Role,
+    ) -> ChainRequestResult<()> {
+        let signer = local_client.signer.from;
+        let who = match Signers::<T>::get(signer) {
+            Some(who) => who,
+            None => return Ok(()),
+        };
+        let pair = (remote_chain, local_chain);
+        let last_finalized = LastFinalizedNonce::<T>::get(remote_chain, local_chain);
+        let (kind, block) = if *role == Role::QP_MINER && Pallet::<T>::is_miner(&who) {
+            let block = local_client.last_remote_mined_block(remote_chain)?;
+            if block.nonce > last_finalized.saturating_add(MAX_NONCE_JUMP) {
+                return Ok(());
+            }
+            (QpBlockRecordKind::Mined, block)
+        } else if *role == Role::QP_FINALIZER && Pallet::<T>::is_finalizer(&who) {
+            (
+                QpBlockRecordKind::Finalized,
+                local_client.last_finalized_block(remote_chain)?,
+            )
+        } else {
+            return Ok(());
+        };
+        let last_reported = LastReportedNonce::<T>::get(pair, &who).unwrap_or_default();
+        if block.nonce <= last_finalized || block.nonce <= last_reported {
+            // Already reported, or confirmed without this worker
+            return Ok(());
+        }
+
+        let (mined, _) = local_client.mined_block_by_nonce(remote_chain, block.nonce)?;
+        if kind == QpBlockRecordKind::Mined && mined.miner != signer {
+            // Mined by another miner
+            return Ok(());
+        }
+        log::info!(
+            target: LOG_TARGET,
+            "[{}=>{}] Reporting {:?} block {}",
+            remote_chain,
+            local_chain,
+            kind,
+            block.nonce
+        );
+        Pallet::<T>::submit_work_report(
+            &local_client.signer,
+            kind,
+            QpWorkReport {
+                remote_chain,
+                local_chain,
+                nonce: block.nonce,
+                miner: mined.miner,
+            },
+        )
     }
 
     fn storage_key(key: u64) -> Vec<u8> {
@@ -212,7 +348,7 @@ impl<T: Config> QuantumPortalService<T> {
         Vec::from(key.as_slice())
     }
 
-    fn save_tx(&self, tx: PendingTransaction) -> ChainRequestResult<()> {
+    pub(crate) fn save_tx(&self, tx: PendingTransaction) -> ChainRequestResult<()> {
         let key = Self::storage_key_for_tx(&tx);
         let key = Self::storage_key(key);
         let key = key.as_slice();
@@ -223,27 +359,32 @@ impl<T: Config> QuantumPortalService<T> {
 
     fn pending_transactions(&self, chain_id: u64) -> ChainRequestResult<Vec<PendingTransaction>> {
         let stored_pending_transactions = self.stored_pending_transactions(chain_id)?;
-        Ok(stored_pending_transactions
-            .into_iter()
-            .filter(
-                |t| self.is_tx_pending(t).unwrap(), // TODO: No unwrap here.
-            )
-            .collect())
+        let mut live_txs: Vec<PendingTransaction> = Vec::new();
+        for t in stored_pending_transactions {
+            if self.is_tx_pending(&t)? {
+                live_txs.push(t);
+            }
+        }
+        let now = self.client(chain_id)?.now;
+        let oldest_age = live_txs
+            .iter()
+            .map(|t| now.saturating_sub(t.timestamp()))
+            .max()
+            .unwrap_or(0);
+        QpMetricsStore::record_pending_tx_age(chain_id, oldest_age);
+        Ok(live_txs)
     }
 
-    fn stored_pending_transactions(
+    pub(crate) fn stored_pending_transactions(
         &self,
         chain_id: u64,
     ) -> ChainRequestResult<Vec<PendingTransaction>> {
         let key = Self::storage_key(chain_id);
         let key = key.as_slice();
         let s = StorageValueRef::persistent(key);
-        let rv = s.get().unwrap();
+        let rv = s.get().map_err(|_| ChainRequestError::ConversionError)?;
         Ok(match rv {
-            None => {
-                log::info!("stored_pending_transactions nichivo");
-                Vec::new()
-            }
+            None => Vec::new(),
             Some(v) => vec![v],
         })
     }
@@ -257,7 +398,7 @@ impl<T: Config> QuantumPortalService<T> {
         Ok(())
     }
 
-    fn is_tx_pending(&self, t: &PendingTransaction) -> ChainRequestResult<bool> {
+    pub(crate) fn is_tx_pending(&self, t: &PendingTransaction) -> ChainRequestResult<bool> {
         // Check if the tx is still pending
         // If so, return true.
         // otherwise. Update storage and remove the tx.
@@ -269,24 +410,23 @@ impl<T: Config> QuantumPortalService<T> {
             }
             PendingTransaction::None => panic!("tx is none"),
         };
-        let client = &self.clients[self.find_client_idx(*chain_id1)];
+        let client = self.client(*chain_id1)?;
 
-        log::info!(
+        log::debug!(
+            target: LOG_TARGET,
             "is_tx_pending {}::{:?} ({}) [Current time {}]",
             chain_id1,
             tx_id,
             timestamp,
             client.now
         );
-        let status = ChainQueries::get_transaction_status(
-            str::from_utf8(&client.contract.http_api[..]).unwrap(),
-            tx_id,
-        )?;
+        let status = client.transaction_status(tx_id)?;
         let res = match status {
             TransactionStatus::Confirmed => {
                 // Remove
                 log::info!(
-                    "The transaction is confirmed! {} - {}",
+                    target: LOG_TARGET,
+                    "[{}] Transaction {} confirmed",
                     chain_id1,
                     str::from_utf8(ChainUtils::h256_to_hex_0x(tx_id).as_slice()).unwrap()
                 );
@@ -295,8 +435,9 @@ impl<T: Config> QuantumPortalService<T> {
             }
             TransactionStatus::Failed => {
                 // Remove
-                log::info!(
-                    "The transaction is failed! Please investigate {} - {}",
+                log::warn!(
+                    target: LOG_TARGET,
+                    "[{}] Transaction {} failed, please investigate",
                     chain_id1,
                     str::from_utf8(ChainUtils::h256_to_hex_0x(tx_id).as_slice()).unwrap()
                 );
@@ -307,7 +448,8 @@ impl<T: Config> QuantumPortalService<T> {
             TransactionStatus::NotFound => {
                 if (timestamp + TIMEOUT) < client.now {
                     log::error!(
-                        "The transaction is timed out! Please investigate {} - {}",
+                        target: LOG_TARGET,
+                        "[{}] Transaction {} timed out, please investigate",
                         chain_id1,
                         str::from_utf8(ChainUtils::h256_to_hex_0x(tx_id).as_slice()).unwrap()
                     );
@@ -321,11 +463,11 @@ impl<T: Config> QuantumPortalService<T> {
         Ok(res)
     }
 
-    fn find_client_idx(&self, chain_id: u64) -> usize {
-        let c = self.clients.as_slice();
-        c.iter()
-            .position(|c| c.contract.chain_id == chain_id)
-            .unwrap()
+    fn client(&self, chain_id: u64) -> ChainRequestResult<&QuantumPortalClient<T>> {
+        self.clients
+            .iter()
+            .find(|c| c.contract.chain_id == chain_id)
+            .ok_or(ChainRequestError::NetworkNotConfigured(chain_id))
     }
 
     fn storage_key_for_tx(tx: &PendingTransaction) -> u64 {
@@ -1,407 +1,2265 @@
-// This file is part of Substrate.
-
-// Copyright (C) 2020-2021 Parity Technologies (UK) Ltd.
-// SPDX-License-Identifier: Apache-2.0
-
-// Licensed under the Apache License, Version 2.0 (the "License");
-// you may not use this file except in compliance with the License.
-// You may obtain a copy of the License at
-//
-// 	http://www.apache.org/licenses/LICENSE-2.0
-//
-// Unless required by applicable law or agreed to in writing, software
-// distributed under the License is distributed on an "AS IS" BASIS,
-// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
-// See the License for the specific language governing permissions and
-// limitations under the License.
-
-use crate as example_offchain_worker;
-use crate::*;
-use codec::Decode;
-use frame_support::{assert_ok, parameter_types};
+use crate::{
+    chain_queries::{retry_delay, BlockId, CallResponse, TransactionStatus},
+    chain_utils::{ChainRequestError, ChainUtils, HttpAttemptError, RevertReason},
+    contract_client::CallOptions,
+    migrations::v1,
+    mock::*,
+    native_evm::NativeContractClient,
+    qp_schedule::QpScheduler,
+    qp_types::{
+        QpBlockRecordKind, QpFraudProof, QpLocalBlock, QpNetworkItem, QpNetworkType, QpRemoteBlock,
+        QpRpcAuth, QpSchedule, QpTransaction, QpWorkReport, QpWorkerRole, Role,
+    },
+    quantum_portal_client::{
+        QuantumPortalClient, FINALIZE_METHOD_SIGNATURE, MINE_METHOD_SIGNATURE,
+    },
+    quantum_portal_service::{PairOutcome, PendingTransaction, LOCK_EXPIRY, TIMEOUT},
+};
+use crate::{
+    Call, CurrentEra, EraTotalWork, EraWork, Error, Event, LastFinalizedNonce, LastMinedNonce,
+    LastReportedNonce, Miners, PayoutEra, PendingFinalizations, PendingRewards, ProvenFrauds,
+    Signers, UnconfirmedMinedBlocks, Workers, MAX_FRAUD_ATTESTATIONS, MAX_NONCE_JUMP,
+    MAX_PAYOUTS_PER_BLOCK,
+};
+use ethabi_nostd::{encoder, Token};
+use ferrum_primitives::OFFCHAIN_SIGNER_KEY_TYPE;
+use frame_support::{
+    assert_noop, assert_ok,
+    traits::{
+        Currency, GetStorageVersion, Hooks, OnRuntimeUpgrade, ReservableCurrency, StorageVersion,
+    },
+};
+use parity_scale_codec::Decode;
 use sp_core::{
-	offchain::{testing, OffchainWorkerExt, TransactionPoolExt},
-	sr25519::Signature,
-	H256,
+    ecdsa,
+    offchain::{
+        testing::{OffchainState, PendingRequest, TestTransactionPoolExt},
+        HttpError, Timestamp, TransactionPoolExt,
+    },
+    sr25519, H160, H256, U256,
 };
-use std::sync::Arc;
-
-use sp_keystore::{testing::KeyStore, KeystoreExt, SyncCryptoStore};
 use sp_runtime::{
-	testing::{Header, TestXt},
-	traits::{BlakeTwo256, Extrinsic as ExtrinsicT, IdentifyAccount, IdentityLookup, Verify},
-	RuntimeAppPublic,
+    offchain::storage::StorageValueRef,
+    testing::TestXt,
+    traits::{Dispatchable, ValidateUnsigned},
+    transaction_validity::{InvalidTransaction, TransactionSource},
+    DispatchError, DispatchResult,
 };
 
-type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
-type Block = frame_system::mocking::MockBlock<Test>;
-
-// For testing the module, we construct a mock runtime.
-frame_support::construct_runtime!(
-	pub enum Test where
-		Block = Block,
-		NodeBlock = Block,
-		UncheckedExtrinsic = UncheckedExtrinsic,
-	{
-		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
-		Example: example_offchain_worker::{Pallet, Call, Storage, Event<T>, ValidateUnsigned},
-	}
-);
-
-parameter_types! {
-	pub const BlockHashCount: u64 = 250;
-	pub BlockWeights: frame_system::limits::BlockWeights =
-		frame_system::limits::BlockWeights::simple_max(1024);
-}
-impl frame_system::Config for Test {
-	type BaseCallFilter = frame_support::traits::Everything;
-	type BlockWeights = ();
-	type BlockLength = ();
-	type DbWeight = ();
-	type Origin = Origin;
-	type Call = Call;
-	type Index = u64;
-	type BlockNumber = u64;
-	type Hash = H256;
-	type Hashing = BlakeTwo256;
-	type AccountId = sp_core::sr25519::Public;
-	type Lookup = IdentityLookup<Self::AccountId>;
-	type Header = Header;
-	type Event = Event;
-	type BlockHashCount = BlockHashCount;
-	type Version = ();
-	type PalletInfo = PalletInfo;
-	type AccountData = ();
-	type OnNewAccount = ();
-	type OnKilledAccount = ();
-	type SystemWeightInfo = ();
-	type SS58Prefix = ();
-	type OnSetCode = ();
-}
-
-type Extrinsic = TestXt<Call, ()>;
-type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
-
-impl frame_system::offchain::SigningTypes for Test {
-	type Public = <Signature as Verify>::Signer;
-	type Signature = Signature;
-}
-
-impl<LocalCall> frame_system::offchain::SendTransactionTypes<LocalCall> for Test
-	where
-		Call: From<LocalCall>,
-{
-	type OverarchingCall = Call;
-	type Extrinsic = Extrinsic;
-}
-
-impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Test
-	where
-		Call: From<LocalCall>,
-{
-	fn create_transaction<C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>>(
-		call: Call,
-		_public: <Signature as Verify>::Signer,
-		_account: AccountId,
-		nonce: u64,
-	) -> Option<(Call, <Extrinsic as ExtrinsicT>::SignaturePayload)> {
-		Some((call, (nonce, ())))
-	}
-}
-
-parameter_types! {
-	pub const GracePeriod: u64 = 5;
-	pub const UnsignedInterval: u64 = 128;
-	pub const UnsignedPriority: u64 = 1 << 20;
-}
-
-impl Config for Test {
-	type Event = Event;
-	type AuthorityId = crypto::TestAuthId;
-	type Call = Call;
-	type GracePeriod = GracePeriod;
-	type UnsignedInterval = UnsignedInterval;
-	type UnsignedPriority = UnsignedPriority;
-}
-
-#[test]
-fn it_aggregates_the_price() {
-	sp_io::TestExternalities::default().execute_with(|| {
-		assert_eq!(Example::average_price(), None);
-
-		assert_ok!(Example::submit_price(Origin::signed(Default::default()), 27));
-		assert_eq!(Example::average_price(), Some(27));
-
-		assert_ok!(Example::submit_price(Origin::signed(Default::default()), 43));
-		assert_eq!(Example::average_price(), Some(35));
-	});
-}
-
-#[test]
-fn should_make_http_call_and_parse_result() {
-	let (offchain, state) = testing::TestOffchainExt::new();
-	let mut t = sp_io::TestExternalities::default();
-	t.register_extension(OffchainWorkerExt::new(offchain));
-
-	price_oracle_response(&mut state.write());
-
-	t.execute_with(|| {
-		// when
-		let price = Example::fetch_price().unwrap();
-		// then
-		assert_eq!(price, 15523);
-	});
-}
-
-#[test]
-fn knows_how_to_mock_several_http_calls() {
-	let (offchain, state) = testing::TestOffchainExt::new();
-	let mut t = sp_io::TestExternalities::default();
-	t.register_extension(OffchainWorkerExt::new(offchain));
-
-	{
-		let mut state = state.write();
-		state.expect_request(testing::PendingRequest {
-			method: "GET".into(),
-			uri: "https://min-api.cryptocompare.com/data/price?fsym=BTC&tsyms=USD".into(),
-			response: Some(br#"{"USD": 1}"#.to_vec()),
-			sent: true,
-			..Default::default()
-		});
-
-		state.expect_request(testing::PendingRequest {
-			method: "GET".into(),
-			uri: "https://min-api.cryptocompare.com/data/price?fsym=BTC&tsyms=USD".into(),
-			response: Some(br#"{"USD": 2}"#.to_vec()),
-			sent: true,
-			..Default::default()
-		});
-
-		state.expect_request(testing::PendingRequest {
-			method: "GET".into(),
-			uri: "https://min-api.cryptocompare.com/data/price?fsym=BTC&tsyms=USD".into(),
-			response: Some(br#"{"USD": 3}"#.to_vec()),
-			sent: true,
-			..Default::default()
-		});
-	}
-
-	t.execute_with(|| {
-		let price1 = Example::fetch_price().unwrap();
-		let price2 = Example::fetch_price().unwrap();
-		let price3 = Example::fetch_price().unwrap();
-
-		assert_eq!(price1, 100);
-		assert_eq!(price2, 200);
-		assert_eq!(price3, 300);
-	})
-}
-
-#[test]
-fn should_submit_signed_transaction_on_chain() {
-	const PHRASE: &str =
-		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
-
-	let (offchain, offchain_state) = testing::TestOffchainExt::new();
-	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
-	let keystore = KeyStore::new();
-	SyncCryptoStore::sr25519_generate_new(
-		&keystore,
-		crate::crypto::Public::ID,
-		Some(&format!("{}/hunter1", PHRASE)),
-	)
-		.unwrap();
-
-	let mut t = sp_io::TestExternalities::default();
-	t.register_extension(OffchainWorkerExt::new(offchain));
-	t.register_extension(TransactionPoolExt::new(pool));
-	t.register_extension(KeystoreExt(Arc::new(keystore)));
-
-	price_oracle_response(&mut offchain_state.write());
-
-	t.execute_with(|| {
-		// when
-		Example::fetch_price_and_send_signed().unwrap();
-		// then
-		let tx = pool_state.write().transactions.pop().unwrap();
-		assert!(pool_state.read().transactions.is_empty());
-		let tx = Extrinsic::decode(&mut &*tx).unwrap();
-		assert_eq!(tx.signature.unwrap().0, 0);
-		assert_eq!(tx.call, Call::Example(crate::Call::submit_price { price: 15523 }));
-	});
-}
-
-#[test]
-fn should_submit_unsigned_transaction_on_chain_for_any_account() {
-	const PHRASE: &str =
-		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
-	let (offchain, offchain_state) = testing::TestOffchainExt::new();
-	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
-
-	let keystore = KeyStore::new();
-
-	SyncCryptoStore::sr25519_generate_new(
-		&keystore,
-		crate::crypto::Public::ID,
-		Some(&format!("{}/hunter1", PHRASE)),
-	)
-		.unwrap();
-
-	let public_key = SyncCryptoStore::sr25519_public_keys(&keystore, crate::crypto::Public::ID)
-		.get(0)
-		.unwrap()
-		.clone();
-
-	let mut t = sp_io::TestExternalities::default();
-	t.register_extension(OffchainWorkerExt::new(offchain));
-	t.register_extension(TransactionPoolExt::new(pool));
-	t.register_extension(KeystoreExt(Arc::new(keystore)));
-
-	price_oracle_response(&mut offchain_state.write());
-
-	let price_payload = PricePayload {
-		block_number: 1,
-		price: 15523,
-		public: <Test as SigningTypes>::Public::from(public_key),
-	};
-
-	// let signature = price_payload.sign::<crypto::TestAuthId>().unwrap();
-	t.execute_with(|| {
-		// when
-		Example::fetch_price_and_send_unsigned_for_any_account(1).unwrap();
-		// then
-		let tx = pool_state.write().transactions.pop().unwrap();
-		let tx = Extrinsic::decode(&mut &*tx).unwrap();
-		assert_eq!(tx.signature, None);
-		if let Call::Example(crate::Call::submit_price_unsigned_with_signed_payload {
-								 price_payload: body,
-								 signature,
-							 }) = tx.call
-		{
-			assert_eq!(body, price_payload);
-
-			let signature_valid =
-				<PricePayload<
-					<Test as SigningTypes>::Public,
-					<Test as frame_system::Config>::BlockNumber,
-				> as SignedPayload<Test>>::verify::<crypto::TestAuthId>(&price_payload, signature);
-
-			assert!(signature_valid);
-		}
-	});
-}
-
-#[test]
-fn should_submit_unsigned_transaction_on_chain_for_all_accounts() {
-	const PHRASE: &str =
-		"news slush supreme milk chapter athlete soap sausage put clutch what kitten";
-	let (offchain, offchain_state) = testing::TestOffchainExt::new();
-	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
-
-	let keystore = KeyStore::new();
-
-	SyncCryptoStore::sr25519_generate_new(
-		&keystore,
-		crate::crypto::Public::ID,
-		Some(&format!("{}/hunter1", PHRASE)),
-	)
-		.unwrap();
-
-	let public_key = SyncCryptoStore::sr25519_public_keys(&keystore, crate::crypto::Public::ID)
-		.get(0)
-		.unwrap()
-		.clone();
-
-	let mut t = sp_io::TestExternalities::default();
-	t.register_extension(OffchainWorkerExt::new(offchain));
-	t.register_extension(TransactionPoolExt::new(pool));
-	t.register_extension(KeystoreExt(Arc::new(keystore)));
-
-	price_oracle_response(&mut offchain_state.write());
-
-	let price_payload = PricePayload {
-		block_number: 1,
-		price: 15523,
-		public: <Test as SigningTypes>::Public::from(public_key),
-	};
-
-	// let signature = price_payload.sign::<crypto::TestAuthId>().unwrap();
-	t.execute_with(|| {
-		// when
-		Example::fetch_price_and_send_unsigned_for_all_accounts(1).unwrap();
-		// then
-		let tx = pool_state.write().transactions.pop().unwrap();
-		let tx = Extrinsic::decode(&mut &*tx).unwrap();
-		assert_eq!(tx.signature, None);
-		if let Call::Example(crate::Call::submit_price_unsigned_with_signed_payload {
-								 price_payload: body,
-								 signature,
-							 }) = tx.call
-		{
-			assert_eq!(body, price_payload);
-
-			let signature_valid =
-				<PricePayload<
-					<Test as SigningTypes>::Public,
-					<Test as frame_system::Config>::BlockNumber,
-				> as SignedPayload<Test>>::verify::<crypto::TestAuthId>(&price_payload, signature);
-
-			assert!(signature_valid);
-		}
-	});
-}
-
-#[test]
-fn should_submit_raw_unsigned_transaction_on_chain() {
-	let (offchain, offchain_state) = testing::TestOffchainExt::new();
-	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
-
-	let keystore = KeyStore::new();
-
-	let mut t = sp_io::TestExternalities::default();
-	t.register_extension(OffchainWorkerExt::new(offchain));
-	t.register_extension(TransactionPoolExt::new(pool));
-	t.register_extension(KeystoreExt(Arc::new(keystore)));
-
-	price_oracle_response(&mut offchain_state.write());
-
-	t.execute_with(|| {
-		// when
-		Example::fetch_price_and_send_raw_unsigned(1).unwrap();
-		// then
-		let tx = pool_state.write().transactions.pop().unwrap();
-		assert!(pool_state.read().transactions.is_empty());
-		let tx = Extrinsic::decode(&mut &*tx).unwrap();
-		assert_eq!(tx.signature, None);
-		assert_eq!(
-			tx.call,
-			Call::Example(crate::Call::submit_price_unsigned { block_number: 1, price: 15523 })
-		);
-	});
-}
-
-fn price_oracle_response(state: &mut testing::OffchainState) {
-	state.expect_request(testing::PendingRequest {
-		method: "GET".into(),
-		uri: "https://min-api.cryptocompare.com/data/price?fsym=BTC&tsyms=USD".into(),
-		response: Some(br#"{"USD": 155.23}"#.to_vec()),
-		sent: true,
-		..Default::default()
-	});
-}
-
-#[test]
-fn parse_price_works() {
-	let test_data = vec![
-		("{\"USD\":6536.92}", Some(653692)),
-		("{\"USD\":65.92}", Some(6592)),
-		("{\"USD\":6536.924565}", Some(653692)),
-		("{\"USD\":6536}", Some(653600)),
-		("{\"USD2\":6536}", None),
-		("{\"USD\":\"6432\"}", None),
-	];
-
-	for (json, expected) in test_data {
-		assert_eq!(expected, Example::parse_price(json));
-	}
+/// Lock entry of the service in the pending transaction storage
+const LOCK_CHAIN: u64 = 9999;
+/// Nonce of the next source block on the remote network
+const SOURCE_NONCE: u64 = 3;
+const SOURCE_TIMESTAMP: u64 = 1_669_999_000;
+/// Scripted answers to the transaction parameters requests
+const ACCOUNT_NONCE: u64 = 5;
+const GAS_LIMIT: u64 = 200_000;
+const GAS_PRICE: u64 = 1_000_000_000;
+
+fn hex_0x(bytes: &[u8]) -> String {
+    String::from_utf8(ChainUtils::hex_add_0x(&ChainUtils::bytes_to_hex(bytes))).unwrap()
+}
+
+fn quoted(s: &str) -> String {
+    format!("\"{}\"", s)
+}
+
+/// Json rpc result holding the ABI encoding of the given tokens
+fn abi_result(tokens: &[Token]) -> String {
+    quoted(&hex_0x(&encoder::encode(tokens)))
+}
+
+fn uint(v: u64) -> Token {
+    Token::Uint(U256::from(v))
+}
+
+/// Local block fields. Static tuples are encoded in place, so they are flattened into the
+/// surrounding tokens of the scripted results
+fn local_block(chain_id: u64, nonce: u64, timestamp: u64) -> Vec<Token> {
+    vec![uint(chain_id), uint(nonce), uint(timestamp)]
+}
+
+fn source_tx() -> QpTransaction {
+    QpTransaction {
+        timestamp: SOURCE_TIMESTAMP,
+        remote_contract: H160::repeat_byte(0x11),
+        source_msg_sender: H160::repeat_byte(0x22),
+        source_beneficiary: H160::repeat_byte(0x33),
+        token: H160::repeat_byte(0x44),
+        amount: U256::from(1_000_000u64),
+        method: vec![0xde, 0xad, 0xbe, 0xef],
+        gas: 100_000,
+    }
+}
+
+fn tx_token(tx: &QpTransaction) -> Token {
+    Token::Tuple(vec![
+        uint(tx.timestamp),
+        Token::Address(tx.remote_contract),
+        Token::Address(tx.source_msg_sender),
+        Token::Address(tx.source_beneficiary),
+        Token::Address(tx.token),
+        Token::Uint(tx.amount),
+        Token::Bytes(tx.method.clone()),
+        uint(tx.gas),
+    ])
+}
+
+fn rpc_url(chain_id: u64) -> &'static str {
+    if chain_id == REMOTE_CHAIN {
+        REMOTE_RPC
+    } else {
+        LOCAL_RPC
+    }
+}
+
+/// Expect an `eth_call` of the ledger manager on the given network
+fn expect_call(
+    state: &mut OffchainState,
+    chain_id: u64,
+    signature: &[u8],
+    inputs: &[Token],
+    result: &[Token],
+) {
+    expect_call_at(state, chain_id, "latest", signature, inputs, result);
+}
+
+/// Expect an `eth_call` of the ledger manager reading the state of the given block
+fn expect_call_at(
+    state: &mut OffchainState,
+    chain_id: u64,
+    block: &str,
+    signature: &[u8],
+    inputs: &[Token],
+    result: &[Token],
+) {
+    let params = format!(
+        r#"{{"data":"{}","to":"{}"}},"{}""#,
+        hex_0x(&encoder::encode_function_u8(signature, inputs)),
+        hex_0x(ledger_manager(chain_id).as_bytes()),
+        block,
+    );
+    expect_rpc(
+        state,
+        rpc_url(chain_id),
+        "eth_call",
+        &params,
+        &abi_result(result),
+    );
+}
+
+/// Expect the `eth_chainId` check before the first request reading from or sending to a
+/// network. A verified chain id is kept in offchain storage, later requests skip the check
+fn expect_chain_id(state: &mut OffchainState, url: &str, chain_id: u64) {
+    expect_rpc(
+        state,
+        url,
+        "eth_chainId",
+        "",
+        &quoted(&format!("{:#x}", chain_id)),
+    );
+}
+
+/// Params of the pre-flight `eth_call` of a transaction, from the signer
+fn preflight_params(
+    client: &QuantumPortalClient<Test>,
+    signature: &[u8],
+    inputs: &[Token],
+) -> String {
+    format!(
+        r#"{{"data":"{}","to":"{}","from":"{}"}},"latest""#,
+        hex_0x(&encoder::encode_function_u8(signature, inputs)),
+        hex_0x(client.contract.contract_address.as_bytes()),
+        hex_0x(client.signer.from.as_bytes()),
+    )
+}
+
+/// Expect the requests of a transaction sent by the client, answered with `tx_hash`. Must run
+/// inside the externalities, to sign the expected raw transaction
+fn expect_send(
+    state: &mut OffchainState,
+    client: &QuantumPortalClient<Test>,
+    signature: &[u8],
+    inputs: &[Token],
+    tx_hash: H256,
+) {
+    let url = rpc_url(client.contract.chain_id);
+    let input = encoder::encode_function_u8(signature, inputs);
+    let from = hex_0x(client.signer.from.as_bytes());
+    expect_rpc(
+        state,
+        url,
+        "eth_call",
+        &preflight_params(client, signature, inputs),
+        &quoted("0x"),
+    );
+    expect_rpc(
+        state,
+        url,
+        "eth_getTransactionCount",
+        &format!(r#""{}","latest""#, from),
+        &quoted(&format!("{:#x}", ACCOUNT_NONCE)),
+    );
+    expect_rpc(
+        state,
+        url,
+        "eth_estimateGas",
+        &format!(
+            r#"{{"input":"{}","from":"{}","to":"{}","value":"0x0"}},"latest""#,
+            hex_0x(&input),
+            from,
+            hex_0x(client.contract.contract_address.as_bytes()),
+        ),
+        &quoted(&format!("{:#x}", GAS_LIMIT)),
+    );
+    expect_rpc(
+        state,
+        url,
+        "eth_gasPrice",
+        "",
+        &quoted(&format!("{:#x}", GAS_PRICE)),
+    );
+    // The worker bids 25% over the network gas price, with 20% more gas than the estimate
+    let raw_tx = client
+        .contract
+        .signed_transaction(
+            input,
+            U256::from(ACCOUNT_NONCE),
+            U256::from(GAS_PRICE * 125 / 100),
+            U256::from(GAS_LIMIT * 120 / 100),
+            U256::zero(),
+            &client.signer,
+        )
+        .unwrap();
+    expect_rpc(
+        state,
+        url,
+        "eth_sendRawTransaction",
+        &quoted(&hex_0x(&raw_tx)),
+        &quoted(&hex_0x(tx_hash.as_bytes())),
+    );
+}
+
+/// Expect the receipt request of a transaction on the local network
+fn expect_receipt(state: &mut OffchainState, tx_hash: H256, status: Option<&str>) {
+    let result = match status {
+        None => "null".to_string(),
+        Some(status) => format!(
+            r#"{{"blockHash":"{}","blockNumber":"0x10","status":"{}"}}"#,
+            hex_0x(H256::repeat_byte(0xbb).as_bytes()),
+            status
+        ),
+    };
+    expect_rpc(
+        state,
+        LOCAL_RPC,
+        "eth_getTransactionReceipt",
+        &quoted(&hex_0x(tx_hash.as_bytes())),
+        &result,
+    );
+}
+
+/// Expect the source block checks of the mine flow, up to the last mined block of the local
+/// network
+fn expect_mine_checks(state: &mut OffchainState, last_mined_nonce: u64) {
+    expect_chain_id(state, REMOTE_RPC, REMOTE_CHAIN);
+    expect_call(
+        state,
+        REMOTE_CHAIN,
+        b"isLocalBlockReady(uint64)",
+        &[uint(LOCAL_CHAIN)],
+        &[uint(1)],
+    );
+    expect_call(
+        state,
+        REMOTE_CHAIN,
+        b"lastLocalBlock(uint256)",
+        &[uint(LOCAL_CHAIN)],
+        &local_block(LOCAL_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP),
+    );
+    expect_chain_id(state, LOCAL_RPC, LOCAL_CHAIN);
+    expect_call(
+        state,
+        LOCAL_CHAIN,
+        b"lastRemoteMinedBlock(uint64)",
+        &[uint(REMOTE_CHAIN)],
+        &local_block(REMOTE_CHAIN, last_mined_nonce, SOURCE_TIMESTAMP),
+    );
+}
+
+/// Expect the mined block lookup of the local network, returning a block with the given hash
+fn expect_mined_block(state: &mut OffchainState, block_hash: H256) {
+    let mut result = vec![
+        Token::FixedBytes(block_hash.as_bytes().to_vec()),
+        Token::Address(H160::repeat_byte(0x55)),
+        uint(0),
+        uint(0),
+    ];
+    result.extend(local_block(REMOTE_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP));
+    result.push(Token::Array(vec![]));
+    expect_call(
+        state,
+        LOCAL_CHAIN,
+        b"minedBlockByNonce(uint64,uint64)",
+        &[uint(REMOTE_CHAIN), uint(SOURCE_NONCE)],
+        &result,
+    );
+}
+
+#[test]
+fn mine_sends_the_next_source_block() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let svc = service(&signer);
+        let tx_hash = H256::repeat_byte(0xaa);
+        {
+            let mut state = state.write();
+            expect_mine_checks(&mut state, SOURCE_NONCE - 1);
+            expect_mined_block(&mut state, H256::zero());
+            let mut source_block = local_block(LOCAL_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP);
+            source_block.push(Token::Array(vec![tx_token(&source_tx())]));
+            expect_call(
+                &mut state,
+                REMOTE_CHAIN,
+                b"localBlockByNonce(uint64,uint64)",
+                &[uint(LOCAL_CHAIN), uint(SOURCE_NONCE)],
+                &source_block,
+            );
+            let inputs = QuantumPortalClient::<Test>::mine_inputs(
+                REMOTE_CHAIN,
+                SOURCE_NONCE,
+                &[source_tx()],
+            );
+            expect_send(
+                &mut state,
+                &svc.clients[1],
+                MINE_METHOD_SIGNATURE,
+                &inputs,
+                tx_hash,
+            );
+        }
+
+        let outcome = svc.process_pair_with_lock(REMOTE_CHAIN, LOCAL_CHAIN, Role::QP_MINER);
+
+        assert_eq!(outcome, Ok(PairOutcome::Mined(tx_hash)));
+        assert_eq!(
+            svc.stored_pending_transactions(LOCAL_CHAIN),
+            Ok(vec![PendingTransaction::MineTransaction(
+                LOCAL_CHAIN,
+                REMOTE_CHAIN,
+                NOW,
+                tx_hash
+            )])
+        );
+        assert_eq!(svc.stored_pending_transactions(LOCK_CHAIN), Ok(vec![]));
+    });
+}
+
+/// Output of a call reverted with `Error(message)`
+fn revert_data(message: &str) -> Vec<u8> {
+    [
+        &[0x08, 0xc3, 0x79, 0xa0][..],
+        &encoder::encode(&[Token::String(message.as_bytes().to_vec())]),
+    ]
+    .concat()
+}
+
+#[test]
+fn mine_is_skipped_when_the_transaction_would_revert() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let svc = service(&signer);
+        {
+            let mut state = state.write();
+            expect_mine_checks(&mut state, SOURCE_NONCE - 1);
+            expect_mined_block(&mut state, H256::zero());
+            let mut source_block = local_block(LOCAL_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP);
+            source_block.push(Token::Array(vec![]));
+            expect_call(
+                &mut state,
+                REMOTE_CHAIN,
+                b"localBlockByNonce(uint64,uint64)",
+                &[uint(LOCAL_CHAIN), uint(SOURCE_NONCE)],
+                &source_block,
+            );
+            let inputs = QuantumPortalClient::<Test>::mine_inputs(REMOTE_CHAIN, SOURCE_NONCE, &[]);
+            // Mined by another miner since the checks, nothing is signed or sent
+            expect_rpc_error(
+                &mut state,
+                LOCAL_RPC,
+                "eth_call",
+                &preflight_params(&svc.clients[1], MINE_METHOD_SIGNATURE, &inputs),
+                &format!(
+                    r#"{{"code":3,"message":"execution reverted: QPLM: block already mined","data":"{}"}}"#,
+                    hex_0x(&revert_data("QPLM: block already mined"))
+                ),
+            );
+        }
+
+        let outcome = svc.process_pair_with_lock(REMOTE_CHAIN, LOCAL_CHAIN, Role::QP_MINER);
+
+        assert_eq!(
+            outcome,
+            Ok(PairOutcome::Skipped(RevertReason::AlreadyMined))
+        );
+        assert_eq!(svc.stored_pending_transactions(LOCAL_CHAIN), Ok(vec![]));
+        assert_eq!(svc.stored_pending_transactions(LOCK_CHAIN), Ok(vec![]));
+    });
+}
+
+#[test]
+fn mine_is_idle_without_a_ready_source_block() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        expect_chain_id(&mut state.write(), REMOTE_RPC, REMOTE_CHAIN);
+        expect_call(
+            &mut state.write(),
+            REMOTE_CHAIN,
+            b"isLocalBlockReady(uint64)",
+            &[uint(LOCAL_CHAIN)],
+            &[uint(0)],
+        );
+        let (remote, local) = clients(&signer);
+
+        assert_eq!(local.mine(&remote), Ok(None));
+    });
+}
+
+#[test]
+fn mine_reads_the_source_state_at_the_confirmation_depth() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        {
+            let mut state = state.write();
+            expect_chain_id(&mut state, REMOTE_RPC, REMOTE_CHAIN);
+            expect_rpc(
+                &mut state,
+                REMOTE_RPC,
+                "eth_blockNumber",
+                "",
+                &quoted("0x64"),
+            );
+            // A block closed in the last 3 blocks is not visible yet
+            expect_call_at(
+                &mut state,
+                REMOTE_CHAIN,
+                "0x61",
+                b"isLocalBlockReady(uint64)",
+                &[uint(LOCAL_CHAIN)],
+                &[uint(0)],
+            );
+        }
+        let (mut remote, local) = clients(&signer);
+        remote.confirmations = 3;
+
+        assert_eq!(local.mine(&remote), Ok(None));
+    });
+}
+
+#[test]
+fn mine_is_idle_when_caught_up() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        expect_mine_checks(&mut state.write(), SOURCE_NONCE);
+        let (remote, local) = clients(&signer);
+
+        assert_eq!(local.mine(&remote), Ok(None));
+    });
+}
+
+#[test]
+fn mine_fails_when_the_block_is_already_mined() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        {
+            let mut state = state.write();
+            expect_mine_checks(&mut state, SOURCE_NONCE - 1);
+            expect_mined_block(&mut state, H256::repeat_byte(0x77));
+        }
+        let (remote, local) = clients(&signer);
+
+        assert_eq!(
+            local.mine(&remote),
+            Err(ChainRequestError::RemoteBlockAlreadyMined)
+        );
+    });
+}
+
+#[test]
+fn finalize_sends_the_last_mined_block() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let svc = service(&signer);
+        let block_hash = H256::repeat_byte(0x77);
+        let tx_hash = H256::repeat_byte(0xcc);
+        {
+            let mut state = state.write();
+            expect_chain_id(&mut state, LOCAL_RPC, LOCAL_CHAIN);
+            expect_call(
+                &mut state,
+                LOCAL_CHAIN,
+                b"lastRemoteMinedBlock(uint64)",
+                &[uint(REMOTE_CHAIN)],
+                &local_block(REMOTE_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP),
+            );
+            expect_call(
+                &mut state,
+                LOCAL_CHAIN,
+                b"lastFinalizedBlock(uint256)",
+                &[uint(REMOTE_CHAIN)],
+                &local_block(REMOTE_CHAIN, SOURCE_NONCE - 1, SOURCE_TIMESTAMP),
+            );
+            expect_mined_block(&mut state, block_hash);
+            let mined_block = QpRemoteBlock {
+                block_hash,
+                miner: H160::repeat_byte(0x55),
+                stake: U256::zero(),
+                total_value: U256::zero(),
+                block_metadata: QpLocalBlock {
+                    chain_id: REMOTE_CHAIN,
+                    nonce: SOURCE_NONCE,
+                    timestamp: SOURCE_TIMESTAMP,
+                },
+            };
+            let inputs = svc.clients[1]
+                .finalize_inputs(REMOTE_CHAIN, SOURCE_NONCE, &mined_block)
+                .unwrap();
+            expect_send(
+                &mut state,
+                &svc.clients[1],
+                FINALIZE_METHOD_SIGNATURE,
+                &inputs,
+                tx_hash,
+            );
+        }
+
+        let outcome = svc.process_pair_with_lock(REMOTE_CHAIN, LOCAL_CHAIN, Role::QP_FINALIZER);
+
+        assert_eq!(outcome, Ok(PairOutcome::Finalized(tx_hash)));
+        assert_eq!(
+            svc.stored_pending_transactions(LOCAL_CHAIN),
+            Ok(vec![PendingTransaction::FinalizeTransaction(
+                LOCAL_CHAIN,
+                NOW,
+                tx_hash
+            )])
+        );
+        assert_eq!(svc.stored_pending_transactions(LOCK_CHAIN), Ok(vec![]));
+    });
+}
+
+#[test]
+fn finalize_is_skipped_when_the_signature_expired() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let (_, local) = clients(&signer);
+        let block_hash = H256::repeat_byte(0x77);
+        {
+            let mut state = state.write();
+            expect_chain_id(&mut state, LOCAL_RPC, LOCAL_CHAIN);
+            expect_call(
+                &mut state,
+                LOCAL_CHAIN,
+                b"lastRemoteMinedBlock(uint64)",
+                &[uint(REMOTE_CHAIN)],
+                &local_block(REMOTE_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP),
+            );
+            expect_call(
+                &mut state,
+                LOCAL_CHAIN,
+                b"lastFinalizedBlock(uint256)",
+                &[uint(REMOTE_CHAIN)],
+                &local_block(REMOTE_CHAIN, SOURCE_NONCE - 1, SOURCE_TIMESTAMP),
+            );
+            expect_mined_block(&mut state, block_hash);
+            let mined_block = QpRemoteBlock {
+                block_hash,
+                miner: H160::repeat_byte(0x55),
+                stake: U256::zero(),
+                total_value: U256::zero(),
+                block_metadata: QpLocalBlock {
+                    chain_id: REMOTE_CHAIN,
+                    nonce: SOURCE_NONCE,
+                    timestamp: SOURCE_TIMESTAMP,
+                },
+            };
+            let inputs = local
+                .finalize_inputs(REMOTE_CHAIN, SOURCE_NONCE, &mined_block)
+                .unwrap();
+            // Nodes without revert data only give the message
+            expect_rpc_error(
+                &mut state,
+                LOCAL_RPC,
+                "eth_call",
+                &preflight_params(&local, FINALIZE_METHOD_SIGNATURE, &inputs),
+                r#"{"code":-32000,"message":"execution reverted: signature expired"}"#,
+            );
+        }
+
+        assert_eq!(
+            local.finalize(REMOTE_CHAIN),
+            Err(ChainRequestError::TransactionSkipped(RevertReason::Expired))
+        );
+    });
+}
+
+#[test]
+fn revert_reasons_are_classified() {
+    for (message, reason) in [
+        ("block already mined", RevertReason::AlreadyMined),
+        ("QPLM: Already finalized", RevertReason::AlreadyFinalized),
+        ("QPAM: Not authorized", RevertReason::NotAuthorized),
+        ("QPLM: expired", RevertReason::Expired),
+        (
+            "QPLM: bad nonce",
+            RevertReason::Other(b"QPLM: bad nonce".to_vec()),
+        ),
+    ] {
+        assert_eq!(
+            RevertReason::from_revert_data(&revert_data(message)),
+            reason
+        );
+    }
+    assert_eq!(
+        RevertReason::from_message(b"execution reverted: block already mined"),
+        RevertReason::AlreadyMined
+    );
+    // A custom error is kept as it is
+    assert_eq!(
+        RevertReason::from_revert_data(&[0xde, 0xad, 0xbe, 0xef]),
+        RevertReason::Other(vec![0xde, 0xad, 0xbe, 0xef])
+    );
+}
+
+#[test]
+fn finalize_is_idle_when_all_blocks_are_finalized() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        {
+            let mut state = state.write();
+            expect_chain_id(&mut state, LOCAL_RPC, LOCAL_CHAIN);
+            expect_call(
+                &mut state,
+                LOCAL_CHAIN,
+                b"lastRemoteMinedBlock(uint64)",
+                &[uint(REMOTE_CHAIN)],
+                &local_block(REMOTE_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP),
+            );
+            expect_call(
+                &mut state,
+                LOCAL_CHAIN,
+                b"lastFinalizedBlock(uint256)",
+                &[uint(REMOTE_CHAIN)],
+                &local_block(REMOTE_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP),
+            );
+        }
+        let svc = service(&signer);
+
+        let outcome = svc.process_pair_with_lock(REMOTE_CHAIN, LOCAL_CHAIN, Role::QP_FINALIZER);
+
+        assert_eq!(outcome, Ok(PairOutcome::Idle));
+        assert_eq!(svc.stored_pending_transactions(LOCAL_CHAIN), Ok(vec![]));
+    });
+}
+
+#[test]
+fn process_pair_skips_while_the_lock_is_held() {
+    let (mut ext, _state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let svc = service(&signer);
+        svc.lock().unwrap();
+
+        // No request is scripted, any rpc call fails the test
+        let outcome = svc.process_pair_with_lock(REMOTE_CHAIN, LOCAL_CHAIN, Role::QP_MINER);
+
+        assert_eq!(outcome, Ok(PairOutcome::Locked));
+        assert_eq!(
+            svc.stored_pending_transactions(LOCK_CHAIN).unwrap().len(),
+            1
+        );
+    });
+}
+
+#[test]
+fn an_expired_lock_is_taken_over() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let svc = service(&signer);
+        // Left by a run that panicked
+        svc.save_tx(PendingTransaction::FinalizeTransaction(
+            LOCK_CHAIN,
+            NOW - LOCK_EXPIRY - 1,
+            H256::zero(),
+        ))
+        .unwrap();
+        let tx_hash = H256::repeat_byte(0xaa);
+        let pending =
+            PendingTransaction::MineTransaction(LOCAL_CHAIN, REMOTE_CHAIN, NOW - 60_000, tx_hash);
+        svc.save_tx(pending).unwrap();
+        expect_receipt(&mut state.write(), tx_hash, None);
+
+        let outcome = svc.process_pair_with_lock(REMOTE_CHAIN, LOCAL_CHAIN, Role::QP_MINER);
+
+        assert_eq!(outcome, Ok(PairOutcome::Pending(1)));
+        assert_eq!(svc.stored_pending_transactions(LOCK_CHAIN), Ok(vec![]));
+    });
+}
+
+#[test]
+fn a_pair_of_a_missing_network_fails_alone() {
+    let (mut ext, _state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let svc = service(&signer);
+
+        let outcome = svc.process_pair_with_lock(1234, LOCAL_CHAIN, Role::QP_MINER);
+
+        assert_eq!(outcome, Err(ChainRequestError::NetworkNotConfigured(1234)));
+        assert_eq!(svc.stored_pending_transactions(LOCK_CHAIN), Ok(vec![]));
+    });
+}
+
+#[test]
+fn process_pair_waits_for_a_pending_transaction() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let svc = service(&signer);
+        let tx_hash = H256::repeat_byte(0xaa);
+        let pending =
+            PendingTransaction::MineTransaction(LOCAL_CHAIN, REMOTE_CHAIN, NOW - 60_000, tx_hash);
+        svc.save_tx(pending.clone()).unwrap();
+        expect_receipt(&mut state.write(), tx_hash, None);
+
+        let outcome = svc.process_pair_with_lock(REMOTE_CHAIN, LOCAL_CHAIN, Role::QP_MINER);
+
+        assert_eq!(outcome, Ok(PairOutcome::Pending(1)));
+        assert_eq!(
+            svc.stored_pending_transactions(LOCAL_CHAIN),
+            Ok(vec![pending])
+        );
+        assert_eq!(svc.stored_pending_transactions(LOCK_CHAIN), Ok(vec![]));
+    });
+}
+
+#[test]
+fn is_tx_pending_removes_confirmed_and_failed_transactions() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let svc = service(&signer);
+        for (status, tx_hash) in [
+            ("0x1", H256::repeat_byte(0x01)),
+            ("0x0", H256::repeat_byte(0x02)),
+        ] {
+            let tx = PendingTransaction::FinalizeTransaction(LOCAL_CHAIN, NOW - 60_000, tx_hash);
+            svc.save_tx(tx.clone()).unwrap();
+            expect_receipt(&mut state.write(), tx_hash, Some(status));
+
+            assert_eq!(svc.is_tx_pending(&tx), Ok(false));
+            assert_eq!(svc.stored_pending_transactions(LOCAL_CHAIN), Ok(vec![]));
+        }
+    });
+}
+
+#[test]
+fn a_receipt_short_of_the_confirmation_depth_stays_pending() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let (_, mut local) = clients(&signer);
+        local.confirmations = 6;
+        let tx_hash = H256::repeat_byte(0x04);
+
+        // The receipt is in block 0x10
+        expect_receipt(&mut state.write(), tx_hash, Some("0x1"));
+        expect_rpc(
+            &mut state.write(),
+            LOCAL_RPC,
+            "eth_blockNumber",
+            "",
+            &quoted("0x15"),
+        );
+        assert!(matches!(
+            local.transaction_status(&tx_hash),
+            Ok(TransactionStatus::Pending)
+        ));
+
+        expect_receipt(&mut state.write(), tx_hash, Some("0x1"));
+        expect_rpc(
+            &mut state.write(),
+            LOCAL_RPC,
+            "eth_blockNumber",
+            "",
+            &quoted("0x16"),
+        );
+        assert!(matches!(
+            local.transaction_status(&tx_hash),
+            Ok(TransactionStatus::Confirmed)
+        ));
+    });
+}
+
+#[test]
+fn is_tx_pending_keeps_unknown_transactions_until_the_timeout() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let svc = service(&signer);
+        let tx_hash = H256::repeat_byte(0x03);
+
+        let recent = PendingTransaction::FinalizeTransaction(LOCAL_CHAIN, NOW - TIMEOUT, tx_hash);
+        svc.save_tx(recent.clone()).unwrap();
+        expect_receipt(&mut state.write(), tx_hash, None);
+        assert_eq!(svc.is_tx_pending(&recent), Ok(true));
+        assert_eq!(
+            svc.stored_pending_transactions(LOCAL_CHAIN),
+            Ok(vec![recent])
+        );
+
+        let timed_out =
+            PendingTransaction::FinalizeTransaction(LOCAL_CHAIN, NOW - TIMEOUT - 1, tx_hash);
+        svc.save_tx(timed_out.clone()).unwrap();
+        expect_receipt(&mut state.write(), tx_hash, None);
+        assert_eq!(svc.is_tx_pending(&timed_out), Ok(false));
+        assert_eq!(svc.stored_pending_transactions(LOCAL_CHAIN), Ok(vec![]));
+    });
+}
+
+#[test]
+fn finalize_on_a_native_network_submits_a_signed_transaction() {
+    let (mut ext, _state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let client = native_client(&signer);
+        let block_hash = H256::repeat_byte(0x77);
+        expect_native_call(
+            encoder::encode_function_u8(b"lastRemoteMinedBlock(uint64)", &[uint(REMOTE_CHAIN)]),
+            encoder::encode(&local_block(REMOTE_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP)),
+        );
+        expect_native_call(
+            encoder::encode_function_u8(b"lastFinalizedBlock(uint256)", &[uint(REMOTE_CHAIN)]),
+            encoder::encode(&local_block(
+                REMOTE_CHAIN,
+                SOURCE_NONCE - 1,
+                SOURCE_TIMESTAMP,
+            )),
+        );
+        let mut mined = vec![
+            Token::FixedBytes(block_hash.as_bytes().to_vec()),
+            Token::Address(H160::repeat_byte(0x55)),
+            uint(0),
+            uint(0),
+        ];
+        mined.extend(local_block(REMOTE_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP));
+        mined.push(Token::Array(vec![]));
+        expect_native_call(
+            encoder::encode_function_u8(
+                b"minedBlockByNonce(uint64,uint64)",
+                &[uint(REMOTE_CHAIN), uint(SOURCE_NONCE)],
+            ),
+            encoder::encode(&mined),
+        );
+        let mined_block = QpRemoteBlock {
+            block_hash,
+            miner: H160::repeat_byte(0x55),
+            stake: U256::zero(),
+            total_value: U256::zero(),
+            block_metadata: QpLocalBlock {
+                chain_id: REMOTE_CHAIN,
+                nonce: SOURCE_NONCE,
+                timestamp: SOURCE_TIMESTAMP,
+            },
+        };
+        let inputs = client
+            .finalize_inputs(REMOTE_CHAIN, SOURCE_NONCE, &mined_block)
+            .unwrap();
+        // The pre-flight call
+        expect_native_call(
+            encoder::encode_function_u8(FINALIZE_METHOD_SIGNATURE, &inputs),
+            vec![],
+        );
+
+        let tx_hash = client.finalize(REMOTE_CHAIN).unwrap().unwrap();
+
+        let submitted = native_submitted();
+        assert_eq!(submitted.len(), 1);
+        let tx = &submitted[0];
+        assert_eq!(
+            tx.input,
+            encoder::encode_function_u8(FINALIZE_METHOD_SIGNATURE, &inputs)
+        );
+        assert_eq!(tx.nonce, U256::from(NATIVE_NONCE));
+        assert_eq!(tx.gas_limit, U256::from(NATIVE_GAS * 120 / 100));
+        assert_eq!(tx.gas_price, U256::from(NATIVE_GAS_PRICE * 125 / 100));
+        assert_eq!(tx.signature.chain_id(), Some(NATIVE_CHAIN));
+        assert!(matches!(
+            client.transaction_status(&tx_hash),
+            Ok(TransactionStatus::Confirmed)
+        ));
+    });
+}
+
+#[test]
+fn a_native_transaction_outcome_is_only_known_in_its_block() {
+    let (mut ext, _state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let client = native_client(&signer);
+        let send = || {
+            NativeContractClient::<TestNativeEvm>::send(
+                &client.contract,
+                FINALIZE_METHOD_SIGNATURE,
+                &[],
+                &client.signer,
+            )
+            .unwrap()
+        };
+        let included = send();
+        assert!(matches!(
+            client.transaction_status(&included),
+            Ok(TransactionStatus::Confirmed)
+        ));
+
+        // Seen from a later block the outcome is gone, the nonce may have gone to another
+        // transaction
+        let earlier = send();
+        next_native_block(true);
+        assert!(matches!(
+            client.transaction_status(&earlier),
+            Ok(TransactionStatus::Failed)
+        ));
+
+        let dropped = send();
+        next_native_block(false);
+        assert!(matches!(
+            client.transaction_status(&dropped),
+            Ok(TransactionStatus::NotFound)
+        ));
+        assert!(matches!(
+            client.transaction_status(&H256::repeat_byte(1)),
+            Ok(TransactionStatus::NotFound)
+        ));
+    });
+}
+
+#[test]
+fn native_transactions_in_the_pool_do_not_share_a_nonce() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let client = native_client(&signer);
+        let send = || {
+            NativeContractClient::<TestNativeEvm>::send(
+                &client.contract,
+                FINALIZE_METHOD_SIGNATURE,
+                &[],
+                &client.signer,
+            )
+            .unwrap()
+        };
+        send();
+        send();
+        next_native_block(false);
+        send();
+        let nonces: Vec<_> = native_submitted().iter().map(|tx| tx.nonce).collect();
+        assert_eq!(
+            nonces,
+            (NATIVE_NONCE..NATIVE_NONCE + 3)
+                .map(U256::from)
+                .collect::<Vec<_>>()
+        );
+
+        // Once the service gave up on them, dropped transactions leave no gap
+        next_native_block(false);
+        state.write().timestamp = Timestamp::from_unix_millis(NOW + TIMEOUT + 1);
+        send();
+        assert_eq!(native_submitted()[3].nonce, U256::from(NATIVE_NONCE));
+    });
+}
+
+#[test]
+fn a_found_signer_key_is_remembered() {
+    let (mut ext, _state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let configured = ChainUtils::bytes_to_hex(&signer.0);
+        assert!(QuantumPortal::signer_key_available(&configured));
+        let checked = StorageValueRef::persistent(b"quantum-portal::signer-key-check")
+            .get::<(Vec<u8>, u64)>()
+            .unwrap();
+        assert_eq!(checked, Some((configured, NOW)));
+
+        let missing = ChainUtils::bytes_to_hex(&[2; 33]);
+        assert!(!QuantumPortal::signer_key_available(&missing));
+        assert!(!QuantumPortal::signer_key_available(&missing));
+    });
+}
+
+#[test]
+fn a_native_network_must_use_the_evm_chain_id() {
+    let (mut ext, _state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let res = QuantumPortal::configure_network(
+            1,
+            QpNetworkItem {
+                url: vec![],
+                ledger_manager: LOCAL_LEDGER_MANAGER.as_bytes().to_vec(),
+                id: LOCAL_CHAIN,
+                network_type: QpNetworkType::Native,
+                auth: Default::default(),
+                confirmations: 0,
+            },
+            ChainUtils::bytes_to_hex(&signer.0),
+            eip_712_config(),
+        );
+        assert!(matches!(
+            res,
+            Err(ChainRequestError::ChainIdMismatch {
+                expected: LOCAL_CHAIN,
+                actual: NATIVE_CHAIN,
+            })
+        ));
+    });
+}
+
+#[test]
+fn unknown_network_types_are_rejected() {
+    assert_eq!(QpNetworkType::try_from(&b""[..]), Ok(QpNetworkType::Http));
+    assert_eq!(
+        QpNetworkType::try_from(&b"http"[..]),
+        Ok(QpNetworkType::Http)
+    );
+    assert_eq!(
+        QpNetworkType::try_from(&b"native"[..]),
+        Ok(QpNetworkType::Native)
+    );
+    assert!(QpNetworkType::try_from(&b"Native"[..]).is_err());
+    assert!(QpNetworkType::try_from(&b"ws"[..]).is_err());
+}
+
+fn schedule(run_every_blocks: u64, pair_interval_blocks: u64) -> QpSchedule {
+    QpSchedule {
+        run_every_blocks,
+        pair_interval_blocks,
+        run_budget_ms: 0,
+    }
+}
+
+#[test]
+fn scheduler_runs_every_n_blocks_across_restarts() {
+    let (mut ext, _state, _signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let mut scheduler = QpScheduler::load(schedule(3, 1));
+        assert!(scheduler.start_run(10));
+        scheduler.save();
+
+        let mut scheduler = QpScheduler::load(schedule(3, 1));
+        assert!(!scheduler.start_run(10));
+        assert!(!scheduler.start_run(12));
+        assert!(scheduler.start_run(13));
+        // A reverted chain runs again
+        assert!(scheduler.start_run(5));
+    });
+}
+
+#[test]
+fn scheduler_skips_pairs_until_their_interval_passed() {
+    let pairs = [(REMOTE_CHAIN, LOCAL_CHAIN), (LOCAL_CHAIN, REMOTE_CHAIN)];
+    let mut scheduler = QpScheduler::new(schedule(1, 5), Default::default());
+    assert!(scheduler.pair_due(REMOTE_CHAIN, LOCAL_CHAIN, 10));
+
+    scheduler.record_pair(&pairs, 0, 10);
+
+    assert!(!scheduler.pair_due(REMOTE_CHAIN, LOCAL_CHAIN, 14));
+    assert!(scheduler.pair_due(REMOTE_CHAIN, LOCAL_CHAIN, 15));
+    assert!(scheduler.pair_due(LOCAL_CHAIN, REMOTE_CHAIN, 14));
+}
+
+#[test]
+fn scheduler_starts_with_the_pairs_left_by_the_previous_run() {
+    let pairs = [(1, 2), (2, 1), (1, 3)];
+    let mut scheduler = QpScheduler::new(schedule(1, 1), Default::default());
+    assert_eq!(scheduler.pair_order(pairs.len()), vec![0, 1, 2]);
+
+    // The run ran out of time after the first pair
+    scheduler.record_pair(&pairs, 0, 10);
+    assert_eq!(scheduler.pair_order(pairs.len()), vec![1, 2, 0]);
+
+    scheduler.record_pair(&pairs, 1, 11);
+    scheduler.record_pair(&pairs, 2, 11);
+    assert_eq!(scheduler.pair_order(pairs.len()), vec![0, 1, 2]);
+    // Removed pairs are forgotten
+    scheduler.record_pair(&pairs[..2], 0, 12);
+    assert_eq!(scheduler.state().pair_runs, vec![(1, 2, 12), (2, 1, 11)]);
+}
+
+#[test]
+fn retry_delay_doubles_with_a_bounded_jitter() {
+    assert_eq!(retry_delay(1, &[0; 32]), 500);
+    assert_eq!(retry_delay(2, &[0; 32]), 1000);
+    assert_eq!(retry_delay(3, &[0; 32]), 2000);
+    for seed in [[0xff; 32], [0x5a; 32], [0x01; 32]] {
+        let delay = retry_delay(2, &seed);
+        assert!((1000..=1500).contains(&delay), "delay {}", delay);
+    }
+}
+
+#[test]
+fn only_transient_http_failures_are_retried() {
+    for retryable in [
+        HttpAttemptError::Timeout,
+        HttpAttemptError::Io,
+        HttpAttemptError::Status(429),
+        HttpAttemptError::Status(502),
+        HttpAttemptError::Status(503),
+    ] {
+        assert!(retryable.is_retryable(), "{:?}", retryable);
+    }
+    for fatal in [
+        HttpAttemptError::Unknown,
+        HttpAttemptError::Status(400),
+        HttpAttemptError::Status(401),
+        HttpAttemptError::Status(404),
+    ] {
+        assert!(!fatal.is_retryable(), "{:?}", fatal);
+    }
+}
+
+#[test]
+fn host_http_errors_map_to_attempt_errors() {
+    assert_eq!(
+        HttpAttemptError::from(HttpError::DeadlineReached),
+        HttpAttemptError::Timeout
+    );
+    assert_eq!(
+        HttpAttemptError::from(&HttpError::IoError),
+        HttpAttemptError::Io
+    );
+    assert_eq!(
+        HttpAttemptError::from(HttpError::Invalid),
+        HttpAttemptError::Unknown
+    );
+}
+
+#[test]
+fn requests_carry_the_network_credentials() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let client = QuantumPortal::configure_network(
+            1,
+            QpNetworkItem {
+                url: REMOTE_RPC.as_bytes().to_vec(),
+                ledger_manager: REMOTE_LEDGER_MANAGER.as_bytes().to_vec(),
+                id: REMOTE_CHAIN,
+                network_type: QpNetworkType::Http,
+                auth: auth(),
+                confirmations: 0,
+            },
+            ChainUtils::bytes_to_hex(&signer.0),
+            eip_712_config(),
+        )
+        .unwrap();
+        let tx_hash = H256::repeat_byte(0xcc);
+        state.write().expect_request(PendingRequest {
+            method: "POST".into(),
+            uri: REMOTE_RPC.into(),
+            headers: vec![
+                ("Content-Type".into(), "application/json".into()),
+                ("x-api-key".into(), "secret-key".into()),
+                ("Authorization".into(), "Basic dXNlcjpwYXNz".into()),
+            ],
+            body: format!(
+                r#"{{"id":1,"method":"eth_getTransactionReceipt","jsonrpc":"2.0","params":["{}"]}}"#,
+                hex_0x(tx_hash.as_bytes())
+            )
+            .into_bytes(),
+            response: Some(br#"{"jsonrpc":"2.0","id":1,"result":null}"#.to_vec()),
+            sent: true,
+            ..Default::default()
+        });
+
+        assert!(matches!(
+            client.transaction_status(&tx_hash),
+            Ok(TransactionStatus::NotFound)
+        ));
+    });
+}
+
+#[test]
+fn requests_are_not_sent_past_the_run_deadline() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let mut client = client(LOCAL_RPC, LOCAL_LEDGER_MANAGER, LOCAL_CHAIN, &signer);
+        let tx_hash = H256::repeat_byte(0xcc);
+        client.contract.deadline = Some(NOW + 1_000);
+        expect_receipt(&mut state.write(), tx_hash, None);
+        assert!(matches!(
+            client.transaction_status(&tx_hash),
+            Ok(TransactionStatus::NotFound)
+        ));
+
+        // No request is scripted, sending one would fail the test
+        client.contract.deadline = Some(NOW);
+        assert!(matches!(
+            client.transaction_status(&tx_hash),
+            Err(ChainRequestError::HttpRequestFailed(attempts))
+                if attempts == vec![HttpAttemptError::Timeout]
+        ));
+    });
+}
+
+fn auth() -> QpRpcAuth {
+    QpRpcAuth {
+        headers: vec![(b"x-api-key".to_vec(), b"secret-key".to_vec())],
+        basic_auth: Some((b"user".to_vec(), b"pass".to_vec())),
+    }
+}
+
+#[test]
+fn credentials_are_redacted_from_debug_output() {
+    let item = QpNetworkItem {
+        url: b"https://user:pw@mainnet.example.io/v3/secret-path?key=secret-query".to_vec(),
+        ledger_manager: REMOTE_LEDGER_MANAGER.as_bytes().to_vec(),
+        id: REMOTE_CHAIN,
+        network_type: QpNetworkType::Http,
+        auth: auth(),
+        confirmations: 0,
+    };
+
+    let debug = format!("{:?}", item);
+
+    assert!(debug.contains("mainnet.example.io"), "{}", debug);
+    assert!(debug.contains("x-api-key"), "{}", debug);
+    for secret in ["secret", "user", "pass", "pw"] {
+        assert!(!debug.contains(secret), "{} in {}", secret, debug);
+    }
+}
+
+#[test]
+fn base64_encodes_with_padding() {
+    assert_eq!(ChainUtils::base64_encode(b""), b"");
+    assert_eq!(ChainUtils::base64_encode(b"f"), b"Zg==");
+    assert_eq!(ChainUtils::base64_encode(b"fo"), b"Zm8=");
+    assert_eq!(ChainUtils::base64_encode(b"foo"), b"Zm9v");
+    assert_eq!(ChainUtils::base64_encode(b"user:pass"), b"dXNlcjpwYXNz");
+}
+
+#[test]
+fn sending_is_refused_when_the_endpoint_serves_another_chain() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let (_, local) = clients(&signer);
+        expect_chain_id(&mut state.write(), LOCAL_RPC, REMOTE_CHAIN);
+
+        let res = local.contract.send(
+            MINE_METHOD_SIGNATURE,
+            &[],
+            None,
+            None,
+            U256::zero(),
+            None,
+            local.signer.from,
+            &local.signer,
+        );
+
+        assert_eq!(
+            res,
+            Err(ChainRequestError::ChainIdMismatch {
+                expected: LOCAL_CHAIN,
+                actual: REMOTE_CHAIN
+            })
+        );
+        // Not remembered, the endpoint is checked again next time
+        expect_chain_id(&mut state.write(), LOCAL_RPC, LOCAL_CHAIN);
+        assert_eq!(local.contract.verify_chain_id(), Ok(()));
+    });
+}
+
+#[test]
+fn reading_is_refused_when_the_endpoint_serves_another_chain() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let (remote, local) = clients(&signer);
+        expect_chain_id(&mut state.write(), REMOTE_RPC, LOCAL_CHAIN);
+
+        assert_eq!(
+            local.mine(&remote),
+            Err(ChainRequestError::ChainIdMismatch {
+                expected: REMOTE_CHAIN,
+                actual: LOCAL_CHAIN
+            })
+        );
+    });
+}
+
+#[test]
+fn a_verified_chain_id_is_not_queried_again() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let (_, local) = clients(&signer);
+        expect_chain_id(&mut state.write(), LOCAL_RPC, LOCAL_CHAIN);
+
+        assert_eq!(local.contract.verify_chain_id(), Ok(()));
+        // A request would panic, none is expected
+        assert_eq!(local.contract.verify_chain_id(), Ok(()));
+        let (_, local) = clients(&signer);
+        assert_eq!(local.contract.verify_chain_id(), Ok(()));
+    });
+}
+
+#[test]
+fn call_options_set_the_block_sender_and_state_overrides() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    ext.execute_with(|| {
+        let (_, local) = clients(&signer);
+        let block_hash = H256::repeat_byte(0xbb);
+        let from = local.signer.from;
+        let slot = H256::from_low_u64_be(1);
+        let inputs = [uint(REMOTE_CHAIN)];
+        let params = format!(
+            concat!(
+                r#"{{"data":"{}","to":"{}","from":"{}","gas":"0x7a120"}},"#,
+                r#"{{"blockHash":"{}"}},"#,
+                r#"{{"{}":{{"balance":"0xde0b6b3a7640000","stateDiff":{{"{}":"{}"}}}}}}"#
+            ),
+            hex_0x(&encoder::encode_function_u8(
+                b"lastRemoteMinedBlock(uint64)",
+                &inputs
+            )),
+            hex_0x(local.contract.contract_address.as_bytes()),
+            hex_0x(from.as_bytes()),
+            hex_0x(block_hash.as_bytes()),
+            hex_0x(from.as_bytes()),
+            hex_0x(slot.as_bytes()),
+            hex_0x(H256::from_low_u64_be(0xff).as_bytes()),
+        );
+        expect_chain_id(&mut state.write(), LOCAL_RPC, LOCAL_CHAIN);
+        expect_rpc(
+            &mut state.write(),
+            LOCAL_RPC,
+            "eth_call",
+            &params,
+            &quoted("0x01"),
+        );
+        let options = CallOptions::new()
+            .at(BlockId::Hash(block_hash))
+            .from(from)
+            .gas(U256::from(500_000u64))
+            .override_balance(from, U256::from(1_000_000_000_000_000_000u64))
+            .override_storage(from, slot, H256::from_low_u64_be(0xfe))
+            .override_storage(from, slot, H256::from_low_u64_be(0xff));
+
+        let res = local
+            .contract
+            .call::<CallResponse>(b"lastRemoteMinedBlock(uint64)", &inputs, &options)
+            .unwrap();
+
+        assert_eq!(res.result, b"0x01".to_vec());
+    });
+}
+
+fn account(id: u8) -> AccountId {
+    sr25519::Public::from_raw([id; 32])
+}
+
+/// An account holding `balance`
+fn funded(id: u8, balance: u64) -> AccountId {
+    let who = account(id);
+    Balances::make_free_balance_be(&who, balance);
+    who
+}
+
+fn signer_address(id: u8) -> H160 {
+    H160::repeat_byte(id)
+}
+
+fn register_miner(id: u8) -> AccountId {
+    let who = funded(id, 1_000);
+    assert_ok!(QuantumPortal::register_miner(
+        RuntimeOrigin::signed(who.clone()),
+        signer_address(id)
+    ));
+    who
+}
+
+fn register_finalizer(id: u8) -> AccountId {
+    let who = funded(id, 1_000);
+    assert_ok!(QuantumPortal::register_finalizer(
+        RuntimeOrigin::signed(who.clone()),
+        signer_address(id)
+    ));
+    who
+}
+
+#[test]
+fn registering_bonds_stake_and_links_the_signer() {
+    new_test_ext().execute_with(|| {
+        let miner = register_miner(1);
+        let finalizer = register_finalizer(2);
+
+        assert_eq!(Balances::reserved_balance(&miner), 100);
+        assert_eq!(Balances::reserved_balance(&finalizer), 50);
+        assert!(QuantumPortal::is_miner(&miner) && !QuantumPortal::is_finalizer(&miner));
+        assert!(QuantumPortal::is_finalizer(&finalizer) && !QuantumPortal::is_miner(&finalizer));
+        assert_eq!(Signers::<Test>::get(signer_address(1)), Some(miner));
+        System::assert_last_event(
+            Event::WorkerRegistered {
+                who: finalizer,
+                role: QpWorkerRole::Finalizer,
+                signer: signer_address(2),
+                bond: 50,
+            }
+            .into(),
+        );
+    });
+}
+
+#[test]
+fn registering_twice_or_with_a_used_signer_fails() {
+    new_test_ext().execute_with(|| {
+        let miner = register_miner(1);
+        assert_noop!(
+            QuantumPortal::register_finalizer(RuntimeOrigin::signed(miner), signer_address(9)),
+            Error::<Test>::AlreadyRegistered
+        );
+
+        let other = funded(2, 1_000);
+        assert_noop!(
+            QuantumPortal::register_miner(RuntimeOrigin::signed(other), signer_address(1)),
+            Error::<Test>::SignerInUse
+        );
+
+        let poor = funded(3, 99);
+        assert_noop!(
+            QuantumPortal::register_miner(RuntimeOrigin::signed(poor), signer_address(3)),
+            Error::<Test>::InsufficientBalance
+        );
+    });
+}
+
+#[test]
+fn unbonding_deactivates_the_worker() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            QuantumPortal::unbond(RuntimeOrigin::signed(account(1))),
+            Error::<Test>::NotRegistered
+        );
+
+        let miner = register_miner(1);
+        assert_ok!(QuantumPortal::unbond(RuntimeOrigin::signed(miner.clone())));
+
+        assert!(!QuantumPortal::is_miner(&miner));
+        assert_eq!(Workers::<Test>::get(&miner).unwrap().unlock_at, Some(11));
+        // The bond stays reserved until it is withdrawn
+        assert_eq!(Balances::reserved_balance(&miner), 100);
+        System::assert_last_event(
+            Event::WorkerUnbonded {
+                who: miner.clone(),
+                unlock_at: 11,
+            }
+            .into(),
+        );
+        assert_noop!(
+            QuantumPortal::unbond(RuntimeOrigin::signed(miner)),
+            Error::<Test>::AlreadyUnbonding
+        );
+    });
+}
+
+#[test]
+fn the_bond_is_withdrawn_after_the_unbonding_delay() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            QuantumPortal::withdraw_unbonded(RuntimeOrigin::signed(account(1))),
+            Error::<Test>::NotRegistered
+        );
+        let miner = register_miner(1);
+        assert_noop!(
+            QuantumPortal::withdraw_unbonded(RuntimeOrigin::signed(miner.clone())),
+            Error::<Test>::NotUnbonding
+        );
+        assert_ok!(QuantumPortal::unbond(RuntimeOrigin::signed(miner.clone())));
+
+        System::set_block_number(10);
+        assert_noop!(
+            QuantumPortal::withdraw_unbonded(RuntimeOrigin::signed(miner.clone())),
+            Error::<Test>::BondLocked
+        );
+
+        System::set_block_number(11);
+        assert_ok!(QuantumPortal::withdraw_unbonded(RuntimeOrigin::signed(
+            miner.clone()
+        )));
+        assert_eq!(Balances::reserved_balance(&miner), 0);
+        assert_eq!(Balances::free_balance(&miner), 1_000);
+        assert!(Workers::<Test>::get(&miner).is_none());
+        assert!(Signers::<Test>::get(signer_address(1)).is_none());
+        // The signer can be registered again
+        assert_ok!(QuantumPortal::register_miner(
+            RuntimeOrigin::signed(miner),
+            signer_address(1)
+        ));
+    });
+}
+
+#[test]
+fn slashing_below_the_bond_deactivates_the_worker() {
+    new_test_ext().execute_with(|| {
+        let miner = register_miner(1);
+        assert_noop!(
+            QuantumPortal::slash(RuntimeOrigin::signed(miner.clone()), miner.clone(), 10),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            QuantumPortal::slash(RuntimeOrigin::root(), account(2), 10),
+            Error::<Test>::NotRegistered
+        );
+
+        assert_ok!(QuantumPortal::slash(
+            RuntimeOrigin::root(),
+            miner.clone(),
+            10
+        ));
+        assert_eq!(Balances::reserved_balance(&miner), 90);
+        assert_eq!(Balances::free_balance(&miner), 900);
+        assert_eq!(Workers::<Test>::get(&miner).unwrap().bond, 90);
+        assert!(!Miners::<Test>::contains_key(&miner));
+        System::assert_last_event(
+            Event::WorkerSlashed {
+                who: miner.clone(),
+                amount: 10,
+            }
+            .into(),
+        );
+
+        // No more than the bond is slashed
+        assert_ok!(QuantumPortal::slash(
+            RuntimeOrigin::root(),
+            miner.clone(),
+            1_000
+        ));
+        assert_eq!(Workers::<Test>::get(&miner).unwrap().bond, 0);
+        assert_eq!(Balances::total_balance(&miner), 900);
+    });
+}
+
+fn key_address(public: &ecdsa::Public) -> H160 {
+    H160::from_slice(&ChainUtils::eth_address_from_public_key(public.as_ref()))
+}
+
+/// A finalizer signing with a new `ofsg` key of the test keystore
+fn register_finalizer_with_key(id: u8) -> ecdsa::Public {
+    let public = sp_io::crypto::ecdsa_generate(OFFCHAIN_SIGNER_KEY_TYPE, None);
+    assert_ok!(QuantumPortal::register_finalizer(
+        RuntimeOrigin::signed(funded(id, 1_000)),
+        key_address(&public)
+    ));
+    public
+}
+
+/// A miner signing with a new `ofsg` key of the test keystore
+fn register_miner_with_key(id: u8) -> ecdsa::Public {
+    let public = sp_io::crypto::ecdsa_generate(OFFCHAIN_SIGNER_KEY_TYPE, None);
+    assert_ok!(QuantumPortal::register_miner(
+        RuntimeOrigin::signed(funded(id, 1_000)),
+        key_address(&public)
+    ));
+    public
+}
+
+/// A proof that the block with nonce 1 mined by the miner `miner_id` is fraudulent
+fn fraud_proof(miner_id: u8) -> QpFraudProof {
+    QpFraudProof {
+        remote_chain: REMOTE_CHAIN,
+        local_chain: LOCAL_CHAIN,
+        nonce: 1,
+        miner: signer_address(miner_id),
+        mined_txs_hash: H256::repeat_byte(1),
+        source_txs_hash: H256::repeat_byte(2),
+    }
+}
+
+fn attest(finalizer: &ecdsa::Public, proof: &QpFraudProof) -> [u8; 65] {
+    let hash = proof.attestation_hash(&QuantumPortal::genesis_hash());
+    sp_io::crypto::ecdsa_sign_prehashed(OFFCHAIN_SIGNER_KEY_TYPE, finalizer, hash.as_fixed_bytes())
+        .unwrap()
+        .0
+}
+
+/// A registered miner that reported the block of `fraud_proof`, and three finalizers
+fn fraud_setup() -> (AccountId, Vec<ecdsa::Public>) {
+    let miner = register_miner(1);
+    UnconfirmedMinedBlocks::<Test>::insert((REMOTE_CHAIN, LOCAL_CHAIN), &miner, 1);
+    let finalizers = (10..13).map(register_finalizer_with_key).collect();
+    (miner, finalizers)
+}
+
+#[test]
+fn a_fraud_proof_attested_by_a_quorum_slashes_the_miner() {
+    new_test_ext().execute_with(|| {
+        let (miner, finalizers) = fraud_setup();
+        let proof = fraud_proof(1);
+        let reporter = account(20);
+        // 66% of 3 finalizers, rounded up
+        let attestations = vec![
+            attest(&finalizers[0], &proof),
+            attest(&finalizers[2], &proof),
+        ];
+
+        assert_ok!(QuantumPortal::submit_fraud_proof(
+            RuntimeOrigin::signed(reporter.clone()),
+            proof.clone(),
+            attestations.clone()
+        ));
+
+        assert_eq!(Workers::<Test>::get(&miner).unwrap().bond, 50);
+        assert_eq!(Balances::reserved_balance(&miner), 50);
+        assert!(!QuantumPortal::is_miner(&miner));
+        assert!(UnconfirmedMinedBlocks::<Test>::get((REMOTE_CHAIN, LOCAL_CHAIN), &miner).is_none());
+        assert_eq!(
+            ProvenFrauds::<Test>::get((REMOTE_CHAIN, LOCAL_CHAIN, 1)),
+            Some(reporter.clone())
+        );
+        System::assert_last_event(
+            Event::FraudProven {
+                remote_chain: REMOTE_CHAIN,
+                local_chain: LOCAL_CHAIN,
+                nonce: 1,
+                miner,
+                reporter: reporter.clone(),
+                slashed: 50,
+            }
+            .into(),
+        );
+        assert_noop!(
+            QuantumPortal::submit_fraud_proof(RuntimeOrigin::signed(reporter), proof, attestations),
+            Error::<Test>::FraudAlreadyProven
+        );
+    });
+}
+
+#[test]
+fn a_fraud_proof_needs_a_quorum_of_distinct_finalizers() {
+    new_test_ext().execute_with(|| {
+        let (_, finalizers) = fraud_setup();
+        let proof = fraud_proof(1);
+        let submit = |attestations: Vec<[u8; 65]>| {
+            QuantumPortal::submit_fraud_proof(
+                RuntimeOrigin::signed(account(20)),
+                proof.clone(),
+                attestations,
+            )
+        };
+
+        // Below the quorum
+        assert_noop!(
+            submit(vec![attest(&finalizers[0], &proof)]),
+            Error::<Test>::NotEnoughAttestations
+        );
+        // The same finalizer twice
+        assert_noop!(
+            submit(vec![
+                attest(&finalizers[0], &proof),
+                attest(&finalizers[0], &proof)
+            ]),
+            Error::<Test>::NotEnoughAttestations
+        );
+        // Signed by a key that is not an active finalizer
+        let outsider = sp_io::crypto::ecdsa_generate(OFFCHAIN_SIGNER_KEY_TYPE, None);
+        assert_noop!(
+            submit(vec![
+                attest(&finalizers[0], &proof),
+                attest(&outsider, &proof)
+            ]),
+            Error::<Test>::NotEnoughAttestations
+        );
+        // Attestations of another proof
+        let mut other = proof.clone();
+        other.nonce = 2;
+        assert_noop!(
+            submit(vec![
+                attest(&finalizers[0], &other),
+                attest(&finalizers[1], &other)
+            ]),
+            Error::<Test>::NotEnoughAttestations
+        );
+        // Attestations made for another chain
+        let hash = proof.attestation_hash(&H256::repeat_byte(0xee));
+        let foreign = finalizers[..2]
+            .iter()
+            .map(|f| {
+                sp_io::crypto::ecdsa_sign_prehashed(
+                    OFFCHAIN_SIGNER_KEY_TYPE,
+                    f,
+                    hash.as_fixed_bytes(),
+                )
+                .unwrap()
+                .0
+            })
+            .collect();
+        assert_noop!(submit(foreign), Error::<Test>::NotEnoughAttestations);
+
+        assert_noop!(
+            submit(vec![[0; 65]; MAX_FRAUD_ATTESTATIONS + 1]),
+            Error::<Test>::TooManyAttestations
+        );
+    });
+}
+
+#[test]
+fn a_fraud_proof_must_name_a_registered_miner() {
+    new_test_ext().execute_with(|| {
+        let (_, finalizers) = fraud_setup();
+        let submit = |proof: QpFraudProof| {
+            let attestations = vec![
+                attest(&finalizers[0], &proof),
+                attest(&finalizers[1], &proof),
+            ];
+            QuantumPortal::submit_fraud_proof(
+                RuntimeOrigin::signed(account(20)),
+                proof,
+                attestations,
+            )
+        };
+
+        let mut same_txs = fraud_proof(1);
+        same_txs.source_txs_hash = same_txs.mined_txs_hash;
+        assert_noop!(submit(same_txs), Error::<Test>::ProofNotFraudulent);
+        // Not a registered signer, or the signer of a finalizer
+        assert_noop!(submit(fraud_proof(2)), Error::<Test>::UnknownMiner);
+        let finalizer_signer = H160::from_slice(&ChainUtils::eth_address_from_public_key(
+            finalizers[0].as_ref(),
+        ));
+        let mut by_finalizer = fraud_proof(1);
+        by_finalizer.miner = finalizer_signer;
+        assert_noop!(submit(by_finalizer), Error::<Test>::UnknownMiner);
+    });
+}
+
+#[test]
+fn a_miner_is_slashed_for_a_block_it_did_not_report() {
+    new_test_ext().execute_with(|| {
+        let (miner, finalizers) = fraud_setup();
+        // The miner moved on to its next block
+        UnconfirmedMinedBlocks::<Test>::insert((REMOTE_CHAIN, LOCAL_CHAIN), &miner, 2);
+        let proof = fraud_proof(1);
+        let attestations = vec![
+            attest(&finalizers[0], &proof),
+            attest(&finalizers[1], &proof),
+        ];
+
+        assert_ok!(QuantumPortal::submit_fraud_proof(
+            RuntimeOrigin::signed(account(20)),
+            proof,
+            attestations
+        ));
+
+        assert_eq!(Workers::<Test>::get(&miner).unwrap().bond, 50);
+        // The next block is not affected
+        assert_eq!(
+            UnconfirmedMinedBlocks::<Test>::get((REMOTE_CHAIN, LOCAL_CHAIN), &miner),
+            Some(2)
+        );
+    });
+}
+
+#[test]
+fn finalizer_registration_is_bounded() {
+    new_test_ext().execute_with(|| {
+        for id in 0..100 {
+            register_finalizer(id);
+        }
+        let late = funded(100, 1_000);
+        assert_noop!(
+            QuantumPortal::register_finalizer(RuntimeOrigin::signed(late), signer_address(100)),
+            Error::<Test>::TooManyFinalizers
+        );
+        // Miners are not bounded by it
+        register_miner(101);
+    });
+}
+
+fn register_pair() {
+    assert_ok!(QuantumPortal::register_network(
+        RuntimeOrigin::root(),
+        REMOTE_CHAIN,
+        H160::repeat_byte(1)
+    ));
+    assert_ok!(QuantumPortal::register_network(
+        RuntimeOrigin::root(),
+        LOCAL_CHAIN,
+        H160::repeat_byte(2)
+    ));
+}
+
+fn work_report(nonce: u64, miner: H160) -> QpWorkReport {
+    QpWorkReport {
+        remote_chain: REMOTE_CHAIN,
+        local_chain: LOCAL_CHAIN,
+        nonce,
+        miner,
+    }
+}
+
+fn sign_report(key: &ecdsa::Public, kind: QpBlockRecordKind, report: &QpWorkReport) -> [u8; 65] {
+    let hash = report.signing_hash(kind, &QuantumPortal::genesis_hash());
+    sp_io::crypto::ecdsa_sign_prehashed(OFFCHAIN_SIGNER_KEY_TYPE, key, hash.as_fixed_bytes())
+        .unwrap()
+        .0
+}
+
+fn report_mined(key: &ecdsa::Public, nonce: u64) -> DispatchResult {
+    let report = work_report(nonce, key_address(key));
+    let signature = sign_report(key, QpBlockRecordKind::Mined, &report);
+    QuantumPortal::report_mined_block(RuntimeOrigin::none(), report, signature)
+}
+
+fn attest_finalized(key: &ecdsa::Public, nonce: u64, miner: H160) -> DispatchResult {
+    let report = work_report(nonce, miner);
+    let signature = sign_report(key, QpBlockRecordKind::Finalized, &report);
+    QuantumPortal::report_finalized_block(RuntimeOrigin::none(), report, signature)
+        .map(|_| ())
+        .map_err(|e| e.error)
+}
+
+/// A registered pair, a miner with id 1 and three finalizers with ids 10 to 12
+fn report_setup() -> (ecdsa::Public, Vec<ecdsa::Public>) {
+    register_pair();
+    let miner = register_miner_with_key(1);
+    let finalizers = (10..13).map(register_finalizer_with_key).collect();
+    (miner, finalizers)
+}
+
+#[test]
+fn a_finalized_block_is_confirmed_by_a_quorum_of_attestations() {
+    new_test_ext().execute_with(|| {
+        let (miner_key, finalizers) = report_setup();
+        let miner = account(1);
+        let miner_signer = key_address(&miner_key);
+
+        assert_ok!(report_mined(&miner_key, 5));
+        assert_eq!(LastMinedNonce::<Test>::get(REMOTE_CHAIN, LOCAL_CHAIN), 5);
+        assert_eq!(
+            UnconfirmedMinedBlocks::<Test>::get((REMOTE_CHAIN, LOCAL_CHAIN), &miner),
+            Some(5)
+        );
+
+        // 66% of 3 finalizers, rounded up
+        assert_ok!(attest_finalized(&finalizers[0], 5, miner_signer));
+        assert_eq!(
+            LastFinalizedNonce::<Test>::get(REMOTE_CHAIN, LOCAL_CHAIN),
+            0
+        );
+        assert_eq!(
+            PendingFinalizations::<Test>::get((REMOTE_CHAIN, LOCAL_CHAIN)).len(),
+            1
+        );
+        assert_noop!(
+            attest_finalized(&finalizers[0], 5, miner_signer),
+            Error::<Test>::AlreadyReported
+        );
+        assert_ok!(attest_finalized(&finalizers[2], 5, miner_signer));
+
+        assert_eq!(
+            LastFinalizedNonce::<Test>::get(REMOTE_CHAIN, LOCAL_CHAIN),
+            5
+        );
+        assert!(PendingFinalizations::<Test>::get((REMOTE_CHAIN, LOCAL_CHAIN)).is_empty());
+        assert!(UnconfirmedMinedBlocks::<Test>::get((REMOTE_CHAIN, LOCAL_CHAIN), &miner).is_none());
+        assert_eq!(EraWork::<Test>::get(0, &miner), 1);
+        assert_eq!(EraWork::<Test>::get(0, account(10)), 1);
+        assert_eq!(EraWork::<Test>::get(0, account(11)), 0);
+        assert_eq!(EraWork::<Test>::get(0, account(12)), 1);
+        assert_eq!(EraTotalWork::<Test>::get(0), 3);
+        System::assert_last_event(
+            Event::BlockFinalized {
+                remote_chain: REMOTE_CHAIN,
+                local_chain: LOCAL_CHAIN,
+                nonce: 5,
+                attesters: 2,
+            }
+            .into(),
+        );
+
+        // Late attestations of a confirmed block are refused
+        assert_noop!(
+            attest_finalized(&finalizers[1], 5, miner_signer),
+            Error::<Test>::NonceNotIncreasing
+        );
+    });
+}
+
+#[test]
+fn only_the_attested_miner_is_credited() {
+    new_test_ext().execute_with(|| {
+        let (miner_key, finalizers) = report_setup();
+        let other_key = register_miner_with_key(2);
+        assert_ok!(report_mined(&miner_key, 5));
+        assert_ok!(report_mined(&other_key, 5));
+
+        // One finalizer disagrees, its attestation does not count nor earn
+        assert_ok!(attest_finalized(&finalizers[0], 5, key_address(&miner_key)));
+        assert_ok!(attest_finalized(&finalizers[1], 5, key_address(&other_key)));
+        assert_ok!(attest_finalized(&finalizers[2], 5, key_address(&other_key)));
+
+        assert_eq!(
+            LastFinalizedNonce::<Test>::get(REMOTE_CHAIN, LOCAL_CHAIN),
+            5
+        );
+        assert_eq!(EraWork::<Test>::get(0, account(1)), 0);
+        assert_eq!(EraWork::<Test>::get(0, account(2)), 1);
+        assert_eq!(EraWork::<Test>::get(0, account(10)), 0);
+        assert_eq!(EraTotalWork::<Test>::get(0), 3);
+    });
+}
+
+#[test]
+fn work_reports_must_be_signed_by_an_active_worker() {
+    new_test_ext().execute_with(|| {
+        let (miner_key, finalizers) = report_setup();
+        let miner_signer = key_address(&miner_key);
+
+        let report = work_report(1, miner_signer);
+        let signature = sign_report(&miner_key, QpBlockRecordKind::Mined, &report);
+        assert_noop!(
+            QuantumPortal::report_mined_block(
+                RuntimeOrigin::signed(account(1)),
+                report.clone(),
+                signature
+            ),
+            DispatchError::BadOrigin
+        );
+        // Signed for another kind of report, or by an unknown key
+        let signature = sign_report(&miner_key, QpBlockRecordKind::Finalized, &report);
+        assert_noop!(
+            QuantumPortal::report_mined_block(RuntimeOrigin::none(), report, signature),
+            Error::<Test>::InvalidReportSignature
+        );
+        let outsider = sp_io::crypto::ecdsa_generate(OFFCHAIN_SIGNER_KEY_TYPE, None);
+        assert_noop!(
+            report_mined(&outsider, 1),
+            Error::<Test>::InvalidReportSignature
+        );
+        // A finalizer reporting a mined block, a miner attesting a finalized one
+        assert_noop!(report_mined(&finalizers[0], 1), Error::<Test>::NotMiner);
+        assert_noop!(
+            attest_finalized(&miner_key, 1, miner_signer),
+            Error::<Test>::NotFinalizer
+        );
+        // A miner claiming the block of another signer
+        let report = work_report(1, signer_address(7));
+        let signature = sign_report(&miner_key, QpBlockRecordKind::Mined, &report);
+        assert_noop!(
+            QuantumPortal::report_mined_block(RuntimeOrigin::none(), report, signature),
+            Error::<Test>::NotMiner
+        );
+        // An unbonded miner
+        assert_ok!(QuantumPortal::unbond(RuntimeOrigin::signed(account(1))));
+        assert_noop!(report_mined(&miner_key, 1), Error::<Test>::NotMiner);
+    });
+}
+
+#[test]
+fn mined_nonces_are_bounded_by_the_last_finalized_one() {
+    new_test_ext().execute_with(|| {
+        let (miner_key, finalizers) = report_setup();
+
+        // Would otherwise stop the pair for good
+        assert_noop!(
+            report_mined(&miner_key, u64::MAX),
+            Error::<Test>::NonceTooFar
+        );
+        assert_noop!(
+            report_mined(&miner_key, MAX_NONCE_JUMP + 1),
+            Error::<Test>::NonceTooFar
+        );
+        assert_ok!(report_mined(&miner_key, MAX_NONCE_JUMP));
+        assert_noop!(
+            report_mined(&miner_key, MAX_NONCE_JUMP),
+            Error::<Test>::AlreadyReported
+        );
+
+        // The window moves with the confirmed finalized nonce, the first confirmation of a pair
+        // can start at any nonce
+        let start = 10_000;
+        for finalizer in &finalizers[..2] {
+            assert_ok!(attest_finalized(finalizer, start, H160::zero()));
+        }
+        assert_eq!(
+            LastFinalizedNonce::<Test>::get(REMOTE_CHAIN, LOCAL_CHAIN),
+            start
+        );
+        assert_noop!(
+            report_mined(&miner_key, start),
+            Error::<Test>::NonceNotIncreasing
+        );
+        assert_ok!(report_mined(&miner_key, start + MAX_NONCE_JUMP));
+
+        // Work reports of unregistered networks are refused
+        assert_ok!(QuantumPortal::deregister_network(
+            RuntimeOrigin::root(),
+            REMOTE_CHAIN
+        ));
+        assert_noop!(
+            report_mined(&miner_key, start + 1),
+            Error::<Test>::NetworkNotRegistered
+        );
+    });
+}
+
+#[test]
+fn old_work_reports_cannot_be_replayed() {
+    new_test_ext().execute_with(|| {
+        let (miner_key, finalizers) = report_setup();
+        let miner_signer = key_address(&miner_key);
+
+        // Replaying an older report would swap the unconfirmed block back
+        assert_ok!(report_mined(&miner_key, 5));
+        assert_ok!(report_mined(&miner_key, 6));
+        assert_noop!(report_mined(&miner_key, 5), Error::<Test>::AlreadyReported);
+        assert_noop!(report_mined(&miner_key, 6), Error::<Test>::AlreadyReported);
+
+        // Nor may a finalizer go back to an older attestation
+        assert_ok!(attest_finalized(&finalizers[0], 6, miner_signer));
+        assert_noop!(
+            attest_finalized(&finalizers[0], 5, miner_signer),
+            Error::<Test>::AlreadyReported
+        );
+        assert_noop!(
+            attest_finalized(&finalizers[0], 6, signer_address(7)),
+            Error::<Test>::AlreadyReported
+        );
+        assert_ok!(attest_finalized(&finalizers[0], 7, miner_signer));
+        assert_eq!(
+            LastReportedNonce::<Test>::get((REMOTE_CHAIN, LOCAL_CHAIN), account(10)),
+            Some(7)
+        );
+    });
+}
+
+#[test]
+fn only_valid_work_reports_enter_the_pool() {
+    new_test_ext().execute_with(|| {
+        let (miner_key, _) = report_setup();
+        let validate = |nonce: u64| {
+            let report = work_report(nonce, key_address(&miner_key));
+            let signature = sign_report(&miner_key, QpBlockRecordKind::Mined, &report);
+            QuantumPortal::validate_unsigned(
+                TransactionSource::External,
+                &Call::report_mined_block { report, signature },
+            )
+        };
+
+        assert!(validate(1).is_ok());
+        assert_eq!(
+            validate(MAX_NONCE_JUMP + 1),
+            InvalidTransaction::Future.into()
+        );
+        assert_ok!(report_mined(&miner_key, 1));
+        assert_eq!(validate(1), InvalidTransaction::Stale.into());
+        // Newer reports of a worker replace each other in the pool
+        assert_eq!(validate(2).unwrap().provides, validate(3).unwrap().provides);
+
+        let report = work_report(2, key_address(&miner_key));
+        assert_eq!(
+            QuantumPortal::validate_unsigned(
+                TransactionSource::External,
+                &Call::report_mined_block {
+                    report,
+                    signature: [0; 65]
+                },
+            ),
+            InvalidTransaction::BadProof.into()
+        );
+        assert_eq!(
+            QuantumPortal::validate_unsigned(TransactionSource::External, &Call::claim_rewards {},),
+            InvalidTransaction::Call.into()
+        );
+    });
+}
+
+#[test]
+fn a_registered_finalizer_attests_the_last_finalized_block() {
+    let (mut ext, state, signer) = new_offchain_ext();
+    let (pool, pool_state) = TestTransactionPoolExt::new();
+    ext.register_extension(TransactionPoolExt::new(pool));
+    ext.execute_with(|| {
+        register_pair();
+        assert_ok!(QuantumPortal::register_finalizer(
+            RuntimeOrigin::signed(funded(10, 1_000)),
+            key_address(&signer)
+        ));
+        {
+            let mut state = state.write();
+            // The attestation
+            expect_chain_id(&mut state, LOCAL_RPC, LOCAL_CHAIN);
+            expect_call(
+                &mut state,
+                LOCAL_CHAIN,
+                b"lastFinalizedBlock(uint256)",
+                &[uint(REMOTE_CHAIN)],
+                &local_block(REMOTE_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP),
+            );
+            expect_mined_block(&mut state, H256::repeat_byte(7));
+            // Nothing left to finalize
+            expect_call(
+                &mut state,
+                LOCAL_CHAIN,
+                b"lastRemoteMinedBlock(uint64)",
+                &[uint(REMOTE_CHAIN)],
+                &local_block(REMOTE_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP),
+            );
+            expect_call(
+                &mut state,
+                LOCAL_CHAIN,
+                b"lastFinalizedBlock(uint256)",
+                &[uint(REMOTE_CHAIN)],
+                &local_block(REMOTE_CHAIN, SOURCE_NONCE, SOURCE_TIMESTAMP),
+            );
+        }
+        let svc = service(&signer);
+
+        let outcome = svc.process_pair_with_lock(REMOTE_CHAIN, LOCAL_CHAIN, Role::QP_FINALIZER);
+
+        assert_eq!(outcome, Ok(PairOutcome::Idle));
+        let tx = pool_state.write().transactions.pop().unwrap();
+        assert!(pool_state.read().transactions.is_empty());
+        let tx = TestXt::<RuntimeCall, ()>::decode(&mut &*tx).unwrap();
+        assert_eq!(tx.signature, None);
+        match &tx.call {
+            RuntimeCall::QuantumPortal(Call::report_finalized_block { report, .. }) => {
+                assert_eq!(report, &work_report(SOURCE_NONCE, H160::repeat_byte(0x55)))
+            }
+            call => panic!("unexpected call {:?}", call),
+        }
+        // The only finalizer is a quorum
+        assert_ok!(tx.call.dispatch(RuntimeOrigin::none()));
+        assert_eq!(
+            LastFinalizedNonce::<Test>::get(REMOTE_CHAIN, LOCAL_CHAIN),
+            SOURCE_NONCE
+        );
+        assert_eq!(EraWork::<Test>::get(0, account(10)), 1);
+    });
+}
+
+#[test]
+fn an_era_is_paid_out_over_several_blocks() {
+    new_test_ext().execute_with(|| {
+        let workers = MAX_PAYOUTS_PER_BLOCK as u8 + 6;
+        for id in 0..workers {
+            EraWork::<Test>::insert(0, account(id), 1);
+        }
+        EraTotalWork::<Test>::insert(0, workers as u64);
+
+        QuantumPortal::on_initialize(99);
+        assert_eq!(CurrentEra::<Test>::get(), 0);
+        assert_eq!(PayoutEra::<Test>::get(), None);
+
+        QuantumPortal::on_initialize(100);
+        assert_eq!(CurrentEra::<Test>::get(), 1);
+        assert_eq!(PayoutEra::<Test>::get(), Some(0));
+        assert_eq!(
+            EraWork::<Test>::iter_prefix(0).count(),
+            workers as usize - MAX_PAYOUTS_PER_BLOCK as usize
+        );
+
+        QuantumPortal::on_initialize(101);
+        assert_eq!(PayoutEra::<Test>::get(), None);
+        assert_eq!(EraWork::<Test>::iter_prefix(0).count(), 0);
+        assert_eq!(EraTotalWork::<Test>::get(0), 0);
+        // 1000 shared between 70 workers
+        for id in 0..workers {
+            assert_eq!(PendingRewards::<Test>::get(account(id)), 14);
+        }
+        System::assert_last_event(
+            Event::EraRewarded {
+                era: 0,
+                reward: 1_000,
+                total_work: workers as u64,
+            }
+            .into(),
+        );
+
+        // An era without work is paid out without an event
+        System::reset_events();
+        QuantumPortal::on_initialize(200);
+        assert_eq!(CurrentEra::<Test>::get(), 2);
+        assert_eq!(PayoutEra::<Test>::get(), None);
+        assert!(System::events().is_empty());
+    });
+}
+
+#[test]
+fn work_is_credited_to_the_current_era() {
+    new_test_ext().execute_with(|| {
+        let (miner_key, finalizers) = report_setup();
+        CurrentEra::<Test>::put(3);
+        assert_ok!(report_mined(&miner_key, 1));
+        for finalizer in &finalizers[..2] {
+            assert_ok!(attest_finalized(finalizer, 1, key_address(&miner_key)));
+        }
+        assert_eq!(EraWork::<Test>::get(3, account(1)), 1);
+        assert_eq!(EraTotalWork::<Test>::get(3), 3);
+        assert_eq!(EraTotalWork::<Test>::get(0), 0);
+    });
+}
+
+#[test]
+fn rewards_are_claimed_from_the_pot() {
+    new_test_ext().execute_with(|| {
+        let worker = funded(1, 10);
+        assert_noop!(
+            QuantumPortal::claim_rewards(RuntimeOrigin::signed(worker.clone())),
+            Error::<Test>::NoRewards
+        );
+
+        PendingRewards::<Test>::insert(&worker, 100);
+        // The pot was never funded
+        assert_noop!(
+            QuantumPortal::claim_rewards(RuntimeOrigin::signed(worker.clone())),
+            Error::<Test>::InsufficientPot
+        );
+
+        Balances::make_free_balance_be(&QuantumPortal::pot_account(), 1_000);
+        assert_ok!(QuantumPortal::claim_rewards(RuntimeOrigin::signed(
+            worker.clone()
+        )));
+        assert_eq!(Balances::free_balance(&worker), 110);
+        assert_eq!(Balances::free_balance(&QuantumPortal::pot_account()), 900);
+        assert_eq!(PendingRewards::<Test>::get(&worker), 0);
+        System::assert_last_event(
+            Event::RewardsClaimed {
+                who: worker,
+                amount: 100,
+            }
+            .into(),
+        );
+    });
+}
+
+#[test]
+fn the_v1_migration_removes_the_unused_storage() {
+    new_test_ext().execute_with(|| {
+        StorageVersion::new(0).put::<QuantumPortal>();
+        v1::Numbers::<Test>::put(7);
+        v1::PendingTransactions::<Test>::insert(
+            REMOTE_CHAIN,
+            PendingTransaction::FinalizeTransaction(REMOTE_CHAIN, 1, H256::repeat_byte(1)),
+        );
+        v1::PendingTransactions::<Test>::insert(
+            LOCK_CHAIN,
+            PendingTransaction::MineTransaction(LOCAL_CHAIN, REMOTE_CHAIN, 1, H256::repeat_byte(2)),
+        );
+
+        v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+        assert_eq!(QuantumPortal::on_chain_storage_version(), 1);
+        assert!(!v1::Numbers::<Test>::exists());
+        assert_eq!(v1::PendingTransactions::<Test>::iter_keys().count(), 0);
+    });
+}
+
+#[test]
+fn the_v1_migration_is_skipped_once_applied() {
+    new_test_ext().execute_with(|| {
+        StorageVersion::new(1).put::<QuantumPortal>();
+        v1::Numbers::<Test>::put(7);
+
+        v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+        assert_eq!(QuantumPortal::on_chain_storage_version(), 1);
+        assert_eq!(v1::Numbers::<Test>::get(), 7);
+    });
 }
@@ -0,0 +1,263 @@
+//! Weights for pallet_quantum_portal
+//!
+//! These are NOT benchmark results. The values were estimated by hand from the storage accesses
+//! of each call and have not been measured; the `// Storage:` comments list the accesses each
+//! estimate assumes, not recorded benchmark output. Replace the whole file with the output of the
+//! benchmarks in `benchmarking.rs`, run on the reference validator hardware, before relying on the
+//! fees or block limits of these calls, and again after changing a call:
+//!
+//! ./target/release/ferrum-x-network benchmark pallet
+//!     --chain=dev
+//!     --pallet=pallet_quantum_portal
+//!     --extrinsic=*
+//!     --steps=50
+//!     --repeat=20
+//!     --execution=wasm
+//!     --wasm-execution=compiled
+//!     --output=pallets/quantum-portal/src/weights.rs
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{
+    traits::Get,
+    weights::{constants::RocksDbWeight, Weight},
+};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_quantum_portal.
+pub trait WeightInfo {
+    fn register_network() -> Weight;
+    fn deregister_network() -> Weight;
+    fn report_mined_block() -> Weight;
+    fn report_finalized_block(f: u32) -> Weight;
+    fn register_miner() -> Weight;
+    fn register_finalizer() -> Weight;
+    fn unbond() -> Weight;
+    fn withdraw_unbonded() -> Weight;
+    fn submit_fraud_proof(a: u32, f: u32) -> Weight;
+    fn claim_rewards() -> Weight;
+    fn slash() -> Weight;
+    fn end_era() -> Weight;
+    fn pay_era_rewards(w: u32) -> Weight;
+}
+
+/// Estimated weights for pallet_quantum_portal, to be replaced by benchmarked ones.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+    // Storage: QuantumPortal RegisteredNetworks (r:0 w:1)
+    fn register_network() -> Weight {
+        Weight::from_ref_time(17_310_000 as u64).saturating_add(T::DbWeight::get().writes(1 as u64))
+    }
+    // Storage: QuantumPortal RegisteredNetworks (r:1 w:1)
+    fn deregister_network() -> Weight {
+        Weight::from_ref_time(22_540_000 as u64)
+            .saturating_add(T::DbWeight::get().reads(1 as u64))
+            .saturating_add(T::DbWeight::get().writes(1 as u64))
+    }
+    // Storage: System BlockHash (r:1 w:0)
+    // Storage: QuantumPortal Signers (r:1 w:0)
+    // Storage: QuantumPortal RegisteredNetworks (r:2 w:0)
+    // Storage: QuantumPortal LastFinalizedNonce (r:1 w:0)
+    // Storage: QuantumPortal Miners (r:1 w:0)
+    // Storage: QuantumPortal LastReportedNonce (r:1 w:1)
+    // Storage: QuantumPortal UnconfirmedMinedBlocks (r:0 w:1)
+    // Storage: QuantumPortal LastMinedNonce (r:1 w:1)
+    // Storage: System Number (r:1 w:0)
+    // Storage: QuantumPortal RecentBlockRecords (r:1 w:1)
+    fn report_mined_block() -> Weight {
+        Weight::from_ref_time(112_460_000 as u64)
+            .saturating_add(T::DbWeight::get().reads(10 as u64))
+            .saturating_add(T::DbWeight::get().writes(4 as u64))
+    }
+    // Storage: System BlockHash (r:1 w:0)
+    // Storage: QuantumPortal Signers (r:2 w:0)
+    // Storage: QuantumPortal RegisteredNetworks (r:2 w:0)
+    // Storage: QuantumPortal LastFinalizedNonce (r:1 w:1)
+    // Storage: QuantumPortal Finalizers (r:101 w:0)
+    // Storage: QuantumPortal LastReportedNonce (r:1 w:1)
+    // Storage: QuantumPortal PendingFinalizations (r:1 w:1)
+    // Storage: QuantumPortal CounterForFinalizers (r:1 w:0)
+    // Storage: QuantumPortal UnconfirmedMinedBlocks (r:1 w:1)
+    // Storage: QuantumPortal CurrentEra (r:1 w:0)
+    // Storage: QuantumPortal EraWork (r:101 w:101)
+    // Storage: QuantumPortal EraTotalWork (r:1 w:1)
+    // Storage: System Number (r:1 w:0)
+    // Storage: QuantumPortal RecentBlockRecords (r:1 w:1)
+    /// The range of component `f` is `[0, 99]`.
+    fn report_finalized_block(f: u32) -> Weight {
+        Weight::from_ref_time(131_580_000 as u64)
+            .saturating_add(Weight::from_ref_time(12_410_000 as u64).saturating_mul(f as u64))
+            .saturating_add(T::DbWeight::get().reads(16 as u64))
+            .saturating_add(T::DbWeight::get().reads((2 as u64).saturating_mul(f as u64)))
+            .saturating_add(T::DbWeight::get().writes(8 as u64))
+            .saturating_add(T::DbWeight::get().writes((1 as u64).saturating_mul(f as u64)))
+    }
+    // Storage: QuantumPortal Workers (r:1 w:1)
+    // Storage: QuantumPortal Signers (r:1 w:1)
+    // Storage: System Account (r:1 w:1)
+    // Storage: QuantumPortal Miners (r:0 w:1)
+    fn register_miner() -> Weight {
+        Weight::from_ref_time(48_930_000 as u64)
+            .saturating_add(T::DbWeight::get().reads(3 as u64))
+            .saturating_add(T::DbWeight::get().writes(4 as u64))
+    }
+    // Storage: QuantumPortal Workers (r:1 w:1)
+    // Storage: QuantumPortal Signers (r:1 w:1)
+    // Storage: QuantumPortal CounterForFinalizers (r:1 w:1)
+    // Storage: System Account (r:1 w:1)
+    // Storage: QuantumPortal Finalizers (r:1 w:1)
+    fn register_finalizer() -> Weight {
+        Weight::from_ref_time(52_480_000 as u64)
+            .saturating_add(T::DbWeight::get().reads(5 as u64))
+            .saturating_add(T::DbWeight::get().writes(5 as u64))
+    }
+    // Storage: QuantumPortal Workers (r:1 w:1)
+    // Storage: System Number (r:1 w:0)
+    // Storage: QuantumPortal Miners (r:0 w:1)
+    fn unbond() -> Weight {
+        Weight::from_ref_time(31_750_000 as u64)
+            .saturating_add(T::DbWeight::get().reads(2 as u64))
+            .saturating_add(T::DbWeight::get().writes(2 as u64))
+    }
+    // Storage: QuantumPortal Workers (r:1 w:1)
+    // Storage: System Number (r:1 w:0)
+    // Storage: System Account (r:1 w:1)
+    // Storage: QuantumPortal Signers (r:0 w:1)
+    fn withdraw_unbonded() -> Weight {
+        Weight::from_ref_time(45_180_000 as u64)
+            .saturating_add(T::DbWeight::get().reads(3 as u64))
+            .saturating_add(T::DbWeight::get().writes(3 as u64))
+    }
+    // Storage: QuantumPortal ProvenFrauds (r:1 w:1)
+    // Storage: QuantumPortal Signers (r:1 w:0)
+    // Storage: QuantumPortal Workers (r:101 w:1)
+    // Storage: QuantumPortal UnconfirmedMinedBlocks (r:1 w:1)
+    // Storage: QuantumPortal Finalizers (r:101 w:0)
+    // Storage: System BlockHash (r:1 w:0)
+    // Storage: System Account (r:1 w:1)
+    // Storage: QuantumPortal Miners (r:0 w:1)
+    /// The range of component `a` is `[1, 100]`.
+    /// The range of component `f` is `[1, 100]`.
+    fn submit_fraud_proof(a: u32, f: u32) -> Weight {
+        Weight::from_ref_time(95_640_000 as u64)
+            .saturating_add(Weight::from_ref_time(71_230_000 as u64).saturating_mul(a as u64))
+            .saturating_add(Weight::from_ref_time(9_870_000 as u64).saturating_mul(f as u64))
+            .saturating_add(T::DbWeight::get().reads(7 as u64))
+            .saturating_add(T::DbWeight::get().reads((2 as u64).saturating_mul(f as u64)))
+            .saturating_add(T::DbWeight::get().writes(5 as u64))
+    }
+    // Storage: QuantumPortal PendingRewards (r:1 w:1)
+    // Storage: System Account (r:2 w:2)
+    fn claim_rewards() -> Weight {
+        Weight::from_ref_time(52_360_000 as u64)
+            .saturating_add(T::DbWeight::get().reads(3 as u64))
+            .saturating_add(T::DbWeight::get().writes(3 as u64))
+    }
+    // Storage: QuantumPortal Workers (r:1 w:1)
+    // Storage: System Account (r:1 w:1)
+    // Storage: QuantumPortal Miners (r:0 w:1)
+    fn slash() -> Weight {
+        Weight::from_ref_time(44_070_000 as u64)
+            .saturating_add(T::DbWeight::get().reads(2 as u64))
+            .saturating_add(T::DbWeight::get().writes(3 as u64))
+    }
+    // Storage: QuantumPortal CurrentEra (r:1 w:1)
+    // Storage: QuantumPortal PayoutEra (r:1 w:1)
+    fn end_era() -> Weight {
+        Weight::from_ref_time(9_840_000 as u64)
+            .saturating_add(T::DbWeight::get().reads(2 as u64))
+            .saturating_add(T::DbWeight::get().writes(2 as u64))
+    }
+    // Storage: QuantumPortal PayoutEra (r:1 w:1)
+    // Storage: QuantumPortal EraTotalWork (r:1 w:1)
+    // Storage: QuantumPortal EraWork (r:65 w:64)
+    // Storage: QuantumPortal PendingRewards (r:64 w:64)
+    // Storage: QuantumPortal CurrentEra (r:1 w:0)
+    /// The range of component `w` is `[0, 64]`.
+    fn pay_era_rewards(w: u32) -> Weight {
+        Weight::from_ref_time(18_720_000 as u64)
+            .saturating_add(Weight::from_ref_time(11_350_000 as u64).saturating_mul(w as u64))
+            .saturating_add(T::DbWeight::get().reads(4 as u64))
+            .saturating_add(T::DbWeight::get().reads((2 as u64).saturating_mul(w as u64)))
+            .saturating_add(T::DbWeight::get().writes(2 as u64))
+            .saturating_add(T::DbWeight::get().writes((2 as u64).saturating_mul(w as u64)))
+    }
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+    fn register_network() -> Weight {
+        Weight::from_ref_time(17_310_000 as u64)
+            .saturating_add(RocksDbWeight::get().writes(1 as u64))
+    }
+    fn deregister_network() -> Weight {
+        Weight::from_ref_time(22_540_000 as u64)
+            .saturating_add(RocksDbWeight::get().reads(1 as u64))
+            .saturating_add(RocksDbWeight::get().writes(1 as u64))
+    }
+    fn report_mined_block() -> Weight {
+        Weight::from_ref_time(112_460_000 as u64)
+            .saturating_add(RocksDbWeight::get().reads(10 as u64))
+            .saturating_add(RocksDbWeight::get().writes(4 as u64))
+    }
+    fn report_finalized_block(f: u32) -> Weight {
+        Weight::from_ref_time(131_580_000 as u64)
+            .saturating_add(Weight::from_ref_time(12_410_000 as u64).saturating_mul(f as u64))
+            .saturating_add(RocksDbWeight::get().reads(16 as u64))
+            .saturating_add(RocksDbWeight::get().reads((2 as u64).saturating_mul(f as u64)))
+            .saturating_add(RocksDbWeight::get().writes(8 as u64))
+            .saturating_add(RocksDbWeight::get().writes((1 as u64).saturating_mul(f as u64)))
+    }
+    fn register_miner() -> Weight {
+        Weight::from_ref_time(48_930_000 as u64)
+            .saturating_add(RocksDbWeight::get().reads(3 as u64))
+            .saturating_add(RocksDbWeight::get().writes(4 as u64))
+    }
+    fn register_finalizer() -> Weight {
+        Weight::from_ref_time(52_480_000 as u64)
+            .saturating_add(RocksDbWeight::get().reads(5 as u64))
+            .saturating_add(RocksDbWeight::get().writes(5 as u64))
+    }
+    fn unbond() -> Weight {
+        Weight::from_ref_time(31_750_000 as u64)
+            .saturating_add(RocksDbWeight::get().reads(2 as u64))
+            .saturating_add(RocksDbWeight::get().writes(2 as u64))
+    }
+    fn withdraw_unbonded() -> Weight {
+        Weight::from_ref_time(45_180_000 as u64)
+            .saturating_add(RocksDbWeight::get().reads(3 as u64))
+            .saturating_add(RocksDbWeight::get().writes(3 as u64))
+    }
+    fn submit_fraud_proof(a: u32, f: u32) -> Weight {
+        Weight::from_ref_time(95_640_000 as u64)
+            .saturating_add(Weight::from_ref_time(71_230_000 as u64).saturating_mul(a as u64))
+            .saturating_add(Weight::from_ref_time(9_870_000 as u64).saturating_mul(f as u64))
+            .saturating_add(RocksDbWeight::get().reads(7 as u64))
+            .saturating_add(RocksDbWeight::get().reads((2 as u64).saturating_mul(f as u64)))
+            .saturating_add(RocksDbWeight::get().writes(5 as u64))
+    }
+    fn claim_rewards() -> Weight {
+        Weight::from_ref_time(52_360_000 as u64)
+            .saturating_add(RocksDbWeight::get().reads(3 as u64))
+            .saturating_add(RocksDbWeight::get().writes(3 as u64))
+    }
+    fn slash() -> Weight {
+        Weight::from_ref_time(44_070_000 as u64)
+            .saturating_add(RocksDbWeight::get().reads(2 as u64))
+            .saturating_add(RocksDbWeight::get().writes(3 as u64))
+    }
+    fn end_era() -> Weight {
+        Weight::from_ref_time(9_840_000 as u64)
+            .saturating_add(RocksDbWeight::get().reads(2 as u64))
+            .saturating_add(RocksDbWeight::get().writes(2 as u64))
+    }
+    fn pay_era_rewards(w: u32) -> Weight {
+        Weight::from_ref_time(18_720_000 as u64)
+            .saturating_add(Weight::from_ref_time(11_350_000 as u64).saturating_mul(w as u64))
+            .saturating_add(RocksDbWeight::get().reads(4 as u64))
+            .saturating_add(RocksDbWeight::get().reads((2 as u64).saturating_mul(w as u64)))
+            .saturating_add(RocksDbWeight::get().writes(2 as u64))
+            .saturating_add(RocksDbWeight::get().writes((2 as u64).saturating_mul(w as u64)))
+    }
+}
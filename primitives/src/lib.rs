@@ -24,6 +24,12 @@ pub const OFFCHAIN_SIGNER_CONFIG_PREFIX: &[u8] = b"OFFCHAIN_SIGNER_CONFIG";
 
 pub const OFFCHAIN_SIGNER_CONFIG_KEY: &[u8] = b"network_config";
 
+/// Offchain storage key holding the Quantum Portal worker metrics, scraped by the node
+pub const OFFCHAIN_QP_METRICS_KEY: &[u8] = b"quantum-portal::metrics";
+
+/// Offchain storage key holding the Quantum Portal worker scheduling state
+pub const OFFCHAIN_QP_SCHEDULE_KEY: &[u8] = b"quantum-portal::schedule";
+
 /// The account type to be used in Ferrum. It is a wrapper for 20 fixed bytes. We prefer to use
 /// a dedicated type to prevent using arbitrary 20 byte arrays were AccountIds are expected. With
 /// the introduction of the `scale-info` crate this benefit extends even to non-Rust tools like
@@ -34,16 +40,47 @@ pub const OFFCHAIN_SIGNER_CONFIG_KEY: &[u8] = b"network_config";
 )]
 pub struct AccountId20(pub [u8; 20]);
 
+/// Format an address with the EIP-55 mixed case checksum, e.g.
+/// `0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed`
+#[cfg(feature = "std")]
+pub fn to_checksum_address(address: &[u8; 20]) -> String {
+    let lower = hex::encode(address);
+    let hash = Keccak256::digest(lower.as_bytes());
+    let checksummed: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("0x{checksummed}")
+}
+
+/// Parse a hex address with an optional `0x` prefix. All lower or all upper case addresses carry
+/// no checksum and are accepted as is, mixed case addresses must have a valid EIP-55 checksum.
 #[cfg(feature = "std")]
-impl_serde::impl_fixed_hash_serde!(AccountId20, 20);
+pub fn parse_checksum_address(input: &str) -> Result<[u8; 20], &'static str> {
+    let digits = input.strip_prefix("0x").unwrap_or(input);
+    let mut address = [0u8; 20];
+    hex::decode_to_slice(digits, &mut address).map_err(|_| "invalid hex address.")?;
+
+    let has_lower = digits.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = digits.chars().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper && to_checksum_address(&address)[2..] != *digits {
+        return Err("invalid address checksum.");
+    }
+    Ok(address)
+}
 
 #[cfg(feature = "std")]
 impl std::fmt::Display for AccountId20 {
-    //TODO This is a pretty quck-n-dirty implementation. Perhaps we should add
-    // checksum casing here? I bet there is a crate for that.
-    // Maybe this one https://github.com/miguelmota/rust-eth-checksum
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.0)
+        write!(f, "{}", to_checksum_address(&self.0))
     }
 }
 
@@ -81,57 +118,156 @@ impl From<AccountId20> for H160 {
 impl std::str::FromStr for AccountId20 {
     type Err = &'static str;
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        H160::from_str(input)
-            .map(Into::into)
-            .map_err(|_| "invalid hex address.")
+        parse_checksum_address(input).map(Self)
     }
 }
 
+/// Serialize and deserialize an address type as its checksummed hex string
+#[cfg(feature = "std")]
+macro_rules! impl_checksum_serde {
+    ($name: ident) => {
+        impl Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.to_string())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let input = String::deserialize(deserializer)?;
+                input.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+impl_checksum_serde!(AccountId20);
+
+/// Signature of a message by an Ethereum account, tagged with the way the wallet hashed the
+/// message before signing it
 #[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Eq, PartialEq, Clone, Encode, Decode, sp_core::RuntimeDebug, TypeInfo)]
-pub struct EthereumSignature(ecdsa::Signature);
+pub enum EthereumSignature {
+    /// Signature of the keccak-256 hash of the message, as made by Substrate tooling
+    Raw(ecdsa::Signature),
+    /// `personal_sign` of the message, see `personal_sign_hash`
+    PersonalSign(ecdsa::Signature),
+    /// `eth_signTypedData_v4` of the message, see `eip712_hash`
+    Eip712(ecdsa::Signature),
+}
 
 impl From<ecdsa::Signature> for EthereumSignature {
     fn from(x: ecdsa::Signature) -> Self {
-        EthereumSignature(x)
+        EthereumSignature::Raw(x)
     }
 }
 
-impl sp_runtime::traits::Verify for EthereumSignature {
-    type Signer = EthereumSigner;
-    fn verify<L: sp_runtime::traits::Lazy<[u8]>>(&self, mut msg: L, signer: &AccountId20) -> bool {
-        let mut m = [0u8; 32];
-        m.copy_from_slice(Keccak256::digest(msg.get()).as_slice());
-        match sp_io::crypto::secp256k1_ecdsa_recover(self.0.as_ref(), &m) {
-            Ok(pubkey) => {
-                // TODO This conversion could use a comment. Why H256 first, then H160?
-                // TODO actually, there is probably just a better way to go from Keccak digest.
-                AccountId20(H160::from(H256::from_slice(Keccak256::digest(pubkey).as_slice())).0)
-                    == *signer
-            }
+/// Name in the EIP-712 domain of signed Ferrum transactions
+pub const EIP712_DOMAIN_NAME: &[u8] = b"Ferrum Network";
+/// Version in the EIP-712 domain of signed Ferrum transactions
+pub const EIP712_DOMAIN_VERSION: &[u8] = b"1";
+/// EVM chain id of the Ferrum network, the chain id of the EIP-712 domain of signed transactions.
+/// Wallets only sign typed data for the chain they are connected to.
+pub const EVM_CHAIN_ID: u64 = 26000;
+const EIP712_DOMAIN_TYPE: &[u8] = b"EIP712Domain(string name,string version,uint256 chainId)";
+const EIP712_TRANSACTION_TYPE: &[u8] = b"FerrumTransaction(bytes payload)";
+
+fn keccak_256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+/// Hash signed by `personal_sign` (EIP-191 version 0x45) for a message
+pub fn personal_sign_hash(message: &[u8]) -> [u8; 32] {
+    let mut len = message.len();
+    let mut digits = sp_std::vec![b'0' + (len % 10) as u8];
+    while len >= 10 {
+        len /= 10;
+        digits.push(b'0' + (len % 10) as u8);
+    }
+    digits.reverse();
+
+    let mut prefixed = b"\x19Ethereum Signed Message:\n".to_vec();
+    prefixed.extend(digits);
+    prefixed.extend(message);
+    keccak_256(&prefixed)
+}
+
+/// Hash signed by `eth_signTypedData_v4` for the typed data
+/// `FerrumTransaction { bytes payload }` in the `Ferrum Network` version `1` domain on
+/// `EVM_CHAIN_ID`. The message is a single opaque field, so the wallet shows it as hex rather
+/// than the fields it encodes
+pub fn eip712_hash(message: &[u8]) -> [u8; 32] {
+    eip712_hash_on_chain(EVM_CHAIN_ID, message)
+}
+
+fn eip712_hash_on_chain(chain_id: u64, message: &[u8]) -> [u8; 32] {
+    let mut domain = keccak_256(EIP712_DOMAIN_TYPE).to_vec();
+    domain.extend(keccak_256(EIP712_DOMAIN_NAME));
+    domain.extend(keccak_256(EIP712_DOMAIN_VERSION));
+    let mut chain_id_word = [0u8; 32];
+    chain_id_word[24..].copy_from_slice(&chain_id.to_be_bytes());
+    domain.extend(chain_id_word);
+
+    let mut transaction = keccak_256(EIP712_TRANSACTION_TYPE).to_vec();
+    transaction.extend(keccak_256(message));
+
+    let mut digest = b"\x19\x01".to_vec();
+    digest.extend(keccak_256(&domain));
+    digest.extend(keccak_256(&transaction));
+    keccak_256(&digest)
+}
+
+impl EthereumSignature {
+    /// Account that signed `message`, hashed the way the variant tells
+    fn recover_signer(&self, message: &[u8]) -> Option<AccountId20> {
+        let (signature, hash) = match self {
+            EthereumSignature::Raw(signature) => (signature, keccak_256(message)),
+            EthereumSignature::PersonalSign(signature) => (signature, personal_sign_hash(message)),
+            EthereumSignature::Eip712(signature) => (signature, eip712_hash(message)),
+        };
+        match sp_io::crypto::secp256k1_ecdsa_recover(signature.as_ref(), &hash) {
+            // The address is the last 20 bytes of the keccak hash of the public key
+            Ok(pubkey) => Some(AccountId20(
+                H160::from(H256::from_slice(Keccak256::digest(pubkey).as_slice())).0,
+            )),
             Err(sp_io::EcdsaVerifyError::BadRS) => {
                 log::error!(target: "evm", "Error recovering: Incorrect value of R or S");
-                false
+                None
             }
             Err(sp_io::EcdsaVerifyError::BadV) => {
                 log::error!(target: "evm", "Error recovering: Incorrect value of V");
-                false
+                None
             }
             Err(sp_io::EcdsaVerifyError::BadSignature) => {
                 log::error!(target: "evm", "Error recovering: Invalid signature");
-                false
+                None
             }
         }
     }
 }
 
+/// Accepts a signature of the message made the way its variant tells, with a single
+/// `secp256k1_ecdsa_recover`.
+///
+/// For an extrinsic the message is its signing payload: the SCALE encoded call, signed extra
+/// and additional signed data, replaced by its blake2-256 hash when longer than 256 bytes.
+impl sp_runtime::traits::Verify for EthereumSignature {
+    type Signer = EthereumSigner;
+    fn verify<L: sp_runtime::traits::Lazy<[u8]>>(&self, mut msg: L, signer: &AccountId20) -> bool {
+        self.recover_signer(msg.get()) == Some(*signer)
+    }
+}
+
 /// Public key for an Ethereum compatible account
 #[derive(
     Eq, PartialEq, Ord, PartialOrd, Clone, Encode, Decode, sp_core::RuntimeDebug, TypeInfo,
 )]
-#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
 pub struct EthereumSigner([u8; 20]);
 
+#[cfg(feature = "std")]
+impl_checksum_serde!(EthereumSigner);
+
 impl sp_runtime::traits::IdentifyAccount for EthereumSigner {
     type AccountId = AccountId20;
     fn into_account(self) -> AccountId20 {
@@ -172,7 +308,15 @@ impl From<libsecp256k1::PublicKey> for EthereumSigner {
 #[cfg(feature = "std")]
 impl std::fmt::Display for EthereumSigner {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(fmt, "ethereum signature: {:?}", H160::from_slice(&self.0))
+        write!(fmt, "{}", to_checksum_address(&self.0))
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::str::FromStr for EthereumSigner {
+    type Err = &'static str;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        parse_checksum_address(input).map(Self)
     }
 }
 
@@ -180,7 +324,8 @@ impl std::fmt::Display for EthereumSigner {
 mod tests {
     use super::*;
     use sp_core::{ecdsa, Pair};
-    use sp_runtime::traits::IdentifyAccount;
+    use sp_runtime::traits::{IdentifyAccount, Verify};
+    use std::str::FromStr;
 
     #[test]
     fn test_account_derivation_1() {
@@ -218,4 +363,137 @@ mod tests {
         let expected_account = AccountId20::from(expected_hex_account);
         assert_eq!(account.into_account(), expected_account);
     }
+
+    fn signer_pair() -> (ecdsa::Pair, AccountId20) {
+        let pair = ecdsa::Pair::from_seed(&[7u8; 32]);
+        let account = EthereumSigner::from(pair.public()).into_account();
+        (pair, account)
+    }
+
+    #[test]
+    fn verifies_raw_personal_sign_and_eip712_signatures() {
+        let (pair, account) = signer_pair();
+        let message = b"signing payload";
+        let signatures = [
+            EthereumSignature::Raw(pair.sign_prehashed(&keccak_256(message))),
+            EthereumSignature::PersonalSign(pair.sign_prehashed(&personal_sign_hash(message))),
+            EthereumSignature::Eip712(pair.sign_prehashed(&eip712_hash(message))),
+        ];
+        for signature in signatures {
+            assert!(signature.verify(&message[..], &account));
+            assert!(!signature.verify(&b"other payload"[..], &account));
+        }
+    }
+
+    #[test]
+    fn a_signature_only_verifies_as_its_own_scheme() {
+        let (pair, account) = signer_pair();
+        let message = b"signing payload";
+        let signature = pair.sign_prehashed(&personal_sign_hash(message));
+        assert!(!EthereumSignature::Raw(signature.clone()).verify(&message[..], &account));
+        assert!(!EthereumSignature::Eip712(signature).verify(&message[..], &account));
+    }
+
+    #[test]
+    fn rejects_eip712_signature_for_another_chain() {
+        let (pair, account) = signer_pair();
+        let message = b"signing payload";
+        let hash = eip712_hash_on_chain(EVM_CHAIN_ID + 1, message);
+        let signature = EthereumSignature::Eip712(pair.sign_prehashed(&hash));
+        assert!(!signature.verify(&message[..], &account));
+    }
+
+    #[test]
+    fn rejects_signature_of_another_account() {
+        let (pair, _) = signer_pair();
+        let other =
+            EthereumSigner::from(ecdsa::Pair::from_seed(&[8u8; 32]).public()).into_account();
+        let signature =
+            EthereumSignature::PersonalSign(pair.sign_prehashed(&personal_sign_hash(b"msg")));
+        assert!(!signature.verify(&b"msg"[..], &other));
+    }
+
+    #[test]
+    fn personal_sign_hash_matches_eth_accounts_hash_message() {
+        // web3.eth.accounts.hashMessage("Hello World")
+        assert_eq!(
+            personal_sign_hash(b"Hello World"),
+            hex_literal("a1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2")
+        );
+    }
+
+    fn hex_literal(input: &str) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        hex::decode_to_slice(input, &mut out).unwrap();
+        out
+    }
+
+    // Test vectors from https://eips.ethereum.org/EIPS/eip-55
+    const CHECKSUMMED: [&str; 4] = [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn displays_checksummed_address() {
+        for address in CHECKSUMMED {
+            let lower = address.to_lowercase();
+            let account = AccountId20::from_str(&lower).unwrap();
+            assert_eq!(account.to_string(), address);
+            let signer = EthereumSigner::from_str(&lower).unwrap();
+            assert_eq!(signer.to_string(), address);
+        }
+    }
+
+    #[test]
+    fn parses_unchecksummed_and_checksummed_addresses() {
+        for address in CHECKSUMMED {
+            let account = AccountId20::from_str(address).unwrap();
+            assert_eq!(AccountId20::from_str(&address.to_lowercase()), Ok(account));
+            assert_eq!(
+                AccountId20::from_str(&address[2..].to_uppercase()),
+                Ok(account)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_checksum() {
+        // Last letter lower cased
+        assert_eq!(
+            AccountId20::from_str("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"),
+            Err("invalid address checksum.")
+        );
+        assert_eq!(
+            EthereumSigner::from_str("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"),
+            Err("invalid address checksum.")
+        );
+        assert_eq!(
+            AccountId20::from_str("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA"),
+            Err("invalid hex address.")
+        );
+    }
+
+    #[test]
+    fn serde_uses_checksummed_address() {
+        let account = AccountId20::from_str(CHECKSUMMED[0]).unwrap();
+        let json = serde_json::to_string(&account).unwrap();
+        assert_eq!(json, format!("\"{}\"", CHECKSUMMED[0]));
+        assert_eq!(serde_json::from_str::<AccountId20>(&json).unwrap(), account);
+
+        let signer = EthereumSigner::from_str(CHECKSUMMED[1]).unwrap();
+        let json = serde_json::to_string(&signer).unwrap();
+        assert_eq!(json, format!("\"{}\"", CHECKSUMMED[1]));
+        assert_eq!(
+            serde_json::from_str::<EthereumSigner>(&json).unwrap(),
+            signer
+        );
+
+        assert!(serde_json::from_str::<AccountId20>(
+            "\"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD\""
+        )
+        .is_err());
+    }
 }
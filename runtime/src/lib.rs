@@ -44,7 +44,7 @@ pub use frame_support::{
         constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
         ConstantMultiplier, IdentityFee, Weight,
     },
-    ConsensusEngineId, StorageValue,
+    ConsensusEngineId, PalletId, StorageValue,
 };
 pub use pallet_balances::Call as BalancesCall;
 use pallet_ethereum::{Call::transact, Transaction as EthereumTransaction};
@@ -57,6 +57,8 @@ pub use sp_runtime::{Perbill, Permill};
 
 mod precompiles;
 use precompiles::FrontierPrecompiles;
+mod qp_native_evm;
+use qp_native_evm::QpNativeEvm;
 
 /// Type of block number.
 pub type BlockNumber = u32;
@@ -310,8 +312,49 @@ impl<T: From<H160>> pallet_evm::AddressMapping<T> for IntoAddressMapping {
     }
 }
 
+/// Calls EVM accounts may dispatch through the dispatch and batch precompiles.
+/// Calls re-entering the EVM are never allowed, including when wrapped in sudo calls.
+pub struct EvmDispatchAllowList;
+
+impl EvmDispatchAllowList {
+    /// Whether the call re-enters the EVM, looking through any number of nested sudo calls.
+    /// Any call wrapping other calls added to the runtime, e.g. utility batches, must be
+    /// unwrapped here as well.
+    fn reenters_evm(call: &RuntimeCall) -> bool {
+        match call {
+            RuntimeCall::EVM(_) | RuntimeCall::Ethereum(_) => true,
+            RuntimeCall::Sudo(
+                pallet_sudo::Call::sudo { call }
+                | pallet_sudo::Call::sudo_unchecked_weight { call, .. }
+                | pallet_sudo::Call::sudo_as { call, .. },
+            ) => Self::reenters_evm(call),
+            _ => false,
+        }
+    }
+}
+
+impl frame_support::traits::Contains<RuntimeCall> for EvmDispatchAllowList {
+    fn contains(call: &RuntimeCall) -> bool {
+        match call {
+            RuntimeCall::Balances(
+                pallet_balances::Call::transfer { .. }
+                | pallet_balances::Call::transfer_keep_alive { .. }
+                | pallet_balances::Call::transfer_all { .. },
+            ) => true,
+            RuntimeCall::QuantumPortal(_) => true,
+            RuntimeCall::Sudo(
+                pallet_sudo::Call::sudo { call }
+                | pallet_sudo::Call::sudo_unchecked_weight { call, .. }
+                | pallet_sudo::Call::sudo_as { call, .. },
+            ) => !Self::reenters_evm(call),
+            RuntimeCall::Sudo(pallet_sudo::Call::set_key { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
 parameter_types! {
-    pub const ChainId: u64 = 26000;
+    pub const ChainId: u64 = ferrum_primitives::EVM_CHAIN_ID;
     pub BlockGasLimit: U256 = U256::from(u32::max_value());
     pub PrecompilesValue: FrontierPrecompiles<Runtime> = FrontierPrecompiles::<_>::new();
     pub const WeightPerGas: Weight = Weight::from_ref_time(20_000);
@@ -377,11 +420,39 @@ impl pallet_base_fee::Config for Runtime {
 
 impl pallet_randomness_collective_flip::Config for Runtime {}
 
+parameter_types! {
+    pub const QpMinerBond: Balance = 1_000_000_000_000_000;
+    pub const QpFinalizerBond: Balance = 1_000_000_000_000_000;
+    pub const QpUnbondingDelay: BlockNumber = 7 * DAYS;
+    pub const QpMaxFinalizers: u32 = 100;
+    pub const QpFinalizeQuorum: Perbill = Perbill::from_percent(67);
+    pub const QpFraudProofQuorum: Perbill = Perbill::from_percent(67);
+    pub const QpFraudSlash: Perbill = Perbill::from_percent(100);
+    pub const QpPalletId: PalletId = PalletId(*b"ferqprwd");
+    pub const QpEraLength: BlockNumber = DAYS;
+    pub const QpRewardPerEra: Balance = 100_000_000_000_000;
+}
+
 impl pallet_quantum_portal::Config for Runtime {
     type RuntimeCall = RuntimeCall;
     type RuntimeEvent = RuntimeEvent;
     type PalletRandomness = RandomnessCollectiveFlip;
     type Timestamp = Timestamp;
+    type Currency = Balances;
+    type MinerBond = QpMinerBond;
+    type FinalizerBond = QpFinalizerBond;
+    type UnbondingDelay = QpUnbondingDelay;
+    type SlashOrigin = frame_system::EnsureRoot<AccountId>;
+    type Slash = ();
+    type MaxFinalizers = QpMaxFinalizers;
+    type FinalizeQuorum = QpFinalizeQuorum;
+    type FraudProofQuorum = QpFraudProofQuorum;
+    type FraudSlash = QpFraudSlash;
+    type PalletId = QpPalletId;
+    type EraLength = QpEraLength;
+    type RewardPerEra = QpRewardPerEra;
+    type WeightInfo = pallet_quantum_portal::weights::SubstrateWeight<Runtime>;
+    type NativeEvm = QpNativeEvm;
 }
 
 impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Runtime
@@ -453,7 +524,7 @@ construct_runtime!(
         EVM: pallet_evm::{Pallet, Config, Call, Storage, Event<T>},
         DynamicFee: pallet_dynamic_fee::{Pallet, Call, Storage, Config, Inherent},
         BaseFee: pallet_base_fee::{Pallet, Call, Storage, Config<T>, Event},
-        QuantumPortal: pallet_quantum_portal::{Pallet, Call, Storage, Event<T>/*, ValidateUnsigned*/},
+        QuantumPortal: pallet_quantum_portal::{Pallet, Call, Storage, Event<T>, ValidateUnsigned},
     }
 );
 
@@ -517,8 +588,12 @@ pub type Executive = frame_executive::Executive<
     frame_system::ChainContext<Runtime>,
     Runtime,
     AllPalletsWithSystem,
+    Migrations,
 >;
 
+/// Storage migrations run on the next runtime upgrade
+pub type Migrations = (pallet_quantum_portal::migrations::v1::MigrateToV1<Runtime>,);
+
 impl fp_self_contained::SelfContainedCall for RuntimeCall {
     type SignedInfo = H160;
 
@@ -651,6 +726,30 @@ impl_runtime_apis! {
         }
     }
 
+    impl pallet_quantum_portal_runtime_api::QuantumPortalApi<Block, AccountId, BlockNumber> for Runtime {
+        fn networks() -> Vec<(u64, H160)> {
+            QuantumPortal::networks()
+        }
+
+        fn pairs() -> Vec<pallet_quantum_portal::qp_types::QpPairInfo> {
+            QuantumPortal::pairs()
+        }
+
+        fn miners() -> Vec<AccountId> {
+            QuantumPortal::miners()
+        }
+
+        fn finalizers() -> Vec<AccountId> {
+            QuantumPortal::finalizers()
+        }
+
+        fn recent_records(
+            limit: u32,
+        ) -> Vec<pallet_quantum_portal::qp_types::QpBlockRecord<AccountId, BlockNumber>> {
+            QuantumPortal::recent_records(limit)
+        }
+    }
+
     impl fp_rpc::EthereumRuntimeRPCApi<Block> for Runtime {
         fn chain_id() -> u64 {
             <Runtime as pallet_evm::Config>::ChainId::get()
@@ -872,9 +971,26 @@ impl_runtime_apis! {
             let params = (&config, &whitelist);
 
             add_benchmark!(params, batches, pallet_evm, PalletEvmBench::<Runtime>);
+            add_benchmark!(params, batches, pallet_quantum_portal, QuantumPortal);
 
             if batches.is_empty() { return Err("Benchmark not found for this pallet.".into()) }
             Ok(batches)
         }
     }
+    #[cfg(feature = "try-runtime")]
+    impl frame_try_runtime::TryRuntime<Block> for Runtime {
+        fn on_runtime_upgrade() -> (Weight, Weight) {
+            // Panic so the try-runtime cli reports a failing migration
+            let weight = Executive::try_runtime_upgrade().unwrap();
+            (weight, BlockWeights::get().max_block)
+        }
+
+        fn execute_block(
+            block: Block,
+            state_root_check: bool,
+            select: frame_try_runtime::TryStateSelect
+        ) -> Weight {
+            Executive::try_execute_block(block, state_root_check, select).expect("execute-block failed")
+        }
+    }
 }
@@ -1,22 +1,39 @@
+use codec::Decode;
+use frame_support::{
+    dispatch::{GetDispatchInfo, PostDispatchInfo},
+    traits::Contains,
+};
 use pallet_evm::{Precompile, PrecompileHandle, PrecompileResult, PrecompileSet};
 use sp_core::H160;
+use sp_runtime::traits::Dispatchable;
 use sp_std::marker::PhantomData;
 
 use pallet_evm_precompile_modexp::Modexp;
 use pallet_evm_precompile_sha3fips::Sha3FIPS256;
 use pallet_evm_precompile_simple::{ECRecover, ECRecoverPublicKey, Identity, Ripemd160, Sha256};
 
+mod dispatch;
+mod quantum_portal;
+use dispatch::{BatchPrecompile, CallOf, DispatchPrecompile};
+use quantum_portal::QuantumPortalPrecompile;
+
+use crate::EvmDispatchAllowList;
+
 pub struct FrontierPrecompiles<R>(PhantomData<R>);
 
 impl<R> FrontierPrecompiles<R>
 where
-    R: pallet_evm::Config,
+    R: pallet_evm::Config + pallet_quantum_portal::Config,
+    R::AccountId: From<H160> + Into<H160>,
+    CallOf<R>: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo + Decode,
+    <CallOf<R> as Dispatchable>::RuntimeOrigin: From<Option<R::AccountId>>,
+    EvmDispatchAllowList: Contains<CallOf<R>>,
 {
     pub fn new() -> Self {
         Self(Default::default())
     }
     pub fn used_addresses() -> sp_std::vec::Vec<H160> {
-        sp_std::vec![1, 2, 3, 4, 5, 1024, 1025]
+        sp_std::vec![1, 2, 3, 4, 5, 1024, 1025, 2048, 2049, 2050]
             .into_iter()
             .map(hash)
             .collect()
@@ -24,7 +41,11 @@ where
 }
 impl<R> PrecompileSet for FrontierPrecompiles<R>
 where
-    R: pallet_evm::Config,
+    R: pallet_evm::Config + pallet_quantum_portal::Config,
+    R::AccountId: From<H160> + Into<H160>,
+    CallOf<R>: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo + Decode,
+    <CallOf<R> as Dispatchable>::RuntimeOrigin: From<Option<R::AccountId>>,
+    EvmDispatchAllowList: Contains<CallOf<R>>,
 {
     fn execute(&self, handle: &mut impl PrecompileHandle) -> Option<PrecompileResult> {
         match handle.code_address() {
@@ -37,6 +58,14 @@ where
             // Non-Frontier specific nor Ethereum precompiles :
             a if a == hash(1024) => Some(Sha3FIPS256::execute(handle)),
             a if a == hash(1025) => Some(ECRecoverPublicKey::execute(handle)),
+            // Ferrum specific precompiles :
+            a if a == hash(2048) => Some(QuantumPortalPrecompile::<R>::execute(handle)),
+            a if a == hash(2049) => Some(DispatchPrecompile::<R, EvmDispatchAllowList>::execute(
+                handle,
+            )),
+            a if a == hash(2050) => {
+                Some(BatchPrecompile::<R, EvmDispatchAllowList>::execute(handle))
+            }
             _ => None,
         }
     }
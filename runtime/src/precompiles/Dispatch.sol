@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: MIT
+pragma solidity >=0.8.0;
+
+/// @dev Dispatch Substrate calls with the caller as signed origin
+/// Dispatch precompile address: 0x0000000000000000000000000000000000000801
+/// Takes a SCALE encoded call as raw input, e.g. `address(0x801).call(encodedCall)`
+///
+/// Batch precompile address: 0x0000000000000000000000000000000000000802
+interface Batch {
+    /// Dispatch SCALE encoded calls in order, reverting all of them if one fails
+    function batchAll(bytes[] calldata calls) external;
+}
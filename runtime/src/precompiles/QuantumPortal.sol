@@ -0,0 +1,29 @@
+// SPDX-License-Identifier: MIT
+pragma solidity >=0.8.0;
+
+/// @dev Read only view of the Quantum Portal pallet state
+/// Precompile address: 0x0000000000000000000000000000000000000800
+/// The nonces are the ones the workers reported to the pallet, not read from the ledger managers.
+/// They are 0 for a pair until its first block was reported.
+interface QuantumPortal {
+    /// Chain ids of the registered networks
+    function networks() external view returns (uint256[] memory);
+
+    /// Ledger manager contract of a registered network, zero if the network is not registered
+    function ledgerManager(uint256 chainId) external view returns (address);
+
+    /// Highest source block nonce reported mined on `localChain` for `remoteChain`. Unverified: it is
+    /// raised by the miners' own reports, only `lastFinalizedNonce` is confirmed by the finalizers.
+    function lastMinedNonce(uint256 remoteChain, uint256 localChain) external view returns (uint256);
+
+    /// Last block nonce a quorum of the finalizers confirmed finalized on `localChain` for `remoteChain`
+    function lastFinalizedNonce(uint256 remoteChain, uint256 localChain) external view returns (uint256);
+
+    function isMiner(address account) external view returns (bool);
+
+    function isFinalizer(address account) external view returns (bool);
+
+    function miners() external view returns (address[] memory);
+
+    function finalizers() external view returns (address[] memory);
+}
@@ -0,0 +1,370 @@
+//! Precompiles letting EVM accounts dispatch Substrate calls, so `AccountId20` users can take part in
+//! portal governance and staking from an Ethereum wallet.
+//! Only calls accepted by the runtime allow-list `F` can be dispatched, the call weight is charged as gas.
+
+use codec::{Decode, DecodeLimit};
+use ethabi_nostd::{decoder::decode, ParamKind, Token};
+use frame_support::{
+    dispatch::{DispatchClass, GetDispatchInfo, Pays, PostDispatchInfo},
+    storage::{with_transaction, TransactionOutcome},
+    traits::Contains,
+};
+use pallet_evm::{
+    AddressMapping, ExitSucceed, GasWeightMapping, PrecompileFailure, PrecompileHandle,
+    PrecompileOutput, PrecompileResult,
+};
+use sp_runtime::{traits::Dispatchable, DispatchError};
+use sp_std::{marker::PhantomData, prelude::*};
+
+use super::quantum_portal::revert;
+
+/// Maximum nesting of a decoded call, matches the limit used for extrinsics
+const MAX_CALL_DEPTH: u32 = 256;
+
+/// Maximum number of calls in a batch
+const MAX_BATCH_CALLS: usize = 64;
+
+// Selector of `batchAll(bytes[])`
+const SELECTOR_BATCH_ALL: [u8; 4] = [0x2f, 0x51, 0xe3, 0x29];
+
+pub type CallOf<R> = <R as frame_system::Config>::RuntimeCall;
+
+/// Dispatch a single SCALE encoded call with the caller as signed origin.
+/// The input is the encoded call itself, as with Frontier's dispatch precompile.
+pub struct DispatchPrecompile<R, F>(PhantomData<(R, F)>);
+
+impl<R, F> DispatchPrecompile<R, F>
+where
+    R: pallet_evm::Config,
+    CallOf<R>: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo + Decode,
+    <CallOf<R> as Dispatchable>::RuntimeOrigin: From<Option<R::AccountId>>,
+    F: Contains<CallOf<R>>,
+{
+    pub fn execute(handle: &mut impl PrecompileHandle) -> PrecompileResult {
+        ensure_direct_call(handle)?;
+        let call = decode_call::<R, F>(handle.input())?;
+        dispatch_call::<R>(handle, call).map_err(|e| revert(e.reason()))?;
+
+        Ok(PrecompileOutput {
+            exit_status: ExitSucceed::Stopped,
+            output: Default::default(),
+        })
+    }
+}
+
+/// Dispatch several SCALE encoded calls atomically through `batchAll(bytes[] calls)`.
+/// If any call fails, the changes made by the previous calls are reverted.
+pub struct BatchPrecompile<R, F>(PhantomData<(R, F)>);
+
+impl<R, F> BatchPrecompile<R, F>
+where
+    R: pallet_evm::Config,
+    CallOf<R>: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo + Decode,
+    <CallOf<R> as Dispatchable>::RuntimeOrigin: From<Option<R::AccountId>>,
+    F: Contains<CallOf<R>>,
+{
+    pub fn execute(handle: &mut impl PrecompileHandle) -> PrecompileResult {
+        ensure_direct_call(handle)?;
+        let input = handle.input().to_vec();
+        if input.len() < 4 || input[..4] != SELECTOR_BATCH_ALL {
+            return Err(revert("unknown selector"));
+        }
+
+        let encoded_calls = decode(&[ParamKind::Array(Box::new(ParamKind::Bytes))], &input[4..])
+            .ok()
+            .and_then(|tokens| tokens.into_iter().next())
+            .and_then(|t| t.to_array())
+            .ok_or_else(|| revert("invalid arguments"))?;
+        if encoded_calls.len() > MAX_BATCH_CALLS {
+            return Err(revert("too many calls"));
+        }
+        // Decode and check every call before dispatching any of them
+        let calls = encoded_calls
+            .into_iter()
+            .map(|t| match t {
+                Token::Bytes(bytes) => decode_call::<R, F>(&bytes),
+                _ => Err(revert("invalid arguments")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut failed = None;
+        with_transaction(|| {
+            for (index, call) in calls.into_iter().enumerate() {
+                if let Err(e) = dispatch_call::<R>(handle, call) {
+                    failed = Some((index, e));
+                    return TransactionOutcome::Rollback(Err(DispatchError::Other("batch failed")));
+                }
+            }
+            TransactionOutcome::Commit(Ok(()))
+        })
+        .map_err(|_| match failed.take() {
+            Some((index, e)) => {
+                let mut reason = b"call ".to_vec();
+                reason.extend(decimal(index));
+                reason.extend(b": ");
+                reason.extend(e.reason());
+                revert(reason)
+            }
+            None => revert("batch failed"),
+        })?;
+
+        Ok(PrecompileOutput {
+            exit_status: ExitSucceed::Stopped,
+            output: Default::default(),
+        })
+    }
+}
+
+/// Only dispatch for a direct caller. Through DELEGATECALL or CALLCODE the precompile would run
+/// in the context of the calling contract, which could dispatch calls on behalf of whoever
+/// called that contract. Static calls cannot change state.
+fn ensure_direct_call(handle: &impl PrecompileHandle) -> Result<(), PrecompileFailure> {
+    if handle.code_address() != handle.context().address {
+        return Err(revert("cannot dispatch in a delegate call"));
+    }
+    if handle.is_static() {
+        return Err(revert("cannot dispatch in a static call"));
+    }
+    Ok(())
+}
+
+/// Why a call could not be dispatched
+enum CallError {
+    /// Not enough gas left for the call weight
+    OutOfGas,
+    /// The call itself failed
+    Dispatch(DispatchError),
+}
+
+impl CallError {
+    fn reason(self) -> Vec<u8> {
+        match self {
+            CallError::OutOfGas => b"out of gas".to_vec(),
+            CallError::Dispatch(e) => {
+                let mut reason = b"dispatch failed: ".to_vec();
+                reason.extend(<&'static str>::from(e).as_bytes());
+                reason
+            }
+        }
+    }
+}
+
+fn decimal(mut n: usize) -> Vec<u8> {
+    let mut digits = vec![b'0' + (n % 10) as u8];
+    while n >= 10 {
+        n /= 10;
+        digits.push(b'0' + (n % 10) as u8);
+    }
+    digits.reverse();
+    digits
+}
+
+/// Decode a call and make sure EVM accounts are allowed to dispatch it
+fn decode_call<R, F>(input: &[u8]) -> Result<CallOf<R>, PrecompileFailure>
+where
+    R: pallet_evm::Config,
+    CallOf<R>: GetDispatchInfo + Decode,
+    F: Contains<CallOf<R>>,
+{
+    let call = CallOf::<R>::decode_with_depth_limit(MAX_CALL_DEPTH, &mut &*input)
+        .map_err(|_| revert("could not decode call"))?;
+    if !F::contains(&call) {
+        return Err(revert("call not allowed"));
+    }
+    let info = call.get_dispatch_info();
+    if info.pays_fee != Pays::Yes || info.class != DispatchClass::Normal {
+        return Err(revert("call not allowed"));
+    }
+    Ok(call)
+}
+
+/// Dispatch a call with the caller as origin. The declared weight must fit in the remaining gas,
+/// the actual weight reported by the call is charged whether it succeeds or not.
+fn dispatch_call<R>(handle: &mut impl PrecompileHandle, call: CallOf<R>) -> Result<(), CallError>
+where
+    R: pallet_evm::Config,
+    CallOf<R>: Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
+    <CallOf<R> as Dispatchable>::RuntimeOrigin: From<Option<R::AccountId>>,
+{
+    let info = call.get_dispatch_info();
+    if R::GasWeightMapping::weight_to_gas(info.weight) > handle.remaining_gas() {
+        return Err(CallError::OutOfGas);
+    }
+
+    let origin = R::AddressMapping::into_account_id(handle.context().caller);
+    let (post_info, result) = match call.dispatch(Some(origin).into()) {
+        Ok(post_info) => (post_info, Ok(())),
+        Err(e) => (e.post_info, Err(CallError::Dispatch(e.error))),
+    };
+    let cost = R::GasWeightMapping::weight_to_gas(post_info.calc_actual_weight(&info));
+    handle.record_cost(cost).map_err(|_| CallError::OutOfGas)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EvmDispatchAllowList, Runtime, RuntimeCall};
+    use fp_evm::{Context, ExitError, ExitReason, Transfer};
+    use sp_core::{H160, H256, U256};
+
+    const DISPATCH: u64 = 2049;
+    const BATCH: u64 = 2050;
+
+    /// Handle of a precompile call made by `caller` through a contract at `address`
+    struct MockHandle {
+        code_address: H160,
+        context: Context,
+        input: Vec<u8>,
+    }
+
+    impl MockHandle {
+        fn new(precompile: u64, address: H160, input: Vec<u8>) -> Self {
+            MockHandle {
+                code_address: H160::from_low_u64_be(precompile),
+                context: Context {
+                    address,
+                    caller: H160::repeat_byte(0x11),
+                    apparent_value: U256::zero(),
+                },
+                input,
+            }
+        }
+    }
+
+    impl PrecompileHandle for MockHandle {
+        fn call(
+            &mut self,
+            _to: H160,
+            _transfer: Option<Transfer>,
+            _input: Vec<u8>,
+            _gas_limit: Option<u64>,
+            _is_static: bool,
+            _context: &Context,
+        ) -> (ExitReason, Vec<u8>) {
+            // The dispatch precompiles make no subcalls, fail one like the EVM would
+            (
+                ExitReason::Error(ExitError::Other("unexpected subcall".into())),
+                Vec::new(),
+            )
+        }
+
+        fn record_cost(&mut self, _cost: u64) -> Result<(), ExitError> {
+            Ok(())
+        }
+
+        fn remaining_gas(&self) -> u64 {
+            u64::MAX
+        }
+
+        fn log(&mut self, _: H160, _: Vec<H256>, _: Vec<u8>) -> Result<(), ExitError> {
+            Ok(())
+        }
+
+        fn code_address(&self) -> H160 {
+            self.code_address
+        }
+
+        fn input(&self) -> &[u8] {
+            &self.input
+        }
+
+        fn context(&self) -> &Context {
+            &self.context
+        }
+
+        fn is_static(&self) -> bool {
+            false
+        }
+
+        fn gas_limit(&self) -> Option<u64> {
+            None
+        }
+    }
+
+    fn revert_reason(result: PrecompileResult) -> Vec<u8> {
+        match result {
+            Err(PrecompileFailure::Revert { output, .. }) => output,
+            _ => panic!("expected a revert"),
+        }
+    }
+
+    #[test]
+    fn delegate_calls_are_rejected() {
+        // A contract delegatecalling the precompile runs it with its own address as context
+        let contract = H160::repeat_byte(0x22);
+        let mut handle = MockHandle::new(DISPATCH, contract, vec![0; 4]);
+        assert_eq!(
+            revert_reason(
+                DispatchPrecompile::<Runtime, EvmDispatchAllowList>::execute(&mut handle)
+            ),
+            revert_reason(Err(revert("cannot dispatch in a delegate call")))
+        );
+
+        let mut handle = MockHandle::new(BATCH, contract, SELECTOR_BATCH_ALL.to_vec());
+        assert_eq!(
+            revert_reason(BatchPrecompile::<Runtime, EvmDispatchAllowList>::execute(
+                &mut handle
+            )),
+            revert_reason(Err(revert("cannot dispatch in a delegate call")))
+        );
+    }
+
+    #[test]
+    fn direct_calls_reach_the_call_decoding() {
+        let mut handle = MockHandle::new(DISPATCH, H160::from_low_u64_be(DISPATCH), vec![0xff]);
+        assert_eq!(
+            revert_reason(
+                DispatchPrecompile::<Runtime, EvmDispatchAllowList>::execute(&mut handle)
+            ),
+            revert_reason(Err(revert("could not decode call")))
+        );
+    }
+
+    #[test]
+    fn batch_selector_matches_the_interface() {
+        assert_eq!(
+            SELECTOR_BATCH_ALL[..],
+            sp_core::hashing::keccak_256(b"batchAll(bytes[])")[..4]
+        );
+    }
+
+    #[test]
+    fn formats_call_index() {
+        assert_eq!(decimal(0), b"0".to_vec());
+        assert_eq!(decimal(7), b"7".to_vec());
+        assert_eq!(decimal(63), b"63".to_vec());
+        assert_eq!(decimal(120), b"120".to_vec());
+    }
+
+    fn sudo(call: RuntimeCall) -> RuntimeCall {
+        RuntimeCall::Sudo(pallet_sudo::Call::sudo {
+            call: Box::new(call),
+        })
+    }
+
+    #[test]
+    fn sudo_calls_cannot_reenter_the_evm_at_any_depth() {
+        let evm_call = RuntimeCall::EVM(pallet_evm::Call::withdraw {
+            address: H160::repeat_byte(0x11),
+            value: 1,
+        });
+        let transfer = RuntimeCall::Balances(pallet_balances::Call::transfer_all {
+            dest: H160::repeat_byte(0x22).into(),
+            keep_alive: true,
+        });
+
+        assert!(!EvmDispatchAllowList::contains(&evm_call));
+        assert!(!EvmDispatchAllowList::contains(&sudo(evm_call.clone())));
+        assert!(!EvmDispatchAllowList::contains(&sudo(sudo(
+            evm_call.clone()
+        ))));
+        assert!(!EvmDispatchAllowList::contains(&RuntimeCall::Sudo(
+            pallet_sudo::Call::sudo_as {
+                who: H160::repeat_byte(0x33).into(),
+                call: Box::new(sudo(evm_call)),
+            }
+        )));
+        assert!(EvmDispatchAllowList::contains(&sudo(sudo(transfer))));
+    }
+}
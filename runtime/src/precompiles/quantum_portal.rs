@@ -0,0 +1,223 @@
+//! Read only precompile exposing the quantum portal state of `pallet_quantum_portal` to EVM contracts.
+//! The Solidity interface is in `QuantumPortal.sol` next to this file.
+
+use ethabi_nostd::{decoder::decode, encode, ParamKind, Token};
+use frame_support::traits::Get;
+use pallet_evm::{
+    ExitRevert, ExitSucceed, GasWeightMapping, PrecompileFailure, PrecompileHandle,
+    PrecompileOutput, PrecompileResult,
+};
+use sp_core::{H160, U256};
+use sp_std::{marker::PhantomData, prelude::*};
+
+// Selectors of the `QuantumPortal` interface functions
+const SELECTOR_NETWORKS: [u8; 4] = [0x44, 0x3f, 0xb0, 0xd1]; // networks()
+const SELECTOR_LEDGER_MANAGER: [u8; 4] = [0x3b, 0x88, 0xdf, 0x8f]; // ledgerManager(uint256)
+const SELECTOR_LAST_MINED_NONCE: [u8; 4] = [0x80, 0xab, 0x72, 0x13]; // lastMinedNonce(uint256,uint256)
+const SELECTOR_LAST_FINALIZED_NONCE: [u8; 4] = [0x9d, 0x00, 0x7b, 0x3e]; // lastFinalizedNonce(uint256,uint256)
+const SELECTOR_IS_MINER: [u8; 4] = [0x70, 0x1b, 0x70, 0xac]; // isMiner(address)
+const SELECTOR_IS_FINALIZER: [u8; 4] = [0x70, 0xfb, 0xe9, 0xff]; // isFinalizer(address)
+const SELECTOR_MINERS: [u8; 4] = [0x5e, 0xe0, 0xac, 0xa0]; // miners()
+const SELECTOR_FINALIZERS: [u8; 4] = [0xdf, 0x6a, 0xbd, 0x24]; // finalizers()
+
+/// Selector of the Solidity `Error(string)` revert reason
+const SELECTOR_ERROR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+pub struct QuantumPortalPrecompile<R>(PhantomData<R>);
+
+impl<R> QuantumPortalPrecompile<R>
+where
+    R: pallet_evm::Config + pallet_quantum_portal::Config,
+    R::AccountId: From<H160> + Into<H160>,
+{
+    pub fn execute(handle: &mut impl PrecompileHandle) -> PrecompileResult {
+        let input = handle.input().to_vec();
+        if input.len() < 4 {
+            return Err(revert("input too short"));
+        }
+        let (selector, args) = input.split_at(4);
+        let selector: [u8; 4] = selector.try_into().expect("split at 4; qed");
+
+        let output = match selector {
+            SELECTOR_NETWORKS => {
+                let networks = Self::collect_charged(
+                    handle,
+                    pallet_quantum_portal::RegisteredNetworks::<R>::iter_keys(),
+                )?;
+                Token::Array(
+                    networks
+                        .into_iter()
+                        .map(|chain_id| Token::Uint(U256::from(chain_id)))
+                        .collect(),
+                )
+            }
+            SELECTOR_LEDGER_MANAGER => {
+                Self::record_reads(handle, 1)?;
+                let chain_id = decode_u64(args, 0, 1)?;
+                let ledger_manager =
+                    pallet_quantum_portal::Pallet::<R>::registered_networks(chain_id)
+                        .unwrap_or_default();
+                Token::Address(ledger_manager)
+            }
+            // Self-reported by the miners, see `QuantumPortal.sol`
+            SELECTOR_LAST_MINED_NONCE => {
+                Self::record_reads(handle, 1)?;
+                let nonce = pallet_quantum_portal::Pallet::<R>::last_mined_nonce(
+                    decode_u64(args, 0, 2)?,
+                    decode_u64(args, 1, 2)?,
+                );
+                Token::Uint(U256::from(nonce))
+            }
+            SELECTOR_LAST_FINALIZED_NONCE => {
+                Self::record_reads(handle, 1)?;
+                let nonce = pallet_quantum_portal::Pallet::<R>::last_finalized_nonce(
+                    decode_u64(args, 0, 2)?,
+                    decode_u64(args, 1, 2)?,
+                );
+                Token::Uint(U256::from(nonce))
+            }
+            SELECTOR_IS_MINER => {
+                Self::record_reads(handle, 1)?;
+                let who: R::AccountId = decode_address(args)?.into();
+                Token::Bool(pallet_quantum_portal::Pallet::<R>::is_miner(&who))
+            }
+            SELECTOR_IS_FINALIZER => {
+                Self::record_reads(handle, 1)?;
+                let who: R::AccountId = decode_address(args)?.into();
+                Token::Bool(pallet_quantum_portal::Pallet::<R>::is_finalizer(&who))
+            }
+            SELECTOR_MINERS => address_array(Self::collect_charged(
+                handle,
+                pallet_quantum_portal::Miners::<R>::iter_keys(),
+            )?),
+            SELECTOR_FINALIZERS => address_array(Self::collect_charged(
+                handle,
+                pallet_quantum_portal::Finalizers::<R>::iter_keys(),
+            )?),
+            _ => return Err(revert("unknown selector")),
+        };
+
+        Ok(PrecompileOutput {
+            exit_status: ExitSucceed::Returned,
+            output: encode(&[output]),
+        })
+    }
+
+    /// Charge the gas equivalent of `reads` storage reads
+    fn record_reads(
+        handle: &mut impl PrecompileHandle,
+        reads: u64,
+    ) -> Result<(), PrecompileFailure> {
+        let weight = <R as frame_system::Config>::DbWeight::get().reads(reads);
+        handle.record_cost(<R as pallet_evm::Config>::GasWeightMapping::weight_to_gas(
+            weight,
+        ))?;
+        Ok(())
+    }
+
+    /// Collect the keys of a storage map, charging each read before it is made so a call
+    /// running out of gas stops iterating instead of reading the whole map
+    fn collect_charged<K>(
+        handle: &mut impl PrecompileHandle,
+        mut keys: impl Iterator<Item = K>,
+    ) -> Result<Vec<K>, PrecompileFailure> {
+        let mut collected = Vec::new();
+        loop {
+            // The last read finds no more keys
+            Self::record_reads(handle, 1)?;
+            match keys.next() {
+                Some(key) => collected.push(key),
+                None => return Ok(collected),
+            }
+        }
+    }
+}
+
+fn address_array<A: Into<H160>>(accounts: Vec<A>) -> Token {
+    Token::Array(
+        accounts
+            .into_iter()
+            .map(|a| Token::Address(a.into()))
+            .collect(),
+    )
+}
+
+/// Decode the `index`th of `count` uint256 arguments, which must fit in a u64
+fn decode_u64(args: &[u8], index: usize, count: usize) -> Result<u64, PrecompileFailure> {
+    let kinds = vec![ParamKind::Uint(256); count];
+    let tokens = decode(&kinds, args).map_err(|_| revert("invalid arguments"))?;
+    let value = tokens
+        .into_iter()
+        .nth(index)
+        .and_then(|t| t.to_uint())
+        .ok_or_else(|| revert("invalid arguments"))?;
+    if value > U256::from(u64::MAX) {
+        return Err(revert("argument out of range"));
+    }
+    Ok(value.as_u64())
+}
+
+fn decode_address(args: &[u8]) -> Result<H160, PrecompileFailure> {
+    decode(&[ParamKind::Address], args)
+        .ok()
+        .and_then(|tokens| tokens.into_iter().next())
+        .and_then(|t| t.to_address())
+        .ok_or_else(|| revert("invalid arguments"))
+}
+
+/// Revert with a Solidity `Error(string)` reason
+pub(crate) fn revert(reason: impl AsRef<[u8]>) -> PrecompileFailure {
+    let mut output = SELECTOR_ERROR.to_vec();
+    output.extend(encode(&[Token::String(reason.as_ref().to_vec())]));
+    PrecompileFailure::Revert {
+        exit_status: ExitRevert::Reverted,
+        output,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selector(signature: &str) -> [u8; 4] {
+        sp_core::hashing::keccak_256(signature.as_bytes())[..4]
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn selectors_match_the_interface() {
+        assert_eq!(SELECTOR_NETWORKS, selector("networks()"));
+        assert_eq!(SELECTOR_LEDGER_MANAGER, selector("ledgerManager(uint256)"));
+        assert_eq!(
+            SELECTOR_LAST_MINED_NONCE,
+            selector("lastMinedNonce(uint256,uint256)")
+        );
+        assert_eq!(
+            SELECTOR_LAST_FINALIZED_NONCE,
+            selector("lastFinalizedNonce(uint256,uint256)")
+        );
+        assert_eq!(SELECTOR_IS_MINER, selector("isMiner(address)"));
+        assert_eq!(SELECTOR_IS_FINALIZER, selector("isFinalizer(address)"));
+        assert_eq!(SELECTOR_MINERS, selector("miners()"));
+        assert_eq!(SELECTOR_FINALIZERS, selector("finalizers()"));
+        assert_eq!(SELECTOR_ERROR, selector("Error(string)"));
+    }
+
+    #[test]
+    fn decodes_arguments() {
+        let args = encode(&[Token::Uint(U256::from(4)), Token::Uint(U256::from(26000))]);
+        assert_eq!(decode_u64(&args, 0, 2).unwrap(), 4);
+        assert_eq!(decode_u64(&args, 1, 2).unwrap(), 26000);
+        assert!(decode_u64(&args[..40], 1, 2).is_err());
+
+        let too_big = encode(&[Token::Uint(U256::from(u64::MAX) + 1)]);
+        assert!(decode_u64(&too_big, 0, 1).is_err());
+
+        let address = H160::repeat_byte(0x11);
+        assert_eq!(
+            decode_address(&encode(&[Token::Address(address)])).unwrap(),
+            address
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! The EVM of this chain, as a quantum portal network
+
+use frame_support::traits::Get;
+use frame_system::offchain::SubmitTransaction;
+use pallet_evm::{ExitReason, FeeCalculator, Runner};
+use pallet_quantum_portal::{
+    chain_utils::{ChainRequestError, ChainRequestResult},
+    native_evm::{LegacyTransaction, NativeEvm},
+};
+use sp_core::{H160, H256, U256};
+use sp_std::prelude::*;
+
+use crate::{BlockGasLimit, Ethereum, Runtime, EVM};
+
+pub struct QpNativeEvm;
+
+impl NativeEvm for QpNativeEvm {
+    fn chain_id() -> u64 {
+        <Runtime as pallet_evm::Config>::ChainId::get()
+    }
+
+    fn call(
+        from: H160,
+        to: H160,
+        data: Vec<u8>,
+        value: U256,
+        estimate: bool,
+    ) -> ChainRequestResult<(Vec<u8>, U256)> {
+        let mut config = <Runtime as pallet_evm::Config>::config().clone();
+        config.estimate = estimate;
+        let info = <Runtime as pallet_evm::Config>::Runner::call(
+            from,
+            to,
+            data,
+            value,
+            BlockGasLimit::get().low_u64(),
+            None,
+            None,
+            None,
+            vec![],
+            false,
+            true,
+            &config,
+        )
+        .map_err(|e| {
+            log::warn!("Native EVM call rejected: {:?}", e.error);
+            ChainRequestError::NativeEvmCallFailed(vec![])
+        })?;
+        match info.exit_reason {
+            ExitReason::Succeed(_) => Ok((info.value, info.used_gas)),
+            _ => Err(ChainRequestError::NativeEvmCallFailed(info.value)),
+        }
+    }
+
+    fn nonce(address: H160) -> U256 {
+        let (account, _) = EVM::account_basic(&address);
+        account.nonce
+    }
+
+    fn gas_price() -> U256 {
+        let (gas_price, _) = <Runtime as pallet_evm::Config>::FeeCalculator::min_gas_price();
+        gas_price
+    }
+
+    fn submit_transaction(tx: LegacyTransaction) -> ChainRequestResult<()> {
+        let call = pallet_ethereum::Call::<Runtime>::transact {
+            transaction: pallet_ethereum::Transaction::Legacy(tx),
+        };
+        SubmitTransaction::<Runtime, pallet_ethereum::Call<Runtime>>::submit_unsigned_transaction(
+            call.into(),
+        )
+        .map_err(|_| ChainRequestError::NativeEvmSubmissionFailed)
+    }
+
+    fn transaction_succeeded(tx_hash: H256) -> Option<bool> {
+        let statuses = Ethereum::current_transaction_statuses()?;
+        let index = statuses
+            .iter()
+            .position(|status| status.transaction_hash == tx_hash)?;
+        let status_code = match Ethereum::current_receipts()?.get(index)? {
+            pallet_ethereum::Receipt::Legacy(r)
+            | pallet_ethereum::Receipt::EIP2930(r)
+            | pallet_ethereum::Receipt::EIP1559(r) => r.status_code,
+        };
+        Some(status_code == 1)
+    }
+}